@@ -1,7 +1,6 @@
 use std::ops::{Index, IndexMut};
 
 /// Identifiers which can be assigned to a graphic character set.
-#[allow(dead_code)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum CharsetIndex {
     /// Default set, is designated as ASCII at startup.
@@ -17,11 +16,27 @@ impl Default for CharsetIndex {
     }
 }
 
+impl CharsetIndex {
+    /// Get the index designated by the first intermediate of an SCS sequence.
+    pub fn from_primitive(intermediate: u8) -> Option<Self> {
+        match intermediate {
+            b'(' => Some(CharsetIndex::G0),
+            b')' => Some(CharsetIndex::G1),
+            b'*' => Some(CharsetIndex::G2),
+            b'+' => Some(CharsetIndex::G3),
+            _ => None,
+        }
+    }
+}
+
 /// Standard or common character sets which can be designated as G0-G3.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum StandardCharset {
-    Ascii,   // Standard ASCII characters.
-    Special, // Special characters and line drawings.
+    Ascii,           // Standard ASCII characters.
+    Special,         // Special characters and line drawings.
+    Uk,              // ASCII with the pound sign in place of the number sign.
+    DecSupplemental, // DEC multinational characters.
+    DecTechnical,    // DEC technical and mathematical symbols.
 }
 
 impl Default for StandardCharset {
@@ -31,23 +46,130 @@ impl Default for StandardCharset {
 }
 
 impl StandardCharset {
+    /// Get the charset designated by the final bytes of an SCS sequence.
+    pub fn from_primitive(intermediate: Option<&u8>, byte: u8) -> Option<Self> {
+        match (intermediate, byte) {
+            (None, b'B') => Some(Self::Ascii),
+            (None, b'0') => Some(Self::Special),
+            (None, b'A') => Some(Self::Uk),
+            (None, b'<') | (Some(b'%'), b'5') => Some(Self::DecSupplemental),
+            (None, b'>') => Some(Self::DecTechnical),
+            _ => None,
+        }
+    }
+
     /// Switch/Map character to the active charset. Ascii is the common case and
     /// for that we want to do as little as possible.
     #[inline]
     pub fn map(self, c: char) -> char {
         match self {
             StandardCharset::Ascii => c,
+            StandardCharset::Uk => match c {
+                '#' => '£',
+                _ => c,
+            },
+            StandardCharset::DecSupplemental => match c {
+                '(' => '¤',
+                'W' => 'Œ',
+                ']' => 'Ÿ',
+                'w' => 'œ',
+                '}' => 'ÿ',
+                // The rest of the set matches the upper half of Latin-1.
+                '!'..='~' => std::char::from_u32(c as u32 + 0x80).unwrap_or(c),
+                _ => c,
+            },
+            StandardCharset::DecTechnical => match c {
+                '!' => '⎷',
+                '"' => '┌',
+                '#' => '─',
+                '$' => '⌠',
+                '%' => '⌡',
+                '&' => '│',
+                '\'' => '⎡',
+                '(' => '⎣',
+                ')' => '⎤',
+                '*' => '⎦',
+                '+' => '⎛',
+                ',' => '⎝',
+                '-' => '⎞',
+                '.' => '⎠',
+                '/' => '⎨',
+                '0' => '⎬',
+                '<' => '≤',
+                '=' => '≠',
+                '>' => '≥',
+                '?' => '∫',
+                '@' => '∴',
+                'A' => '∝',
+                'B' => '∞',
+                'C' => '÷',
+                'D' => 'Δ',
+                'E' => '∇',
+                'F' => 'Φ',
+                'G' => 'Γ',
+                'H' => '∼',
+                'I' => '≃',
+                'J' => 'Θ',
+                'K' => '×',
+                'L' => 'Λ',
+                'M' => '⇔',
+                'N' => '⇒',
+                'O' => '≡',
+                'P' => 'Π',
+                'Q' => 'Ψ',
+                'S' => 'Σ',
+                'V' => '√',
+                'W' => 'Ω',
+                'X' => 'Ξ',
+                'Y' => 'Υ',
+                'Z' => '⊂',
+                '[' => '⊃',
+                '\\' => '∩',
+                ']' => '∪',
+                '^' => '∧',
+                '_' => '∨',
+                '`' => '¬',
+                'a' => 'α',
+                'b' => 'β',
+                'c' => 'χ',
+                'd' => 'δ',
+                'e' => 'ε',
+                'f' => 'φ',
+                'g' => 'γ',
+                'h' => 'η',
+                'i' => 'ι',
+                'j' => 'θ',
+                'k' => 'κ',
+                'l' => 'λ',
+                'n' => 'ν',
+                'o' => '∂',
+                'p' => 'π',
+                'q' => 'ψ',
+                'r' => 'ρ',
+                's' => 'σ',
+                't' => 'τ',
+                'v' => 'ƒ',
+                'w' => 'ω',
+                'x' => 'ξ',
+                'y' => 'υ',
+                'z' => 'ζ',
+                '{' => '←',
+                '|' => '↑',
+                '}' => '→',
+                '~' => '↓',
+                _ => c,
+            },
             StandardCharset::Special => match c {
                 '`' => '◆',
                 'a' => '▒',
-                'b' => '\t',
-                'c' => '\u{000c}',
-                'd' => '\r',
-                'e' => '\n',
+                'b' => '\u{2409}',
+                'c' => '\u{240c}',
+                'd' => '\u{240d}',
+                'e' => '\u{240a}',
                 'f' => '°',
                 'g' => '±',
                 'h' => '\u{2424}',
-                'i' => '\u{000b}',
+                'i' => '\u{240b}',
                 'j' => '┘',
                 'k' => '┐',
                 'l' => '┌',
@@ -75,19 +197,87 @@ impl StandardCharset {
     }
 }
 
+/// The G0-G3 character sets, along with the shift state that selects which
+/// of them is used to map printed characters.
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
-pub struct CharsetList([StandardCharset; 4]);
+pub struct CharsetList {
+    charsets: [StandardCharset; 4],
+    /// Charset invoked into GL by the last locking shift.
+    active: CharsetIndex,
+    /// Charset used for the next character only, set by a single shift.
+    single_shift: Option<CharsetIndex>,
+}
+
+impl CharsetList {
+    /// Invoke `index` into GL until the next locking shift (SI, SO, LS2, LS3).
+    pub fn set_active(&mut self, index: CharsetIndex) {
+        self.active = index;
+    }
+
+    /// Use `index` to map the next character only (SS2, SS3).
+    pub fn set_single_shift(&mut self, index: CharsetIndex) {
+        self.single_shift = Some(index);
+    }
+
+    /// Map a printed character through the currently invoked charset.
+    #[inline]
+    pub fn map(&mut self, c: char) -> char {
+        let index = self.single_shift.take().unwrap_or(self.active);
+        self[index].map(c)
+    }
+}
 
 impl Index<CharsetIndex> for CharsetList {
     type Output = StandardCharset;
 
     fn index(&self, index: CharsetIndex) -> &Self::Output {
-        &self.0[index as usize]
+        &self.charsets[index as usize]
     }
 }
 
 impl IndexMut<CharsetIndex> for CharsetList {
     fn index_mut(&mut self, index: CharsetIndex) -> &mut Self::Output {
-        &mut self.0[index as usize]
+        &mut self.charsets[index as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ansi::Processor,
+        screen::Screen,
+        util::point::P,
+    };
+
+    fn line(input: &[u8]) -> String {
+        let mut screen = Screen::new(P(10, 1));
+        let mut processor = Processor::default();
+        processor.advance(input, &mut screen, &mut Vec::new());
+        (0..10).map(|x| screen.cell(P(x, 0)).unwrap().ch.unwrap_or(' ')).collect()
+    }
+
+    #[test]
+    fn test_dialog_line_drawing() {
+        // ncurses designates the line drawing set into G0 for smacs/rmacs.
+        assert_eq!(line(b"\x1b(0lqqk\x1b(Bok\x1b(0x"), "┌──┐ok│   ");
+    }
+
+    #[test]
+    fn test_mc_line_drawing() {
+        // mc under TERM=screen designates it into G1 and shifts with SO/SI.
+        assert_eq!(line(b"\x1b(B\x1b)0\x0etqu\x0fmc\x0ej"), "├─┤mc┘    ");
+    }
+
+    #[test]
+    fn test_single_and_locking_shifts() {
+        assert_eq!(
+            line(b"\x1b*A\x1b+>\x1bN#\x1bO{#\x1bn#\x1bo{\x0f#"),
+            "£←#£←#    ",
+        );
+    }
+
+    #[test]
+    fn test_supplemental_sets() {
+        assert_eq!(line(b"\x1b(%5!W\x1b(<w"), "¡Œœ       ");
     }
 }
@@ -152,6 +152,156 @@ impl fmt::Display for RgbColor {
     }
 }
 
+impl RgbColor {
+    /// Get the default value of an indexed color, following xterm's palette.
+    pub fn from_index(index: u8) -> Self {
+        const ANSI: [u32; 16] = [
+            0x000000, 0xcd0000, 0x00cd00, 0xcdcd00,
+            0x0000ee, 0xcd00cd, 0x00cdcd, 0xe5e5e5,
+            0x7f7f7f, 0xff0000, 0x00ff00, 0xffff00,
+            0x5c5cff, 0xff00ff, 0x00ffff, 0xffffff,
+        ];
+        const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+        match index {
+            0..=15 => {
+                let n = ANSI[index as usize];
+                Self {
+                    r: (n >> 16) as u8,
+                    g: (n >> 8) as u8,
+                    b: n as u8,
+                }
+            },
+            16..=231 => {
+                let n = index - 16;
+                Self {
+                    r: CUBE[(n / 36) as usize],
+                    g: CUBE[(n / 6 % 6) as usize],
+                    b: CUBE[(n % 6) as usize],
+                }
+            },
+            _ => {
+                let level = 8 + (index - 232) * 10;
+                Self { r: level, g: level, b: level }
+            },
+        }
+    }
+}
+
+impl RgbColor {
+    fn distance(&self, other: &Self) -> u32 {
+        let dr = self.r as i32 - other.r as i32;
+        let dg = self.g as i32 - other.g as i32;
+        let db = self.b as i32 - other.b as i32;
+        (dr * dr + dg * dg + db * db) as u32
+    }
+
+    /// Get the index of the nearest color in the 6x6x6 cube or the
+    /// grayscale ramp of the 256 color palette.
+    ///
+    /// The first 16 colors are skipped since hosts often customize them.
+    pub fn to_indexed256(self) -> u8 {
+        fn cube_level(v: u8) -> u8 {
+            match v {
+                0..=47 => 0,
+                48..=114 => 1,
+                _ => (v - 35) / 40,
+            }
+        }
+        let (r, g, b) = (cube_level(self.r), cube_level(self.g), cube_level(self.b));
+        let cube = 16 + 36 * r + 6 * g + b;
+
+        let average = (self.r as u32 + self.g as u32 + self.b as u32) / 3;
+        let gray = if average > 238 {
+            255
+        } else {
+            232 + (average.saturating_sub(3) / 10) as u8
+        };
+
+        if self.distance(&Self::from_index(gray)) < self.distance(&Self::from_index(cube)) {
+            gray
+        } else {
+            cube
+        }
+    }
+
+    /// Get the index of the nearest of the 16 ANSI colors.
+    pub fn to_ansi16(self) -> u8 {
+        (0..16)
+            .min_by_key(|i| self.distance(&Self::from_index(*i)))
+            .unwrap()
+    }
+}
+
+/// Number of colors that the host terminal can display.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum ColorDepth {
+    /// The 8 ANSI colors and their bright variants.
+    Ansi16,
+    /// The 256 color xterm palette.
+    #[default]
+    Indexed256,
+    /// 24-bit RGB colors.
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Detect the color depth of the host from the `COLORTERM` and `TERM`
+    /// environment variables.
+    pub fn detect() -> Self {
+        Self::from_env(
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if let Some("truecolor") | Some("24bit") = colorterm {
+            return Self::TrueColor;
+        }
+        match term {
+            Some(term) if term.ends_with("-direct") => Self::TrueColor,
+            Some(term) if term.contains("256color") => Self::Indexed256,
+            Some(_) | None => Self::Ansi16,
+        }
+    }
+
+    /// Get the nearest color that can be displayed at this depth.
+    pub fn downgrade(&self, color: Color) -> Color {
+        match (self, color) {
+            (Self::TrueColor, color) => color,
+            (Self::Indexed256, Color::Rgb(rgb)) => Color::Indexed(rgb.to_indexed256()),
+            (Self::Ansi16, Color::Rgb(rgb)) => Color::Indexed(rgb.to_ansi16()),
+            (Self::Ansi16, Color::Indexed(idx)) if idx >= 16 => {
+                Color::Indexed(RgbColor::from_index(idx).to_ansi16())
+            },
+            (_, color) => color,
+        }
+    }
+}
+
+impl str::FromStr for ColorDepth {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "16" => Ok(Self::Ansi16),
+            "256" => Ok(Self::Indexed256),
+            "truecolor" | "24bit" => Ok(Self::TrueColor),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ColorDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ansi16 => write!(f, "16"),
+            Self::Indexed256 => write!(f, "256"),
+            Self::TrueColor => write!(f, "truecolor"),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum SpecialColor {
     Foreground,
@@ -161,7 +311,7 @@ pub enum SpecialColor {
 
 #[cfg(test)]
 mod tests {
-    use crate::ansi::color::{Color, RgbColor};
+    use crate::ansi::color::{Color, ColorDepth, RgbColor};
 
     #[test]
     fn test_color_parsing() {
@@ -187,4 +337,36 @@ mod tests {
 
         assert_eq!(Ok(Color::Indexed(23)), "23".parse());
     }
+
+    #[test]
+    fn test_color_depth_detection() {
+        assert_eq!(ColorDepth::from_env(Some("truecolor"), Some("xterm")), ColorDepth::TrueColor);
+        assert_eq!(ColorDepth::from_env(None, Some("xterm-direct")), ColorDepth::TrueColor);
+        assert_eq!(ColorDepth::from_env(None, Some("screen-256color")), ColorDepth::Indexed256);
+        assert_eq!(ColorDepth::from_env(None, Some("linux")), ColorDepth::Ansi16);
+        assert_eq!(ColorDepth::from_env(None, None), ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn test_rgb_to_indexed256() {
+        assert_eq!(RgbColor { r: 0, g: 0, b: 0 }.to_indexed256(), 16);
+        assert_eq!(RgbColor { r: 255, g: 255, b: 255 }.to_indexed256(), 231);
+        assert_eq!(RgbColor { r: 255, g: 0, b: 0 }.to_indexed256(), 196);
+        assert_eq!(RgbColor { r: 0x80, g: 0x80, b: 0x80 }.to_indexed256(), 244);
+        assert_eq!(RgbColor { r: 0x5f, g: 0x87, b: 0xaf }.to_indexed256(), 67);
+        for index in 16..=255 {
+            assert_eq!(RgbColor::from_index(index).to_indexed256(), index);
+        }
+    }
+
+    #[test]
+    fn test_downgrade() {
+        let orange = Color::Rgb(RgbColor { r: 0xff, g: 0x80, b: 0x00 });
+        assert_eq!(ColorDepth::TrueColor.downgrade(orange), orange);
+        assert_eq!(ColorDepth::Indexed256.downgrade(orange), Color::Indexed(208));
+        assert_eq!(ColorDepth::Ansi16.downgrade(orange), Color::Yellow);
+        assert_eq!(ColorDepth::Ansi16.downgrade(Color::Indexed(21)), Color::Blue);
+        assert_eq!(ColorDepth::Ansi16.downgrade(Color::BrightCyan), Color::BrightCyan);
+        assert_eq!(ColorDepth::Ansi16.downgrade(Color::Foreground), Color::Foreground);
+    }
 }
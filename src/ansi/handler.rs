@@ -1,3 +1,4 @@
+use std::ops::Range;
 use crate::ansi::{
     charset::{
         CharsetIndex,
@@ -9,7 +10,10 @@ use crate::ansi::{
     },
     sgr::Attribute,
 };
-use crate::util::Point;
+use crate::{
+    grid::LineSize,
+    util::Point,
+};
 use super::{
     CursorStyle,
     ClipboardType,
@@ -26,6 +30,15 @@ pub trait Handler {
     /// Get the current cursor position.
     fn cursor(&self) -> &Point;
 
+    /// Get the size of a single cell in pixels.
+    fn cell_size(&self) -> &Point;
+
+    /// Get whether a terminal mode is set.
+    fn mode(&self, mode: TerminalMode) -> bool;
+
+    /// Get the lines spanned by the scrolling region.
+    fn scrolling_region(&self) -> &Range<usize>;
+
     /// Print a character to the screen.
     fn put_char(&mut self, ch: char);
 
@@ -70,9 +83,15 @@ pub trait Handler {
     /// Do an alignment test.
     fn do_alignment_test(&mut self);
 
+    /// Set the size of the characters in the current line.
+    fn set_line_size(&mut self, size: LineSize);
+
     /// Set the active charset.
     fn set_active_charset(&mut self, index: CharsetIndex);
 
+    /// Use the charset at `index` for the next character only.
+    fn set_single_shift(&mut self, index: CharsetIndex);
+
     /// Configure the active charset.
     fn configure_charset(&mut self, index: CharsetIndex, charset: StandardCharset);
 
@@ -191,4 +210,7 @@ pub trait Handler {
 
     /// Reset the terminal state.
     fn reset_state(&mut self);
+
+    /// Reset the subset of the terminal state defined by DECSTR.
+    fn soft_reset_state(&mut self);
 }
@@ -4,6 +4,7 @@ pub mod color;
 pub mod handler;
 pub mod processor;
 pub mod renderer;
+pub mod report;
 pub mod sgr;
 
 pub use {
@@ -18,6 +19,7 @@ pub use {
     },
     color::{
         Color,
+        ColorDepth,
         RgbColor,
         SpecialColor,
     },
@@ -216,7 +218,7 @@ impl ClipboardType {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum TerminalMode {
     CursorKeys,
     ColumnMode,
@@ -235,6 +237,7 @@ pub enum TerminalMode {
     AlternateScroll,
     SwapScreenAndSetRestoreCursor,
     BracketedPaste,
+    SynchronizedOutput,
 }
 
 impl TerminalMode {
@@ -261,6 +264,7 @@ impl TerminalMode {
             (1007, true) => Some(Self::AlternateScroll),
             (1049, true) => Some(Self::SwapScreenAndSetRestoreCursor),
             (2004, true) => Some(Self::BracketedPaste),
+            (2026, true) => Some(Self::SynchronizedOutput),
 
             (4, false) => Some(Self::Insert),
             (20, false) => Some(Self::LineFeedNewLine),
@@ -1,11 +1,14 @@
 use std::str;
 use log::trace;
 
+use crate::grid::LineSize;
+
 use crate::ansi::{
     color::{
         SpecialColor,
         RgbColor,
     },
+    report::Report,
     sgr,
     C0,
     charset::{
@@ -105,16 +108,27 @@ impl<'a> ::vte::Perform for Performer<'a> {
 
     fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
         trace!("[processor] esc_dispatch: intermediates={:?}, ignore={:?}, byte={:?}", intermediates, _ignore, byte);
+        // Designate a charset to G0-G3.
+        if let Some(index) = intermediates.first().and_then(|i| CharsetIndex::from_primitive(*i)) {
+            match StandardCharset::from_primitive(intermediates.get(1), byte) {
+                Some(charset) => self.handler.configure_charset(index, charset),
+                None => {
+                    // TODO implement unhandled.
+                },
+            }
+            return;
+        }
+
         match (byte, intermediates.get(0)) {
-            (b'0', Some(b'(')) => self.handler.configure_charset(CharsetIndex::G0, StandardCharset::Ascii),
-            (b'0', Some(b')')) => self.handler.configure_charset(CharsetIndex::G1, StandardCharset::Ascii),
+            (b'3', Some(b'#')) => self.handler.set_line_size(LineSize::DoubleHeightTop),
+            (b'4', Some(b'#')) => self.handler.set_line_size(LineSize::DoubleHeightBottom),
+            (b'5', Some(b'#')) => self.handler.set_line_size(LineSize::Normal),
+            (b'6', Some(b'#')) => self.handler.set_line_size(LineSize::DoubleWidth),
             (b'7', None) => self.handler.save_cursor_position(),
             (b'8', None) => self.handler.restore_cursor_position(),
             (b'8', Some(b'#')) => self.handler.do_alignment_test(),
             (b'=', None) => self.handler.set_keypad_application_mode(),
             (b'>', None) => self.handler.unset_keypad_application_mode(),
-            (b'B', Some(b'(')) => self.handler.configure_charset(CharsetIndex::G0, StandardCharset::Special),
-            (b'B', Some(b')')) => self.handler.configure_charset(CharsetIndex::G1, StandardCharset::Special),
             (b'D', None) => self.handler.put_lf(),
             (b'E', None) => {
                 self.handler.put_lf();
@@ -124,7 +138,11 @@ impl<'a> ::vte::Perform for Performer<'a> {
                 self.handler.cursor().x,
             ),
             (b'M', None) => self.handler.reverse_index(),
+            (b'N', None) => self.handler.set_single_shift(CharsetIndex::G2),
+            (b'O', None) => self.handler.set_single_shift(CharsetIndex::G3),
             (b'c', None) => self.handler.reset_state(),
+            (b'n', None) => self.handler.set_active_charset(CharsetIndex::G2),
+            (b'o', None) => self.handler.set_active_charset(CharsetIndex::G3),
             (b'\\', None) => {},
             _ => {
                 // TODO implement unhandled.
@@ -381,13 +399,31 @@ impl<'a> ::vte::Perform for Performer<'a> {
             }
         }
 
-        if ignore_intermediates || intermediates.len() > 1 {
+        if ignore_intermediates || intermediates.len() > 2 {
             return;
         }
 
         let handler = &mut self.handler;
         let writer = &mut self.writer;
 
+        macro_rules! report {
+            ($report:expr) => {{
+                let _ = $report.write(&**handler, &mut **writer);
+            }};
+        }
+
+        // Sequences with both a private marker and an intermediate.
+        if intermediates.len() == 2 {
+            match (action, intermediates) {
+                // Request private mode
+                ('p', [b'?', b'$']) => {
+                    report!(Report::Mode { number: get_arg!(idx: 0, def: 0), private: true });
+                },
+                _ => unhandled!(),
+            }
+            return;
+        }
+
         match (action, intermediates.get(0)) {
             // Insert blank lines
             ('@', None) => {
@@ -515,9 +551,7 @@ impl<'a> ::vte::Perform for Performer<'a> {
             // Primary device attribute
             ('c', None) => {
                 match get_arg!(idx: 0, def: 0) {
-                    0 => {
-                        let _ = write!(writer, "\x1b[?1;2c");
-                    },
+                    0 => report!(Report::PrimaryDeviceAttributes),
                     _ => unhandled!(),
                 }
             },
@@ -525,9 +559,7 @@ impl<'a> ::vte::Perform for Performer<'a> {
             // Secondary device attribute
             ('c', Some(b'>')) => {
                 match get_arg!(idx: 0, def: 0) {
-                    0 => {
-                        let _ = write!(writer, "\x1b[>84;0;0c");
-                    },
+                    0 => report!(Report::SecondaryDeviceAttributes),
                     _ => unhandled!(),
                 }
             },
@@ -603,15 +635,8 @@ impl<'a> ::vte::Perform for Performer<'a> {
             // Report device status
             ('n', None) => {
                 match get_arg!(idx: 0, def: 0) {
-                    5 => {
-                        // respond with b"\x1b[0n"
-                        let _ = write!(writer, "\x1b[0n");
-                    },
-                    6 => {
-                        // respond with format!("\x1b[{};{}R", cursor_line, cursor_col)
-                        let cur = handler.cursor();
-                        let _ = write!(writer, "\x1b[{};{}R", cur.y, cur.x);
-                    },
+                    5 => report!(Report::DeviceStatus),
+                    6 => report!(Report::CursorPosition),
                     _ => unhandled!(),
                 }
             },
@@ -634,14 +659,22 @@ impl<'a> ::vte::Perform for Performer<'a> {
             },
 
             // Report program name and version
-            // ('q', Some(b'>')) => {
-                // match get_arg!(idx: 0, def: 0) {
-                    // 0 => {
-                        // respond with b"\x1bP>|{program_name} {version}\x1b\\"
-                    // },
-                    // _ => unhandled!(),
-                // }
-            // },
+            ('q', Some(b'>')) => {
+                match get_arg!(idx: 0, def: 0) {
+                    0 => report!(Report::Version),
+                    _ => unhandled!(),
+                }
+            },
+
+            // Soft terminal reset
+            ('p', Some(b'!')) => {
+                handler.soft_reset_state();
+            },
+
+            // Request ANSI mode
+            ('p', Some(b'$')) => {
+                report!(Report::Mode { number: get_arg!(idx: 0, def: 0), private: false });
+            },
 
             // Set scrolling region
             ('r', None) => {
@@ -655,9 +688,13 @@ impl<'a> ::vte::Perform for Performer<'a> {
                 handler.save_cursor_position();
             },
 
-            // Save/restore title
+            // Window reports and save/restore title
             ('t', None) => {
                 match get_arg!(idx: 0, def: 0) {
+                    14 => report!(Report::TextAreaPixels),
+                    16 => report!(Report::CellPixels),
+                    18 => report!(Report::TextAreaChars),
+                    19 => report!(Report::ScreenChars),
                     22 => handler.save_title(),
                     23 => handler.restore_title(),
                     _ => unhandled!(),
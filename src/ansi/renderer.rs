@@ -1,90 +1,247 @@
 use {
     std::io,
-    log::warn,
     crate::ansi::{
+        sgr::Attribute,
         Attributes,
         Color,
+        ColorDepth,
         Flags,
     },
 };
 
-pub struct Renderer<'a, W: io::Write>(pub &'a mut W);
+/// Attributes that set each flag.
+const FLAG_ATTRIBUTES: [(Flags, Attribute); 9] = [
+    (Flags::BOLD, Attribute::Bold),
+    (Flags::DIM, Attribute::Dim),
+    (Flags::ITALIC, Attribute::Italic),
+    (Flags::UNDERLINE, Attribute::Underline),
+    (Flags::BLINK_SLOW, Attribute::BlinkSlow),
+    (Flags::BLINK_FAST, Attribute::BlinkFast),
+    (Flags::INVERSE, Attribute::Inverse),
+    (Flags::HIDDEN, Attribute::Hidden),
+    (Flags::STRIKEOUT, Attribute::Strike),
+];
+
+/// Flags that are cancelled by a parameter that only affects them.
+const CANCEL_ATTRIBUTES: [(Flags, Attribute); 5] = [
+    (Flags::ITALIC, Attribute::CancelItalic),
+    (Flags::UNDERLINE, Attribute::CancelUnderline),
+    (Flags::INVERSE, Attribute::CancelInverse),
+    (Flags::HIDDEN, Attribute::CancelHidden),
+    (Flags::STRIKEOUT, Attribute::CancelStrike),
+];
+
+/// Flags that share their cancel parameter, the ones that remain set have to
+/// be set again after cancelling.
+const SHARED_FLAGS: [(Flags, Flags, Attribute); 2] = [
+    (Flags::BOLD, Flags::DIM, Attribute::CancelBoldDim),
+    (Flags::BLINK_SLOW, Flags::BLINK_FAST, Attribute::CancelBlink),
+];
+
+/// Renders attributes as SGR sequences for a host with the given color depth.
+pub struct Renderer<'a, W: io::Write>(pub &'a mut W, pub ColorDepth);
 
 impl<'a, W: io::Write> Renderer<'a, W> {
+    /// Render attributes when the host attributes are unknown.
     pub fn render_attributes(&mut self, attributes: &Attributes) -> io::Result<()> {
-        // Start of CSI sequence
-        write!(self.0, "\x1b[0")?;
+        let sgr = self.reset_sequence(attributes);
+        self.render_sequence(&sgr)
+    }
 
-        // Render flags
-        if attributes.flags.contains(Flags::BOLD) {
-            write!(self.0, ";1")?;
-        }
-        if attributes.flags.contains(Flags::DIM) {
-            write!(self.0, ";2")?;
+    /// Render the shortest sequence that changes the host attributes from
+    /// `previous` to `next`. Nothing is written when they look the same.
+    pub fn render_transition(&mut self, previous: &Attributes, next: &Attributes) -> io::Result<()> {
+        let diff = self.diff_sequence(previous, next);
+        if diff.is_empty() {
+            return Ok(());
         }
-        if attributes.flags.contains(Flags::ITALIC) {
-            write!(self.0, ";3")?;
+        let reset = self.reset_sequence(next);
+        if self.encoded_len(&reset) < self.encoded_len(&diff) {
+            self.render_sequence(&reset)
+        } else {
+            self.render_sequence(&diff)
         }
-        if attributes.flags.contains(Flags::UNDERLINE) {
-            write!(self.0, ";4")?;
+    }
+
+    /// Attributes that reset the host and then set `attributes`.
+    fn reset_sequence(&self, attributes: &Attributes) -> Vec<Attribute> {
+        let mut sgr = vec![Attribute::Reset];
+        sgr.extend(Self::set_flags(attributes.flags));
+        let fg = self.host_color(attributes.fg, Color::Foreground);
+        if fg != Color::Foreground {
+            sgr.push(Attribute::Foreground(fg));
         }
-        if attributes.flags.contains(Flags::BLINK_SLOW) {
-            write!(self.0, ";5")?;
+        let bg = self.host_color(attributes.bg, Color::Background);
+        if bg != Color::Background {
+            sgr.push(Attribute::Background(bg));
         }
-        if attributes.flags.contains(Flags::BLINK_FAST) {
-            write!(self.0, ";6")?;
+        sgr
+    }
+
+    /// Attributes that change the host from `previous` to `next`.
+    fn diff_sequence(&self, previous: &Attributes, next: &Attributes) -> Vec<Attribute> {
+        let mut sgr = Vec::new();
+        let mut set = Flags::empty();
+        for &(first, second, cancel) in &SHARED_FLAGS {
+            let flags = first | second;
+            let (old, new) = (previous.flags & flags, next.flags & flags);
+            if !(old - new).is_empty() {
+                sgr.push(cancel);
+                set |= new;
+            } else {
+                set |= new - old;
+            }
         }
-        if attributes.flags.contains(Flags::INVERSE) {
-            write!(self.0, ";7")?;
+        for &(flag, cancel) in &CANCEL_ATTRIBUTES {
+            if previous.flags.contains(flag) && !next.flags.contains(flag) {
+                sgr.push(cancel);
+            }
         }
-        if attributes.flags.contains(Flags::HIDDEN) {
-            write!(self.0, ";8")?;
+        set |= next.flags - previous.flags;
+        sgr.extend(Self::set_flags(set));
+
+        let fg = self.host_color(next.fg, Color::Foreground);
+        if self.host_color(previous.fg, Color::Foreground) != fg {
+            sgr.push(Attribute::Foreground(fg));
         }
-        if attributes.flags.contains(Flags::STRIKEOUT) {
-            write!(self.0, ";9")?;
+        let bg = self.host_color(next.bg, Color::Background);
+        if self.host_color(previous.bg, Color::Background) != bg {
+            sgr.push(Attribute::Background(bg));
         }
+        sgr
+    }
+
+    /// Attributes that set the given flags.
+    fn set_flags(flags: Flags) -> impl Iterator<Item = Attribute> {
+        FLAG_ATTRIBUTES.iter()
+            .filter(move |(flag, _)| flags.contains(*flag))
+            .map(|&(_, attribute)| attribute)
+    }
 
-        // Render foreground color
-        match attributes.fg {
-            Color::Black => write!(self.0, ";30")?,
-            Color::Red => write!(self.0, ";31")?,
-            Color::Green => write!(self.0, ";32")?,
-            Color::Yellow => write!(self.0, ";33")?,
-            Color::Blue => write!(self.0, ";34")?,
-            Color::Magenta => write!(self.0, ";35")?,
-            Color::Cyan => write!(self.0, ";36")?,
-            Color::White => write!(self.0, ";37")?,
-            Color::Rgb(c) => write!(self.0, ";38;2;{};{};{}", c.r, c.g, c.b)?,
-            Color::Indexed(c) => write!(self.0, ";38;5;{}", c)?,
-            Color::Foreground => write!(self.0, ";39")?,
-            c => {
-                warn!("invalid foreground color: {:?}", c);
-                write!(self.0, ";30")?;
-            },
+    /// The color as the host displays it. Special colors can't be set on the
+    /// host, so they all fall back to the host's default color.
+    fn host_color(&self, color: Color, default: Color) -> Color {
+        match self.1.downgrade(color) {
+            Color::Special(_) => default,
+            color => color,
         }
+    }
 
-        // Render background color
-        match attributes.bg {
-            Color::Black => write!(self.0, ";40")?,
-            Color::Red => write!(self.0, ";41")?,
-            Color::Green => write!(self.0, ";42")?,
-            Color::Yellow => write!(self.0, ";43")?,
-            Color::Blue => write!(self.0, ";44")?,
-            Color::Magenta => write!(self.0, ";45")?,
-            Color::Cyan => write!(self.0, ";46")?,
-            Color::White => write!(self.0, ";47")?,
-            Color::Rgb(c) => write!(self.0, ";48;2;{};{};{}", c.r, c.g, c.b)?,
-            Color::Indexed(c) => write!(self.0, ";48;5;{}", c)?,
-            Color::Background => write!(self.0, ";49")?,
-            c => {
-                warn!("invalid background color: {:?}", c);
-                write!(self.0, ";40")?;
-            },
+    fn encoded_len(&self, sgr: &[Attribute]) -> usize {
+        let mut buf = Vec::new();
+        let _ = Renderer(&mut buf, self.1).render_sequence(sgr);
+        buf.len()
+    }
+
+    fn render_sequence(&mut self, sgr: &[Attribute]) -> io::Result<()> {
+        write!(self.0, "\x1b[")?;
+        for (i, attribute) in sgr.iter().enumerate() {
+            if i > 0 {
+                write!(self.0, ";")?;
+            }
+            self.render_attribute(*attribute)?;
         }
+        write!(self.0, "m")
+    }
 
-        write!(self.0, "m")?;
+    fn render_attribute(&mut self, attribute: Attribute) -> io::Result<()> {
+        let code = match attribute {
+            Attribute::Reset => 0,
+            Attribute::Bold => 1,
+            Attribute::Dim => 2,
+            Attribute::Italic => 3,
+            Attribute::Underline => 4,
+            Attribute::BlinkSlow => 5,
+            Attribute::BlinkFast => 6,
+            Attribute::Inverse => 7,
+            Attribute::Hidden => 8,
+            Attribute::Strike => 9,
+            // 21 is double underline on most hosts, so bold is cancelled
+            // together with dim.
+            Attribute::CancelBold | Attribute::CancelBoldDim => 22,
+            Attribute::CancelItalic => 23,
+            Attribute::CancelUnderline => 24,
+            Attribute::CancelBlink => 25,
+            Attribute::CancelInverse => 27,
+            Attribute::CancelHidden => 28,
+            Attribute::CancelStrike => 29,
+            Attribute::Foreground(color) => return self.render_color(color, 30),
+            Attribute::Background(color) => return self.render_color(color, 40),
+        };
+        write!(self.0, "{}", code)
+    }
 
-        Ok(())
+    /// Render a color parameter, where `base` is 30 for the foreground and
+    /// 40 for the background.
+    fn render_color(&mut self, color: Color, base: u8) -> io::Result<()> {
+        match color {
+            Color::Indexed(c) if c < 8 => write!(self.0, "{}", base + c),
+            // Bright colors have short aixterm codes, which hosts limited to
+            // 16 colors also require.
+            Color::Indexed(c) if c < 16 => write!(self.0, "{}", base + 60 + c - 8),
+            Color::Indexed(c) => write!(self.0, "{};5;{}", base + 8, c),
+            Color::Rgb(c) => write!(self.0, "{};2;{};{};{}", base + 8, c.r, c.g, c.b),
+            Color::Special(_) => write!(self.0, "{}", base + 9),
+        }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::ansi::{
+        Attributes,
+        Color,
+        ColorDepth,
+        Flags,
+        RgbColor,
+    };
+    use super::Renderer;
+
+    fn transition(previous: &Attributes, next: &Attributes) -> String {
+        let mut buf = Vec::new();
+        Renderer(&mut buf, ColorDepth::TrueColor).render_transition(previous, next).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn attributes(flags: Flags, fg: Color, bg: Color) -> Attributes {
+        Attributes { fg, bg, flags }
+    }
+
+    #[test]
+    fn test_render_attributes() {
+        let mut buf = Vec::new();
+        let attrs = attributes(Flags::BOLD, Color::BrightRed, Color::Indexed(100));
+        Renderer(&mut buf, ColorDepth::TrueColor).render_attributes(&attrs).unwrap();
+        assert_eq!(buf, b"\x1b[0;1;91;48;5;100m");
+    }
+
+    #[test]
+    fn test_render_transition() {
+        let plain = Attributes::default();
+        assert_eq!(transition(&plain, &plain), "");
+
+        let bold = attributes(Flags::BOLD, Color::Foreground, Color::Background);
+        assert_eq!(transition(&plain, &bold), "\x1b[1m");
+        assert_eq!(transition(&bold, &plain), "\x1b[0m");
+
+        let inverse = attributes(Flags::BOLD | Flags::INVERSE, Color::Red, Color::Background);
+        let red = attributes(Flags::BOLD, Color::Red, Color::Background);
+        assert_eq!(transition(&inverse, &red), "\x1b[27m");
+
+        let dim = attributes(Flags::DIM | Flags::INVERSE, Color::Red, Color::Background);
+        assert_eq!(transition(&inverse, &dim), "\x1b[22;2m");
+
+        let rgb = Color::Rgb(RgbColor { r: 1, g: 2, b: 3 });
+        let bright = attributes(Flags::BOLD, Color::BrightWhite, rgb);
+        assert_eq!(transition(&red, &bright), "\x1b[97;48;2;1;2;3m");
+    }
+
+    #[test]
+    fn test_render_transition_downgraded() {
+        let mut buf = Vec::new();
+        let previous = attributes(Flags::empty(), Color::Cursor, Color::Background);
+        let next = attributes(Flags::empty(), Color::Foreground, Color::Background);
+        Renderer(&mut buf, ColorDepth::Ansi16).render_transition(&previous, &next).unwrap();
+        assert!(buf.is_empty());
+    }
+}
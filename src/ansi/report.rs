@@ -0,0 +1,170 @@
+use std::io;
+use super::{
+    Handler,
+    TerminalMode,
+};
+
+/// Name reported to applications that request the terminal version.
+pub const PROGRAM_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Version reported to applications that request the terminal version.
+pub const PROGRAM_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Reports that the application running in the terminal can request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Report {
+    /// Primary device attributes (`CSI c`).
+    PrimaryDeviceAttributes,
+    /// Secondary device attributes (`CSI > c`).
+    SecondaryDeviceAttributes,
+    /// Device status report (`CSI 5 n`).
+    DeviceStatus,
+    /// Cursor position report (`CSI 6 n`).
+    CursorPosition,
+    /// Mode report (`CSI Ps $ p` or `CSI ? Ps $ p`).
+    Mode {
+        number: i64,
+        private: bool,
+    },
+    /// Program name and version (`CSI > q`).
+    Version,
+    /// Text area size in pixels (`CSI 14 t`).
+    TextAreaPixels,
+    /// Cell size in pixels (`CSI 16 t`).
+    CellPixels,
+    /// Text area size in characters (`CSI 18 t`).
+    TextAreaChars,
+    /// Screen size in characters (`CSI 19 t`).
+    ScreenChars,
+}
+
+/// Values of the `Pm` parameter in a DECRPM reply.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ModeStatus {
+    NotRecognized = 0,
+    Set = 1,
+    Reset = 2,
+}
+
+impl Report {
+    /// Get the status of a mode as reported by DECRPM.
+    pub fn mode_status(handler: &dyn Handler, number: i64, private: bool) -> ModeStatus {
+        let intermediate = if private { Some(&b'?') } else { None };
+        match TerminalMode::from_primitive(intermediate, number) {
+            Some(mode) if handler.mode(mode) => ModeStatus::Set,
+            Some(_) => ModeStatus::Reset,
+            None => ModeStatus::NotRecognized,
+        }
+    }
+
+    /// Write the reply to this report.
+    pub fn write(&self, handler: &dyn Handler, writer: &mut dyn io::Write) -> io::Result<()> {
+        match *self {
+            Self::PrimaryDeviceAttributes => {
+                write!(writer, "\x1b[?1;2c")
+            },
+            Self::SecondaryDeviceAttributes => {
+                write!(writer, "\x1b[>84;0;0c")
+            },
+            Self::DeviceStatus => {
+                write!(writer, "\x1b[0n")
+            },
+            Self::CursorPosition => {
+                // The reported position is 1-based and, when origin mode is
+                // set, relative to the top of the scrolling region.
+                let cursor = handler.cursor();
+                let mut line = cursor.y;
+                if handler.mode(TerminalMode::Origin) {
+                    line = line.saturating_sub(handler.scrolling_region().start);
+                }
+                write!(writer, "\x1b[{};{}R", line + 1, cursor.x + 1)
+            },
+            Self::Mode { number, private } => {
+                let status = Self::mode_status(handler, number, private);
+                let marker = if private { "?" } else { "" };
+                write!(writer, "\x1b[{}{};{}$y", marker, number, status as u8)
+            },
+            Self::Version => {
+                write!(writer, "\x1bP>|{} {}\x1b\\", PROGRAM_NAME, PROGRAM_VERSION)
+            },
+            Self::TextAreaPixels => {
+                let size = handler.size();
+                let cell = handler.cell_size();
+                write!(writer, "\x1b[4;{};{}t", size.y * cell.y, size.x * cell.x)
+            },
+            Self::CellPixels => {
+                let cell = handler.cell_size();
+                write!(writer, "\x1b[6;{};{}t", cell.y, cell.x)
+            },
+            Self::TextAreaChars => {
+                let size = handler.size();
+                write!(writer, "\x1b[8;{};{}t", size.y, size.x)
+            },
+            Self::ScreenChars => {
+                let size = handler.size();
+                write!(writer, "\x1b[9;{};{}t", size.y, size.x)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ansi::Processor,
+        screen::Screen,
+        util::point::P,
+    };
+
+    fn reply(screen: &mut Screen, input: &[u8]) -> String {
+        let mut processor = Processor::default();
+        let mut output = Vec::new();
+        processor.advance(input, screen, &mut output);
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_cursor_position_report() {
+        let mut screen = Screen::new(P(10, 5));
+        assert_eq!(reply(&mut screen, b"\x1b[6n"), "\x1b[1;1R");
+        assert_eq!(reply(&mut screen, b"\x1b[3;4H\x1b[6n"), "\x1b[3;4R");
+    }
+
+    #[test]
+    fn test_cursor_position_report_origin_mode() {
+        let mut screen = Screen::new(P(10, 5));
+        assert_eq!(reply(&mut screen, b"\x1b[2;4r\x1b[?6h\x1b[2;3H\x1b[6n"), "\x1b[2;3R");
+        assert_eq!(reply(&mut screen, b"\x1b[?6l\x1b[6n"), "\x1b[1;1R");
+    }
+
+    #[test]
+    fn test_mode_report() {
+        let mut screen = Screen::new(P(10, 5));
+        assert_eq!(reply(&mut screen, b"\x1b[?2004$p"), "\x1b[?2004;2$y");
+        assert_eq!(reply(&mut screen, b"\x1b[?2004h\x1b[?2004$p"), "\x1b[?2004;1$y");
+        assert_eq!(reply(&mut screen, b"\x1b[?25$p"), "\x1b[?25;1$y");
+        assert_eq!(reply(&mut screen, b"\x1b[4$p"), "\x1b[4;2$y");
+        assert_eq!(reply(&mut screen, b"\x1b[?2026h\x1b[?2026$p"), "\x1b[?2026;1$y");
+        assert_eq!(reply(&mut screen, b"\x1b[?2026l\x1b[?2026$p"), "\x1b[?2026;2$y");
+        assert_eq!(reply(&mut screen, b"\x1b[?9999$p"), "\x1b[?9999;0$y");
+    }
+
+    #[test]
+    fn test_version_report() {
+        let mut screen = Screen::new(P(10, 5));
+        assert_eq!(
+            reply(&mut screen, b"\x1b[>q"),
+            format!("\x1bP>|terman {}\x1b\\", env!("CARGO_PKG_VERSION")),
+        );
+    }
+
+    #[test]
+    fn test_window_reports() {
+        let mut screen = Screen::new(P(80, 24));
+        screen.set_cell_size(P(8, 16));
+        assert_eq!(reply(&mut screen, b"\x1b[14t"), "\x1b[4;384;640t");
+        assert_eq!(reply(&mut screen, b"\x1b[16t"), "\x1b[6;16;8t");
+        assert_eq!(reply(&mut screen, b"\x1b[18t"), "\x1b[8;24;80t");
+        assert_eq!(reply(&mut screen, b"\x1b[19t"), "\x1b[9;24;80t");
+    }
+}
@@ -0,0 +1,153 @@
+use {
+    std::collections::VecDeque,
+    anyhow::{
+        anyhow,
+        Result,
+    },
+};
+
+/// Text copied from a pane or set by a command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasteBuffer {
+    pub name: String,
+    pub data: String,
+    /// Whether the buffer was named automatically. Only those buffers are
+    /// dropped past the limit.
+    pub automatic: bool,
+}
+
+impl PasteBuffer {
+    /// Get the start of the data on a single line, with control characters
+    /// escaped, for lists of buffers.
+    pub fn sample(&self, width: usize) -> String {
+        let mut sample = String::new();
+        for ch in self.data.chars() {
+            if sample.chars().count() >= width {
+                sample.push_str("...");
+                break;
+            }
+            match ch {
+                '\n' => sample.push_str("\\n"),
+                '\r' => sample.push_str("\\r"),
+                '\t' => sample.push_str("\\t"),
+                '\\' => sample.push_str("\\\\"),
+                ch if ch.is_control() => sample.push_str(&format!("\\{:03o}", ch as u32)),
+                ch => sample.push(ch),
+            }
+        }
+        sample
+    }
+}
+
+/// Paste buffers shared by all the sessions, the most recent first.
+#[derive(Clone, Debug, Default)]
+pub struct PasteBuffers {
+    buffers: VecDeque<PasteBuffer>,
+    /// Number used to name the next buffer.
+    next_number: usize,
+}
+
+impl PasteBuffers {
+    /// Add a buffer holding `data`, named after the number of buffers
+    /// added before it. The oldest automatically named buffers are dropped
+    /// when there are more than `limit`.
+    pub fn add(&mut self, data: String, limit: usize) -> &PasteBuffer {
+        let name = format!("buffer{}", self.next_number);
+        self.next_number += 1;
+        self.buffers.push_front(PasteBuffer {
+            name,
+            data,
+            automatic: true,
+        });
+        let mut automatic = 0;
+        self.buffers.retain(|buffer| {
+            automatic += buffer.automatic as usize;
+            !buffer.automatic || automatic <= limit.max(1)
+        });
+        &self.buffers[0]
+    }
+
+    /// Set buffer `name` to `data`, making it the most recent buffer.
+    pub fn set(&mut self, name: &str, data: String) {
+        self.buffers.retain(|buffer| buffer.name != name);
+        self.buffers.push_front(PasteBuffer {
+            name: name.to_owned(),
+            data,
+            automatic: false,
+        });
+    }
+
+    /// Get buffer `name`, or the most recent buffer.
+    pub fn get(&self, name: Option<&str>) -> Result<&PasteBuffer> {
+        match name {
+            Some(name) => self.buffers.iter()
+                .find(|buffer| buffer.name == name)
+                .ok_or_else(|| anyhow!("no buffer {}", name)),
+            None => self.buffers.front().ok_or_else(|| anyhow!("no buffers")),
+        }
+    }
+
+    /// Delete buffer `name`, or the most recent buffer.
+    pub fn delete(&mut self, name: Option<&str>) -> Result<PasteBuffer> {
+        let name = self.get(name)?.name.clone();
+        let index = self.buffers.iter().position(|buffer| buffer.name == name).unwrap();
+        Ok(self.buffers.remove(index).unwrap())
+    }
+
+    /// Get the buffers, the most recent first.
+    pub fn iter(&self) -> impl Iterator<Item = &PasteBuffer> {
+        self.buffers.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        PasteBuffer,
+        PasteBuffers,
+    };
+
+    fn names(buffers: &PasteBuffers) -> Vec<&str> {
+        buffers.iter().map(|buffer| buffer.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_add() {
+        let mut buffers = PasteBuffers::default();
+        assert_eq!(buffers.add("one".to_owned(), 3).name, "buffer0");
+        assert_eq!(buffers.add("two".to_owned(), 3).name, "buffer1");
+        assert_eq!(buffers.get(None).unwrap().data, "two");
+        buffers.set("notes", "three".to_owned());
+        for i in 0..3 {
+            buffers.add(i.to_string(), 3);
+        }
+        // Named buffers don't count towards the limit.
+        assert_eq!(names(&buffers), vec!["buffer4", "buffer3", "buffer2", "notes"]);
+    }
+
+    #[test]
+    fn test_set_and_delete() {
+        let mut buffers = PasteBuffers::default();
+        buffers.set("a", "1".to_owned());
+        buffers.add("2".to_owned(), 50);
+        buffers.set("a", "3".to_owned());
+        assert_eq!(names(&buffers), vec!["a", "buffer0"]);
+        assert_eq!(buffers.get(Some("a")).unwrap().data, "3");
+        assert_eq!(buffers.get(Some("b")).unwrap_err().to_string(), "no buffer b");
+
+        assert_eq!(buffers.delete(None).unwrap().name, "a");
+        assert_eq!(buffers.delete(Some("buffer0")).unwrap().data, "2");
+        assert_eq!(buffers.delete(None).unwrap_err().to_string(), "no buffers");
+    }
+
+    #[test]
+    fn test_sample() {
+        let buffer = PasteBuffer {
+            name: "buffer0".to_owned(),
+            data: "ls -l\n\x1b[A\tx".to_owned(),
+            automatic: true,
+        };
+        assert_eq!(buffer.sample(20), "ls -l\\n\\033[A\\tx");
+        assert_eq!(buffer.sample(4), "ls -...");
+    }
+}
@@ -0,0 +1,303 @@
+use {
+    std::{
+        env,
+        io::{
+            self,
+            Write,
+        },
+        os::unix::{
+            io::{
+                AsRawFd,
+                RawFd,
+            },
+            process::CommandExt,
+        },
+        path::Path,
+        process::{
+            Command,
+            Stdio,
+        },
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+    anyhow::{
+        anyhow,
+        Context,
+        Result,
+    },
+    log::trace,
+    termios::*,
+    tokio::{
+        io::AsyncReadExt,
+        net::UnixStream,
+        signal::unix::{
+            signal,
+            SignalKind,
+        },
+        sync::mpsc,
+        task,
+        time::delay_for,
+    },
+    crate::{
+        ansi::ColorDepth,
+        pty::Pty,
+        screen::renderer::MOUSE_OFF,
+        server::{
+            self,
+            protocol::{
+                read_message,
+                write_message,
+                ClientMessage,
+                ServerMessage,
+            },
+        },
+        util::Point,
+    },
+};
+
+/// Size given to sessions created from a client that isn't run in a
+/// terminal.
+const DEFAULT_SIZE: Point = Point { x: 80, y: 24 };
+
+/// How long to wait for a server that was just started to listen.
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time between attempts to connect to a server that is starting.
+const CONNECT_INTERVAL: Duration = Duration::from_millis(10);
+
+fn set_raw_terminal(fd: i32) -> io::Result<Termios> {
+    let old_tios = Termios::from_fd(fd)?;
+    let mut new_tios = old_tios;
+    // cfmakeraw(&mut new_tios);
+    new_tios.c_lflag &= !(ECHO | ICANON | IEXTEN | ISIG);
+    new_tios.c_iflag &= !(BRKINT | ICRNL | INPCK | ISTRIP | IXON);
+    new_tios.c_cflag &= !(CSIZE | PARENB);
+    new_tios.c_cflag |= CS8;
+    new_tios.c_oflag &= !OPOST;
+
+    // new_tios.c_iflag = ICRNL|IXANY;
+    // new_tios.c_oflag = OPOST|ONLCR;
+    // new_tios.c_cflag = CREAD|CS8|HUPCL;
+    new_tios.c_cc[VMIN] = 1;
+    new_tios.c_cc[VTIME] = 0;
+    cfsetispeed(&mut new_tios, cfgetispeed(&old_tios))?;
+    cfsetospeed(&mut new_tios, cfgetospeed(&old_tios))?;
+    tcsetattr(fd, TCSANOW, &new_tios)?;
+    Ok(old_tios)
+}
+
+/// Make reads from `fd` non blocking, returning the previous file status
+/// flags to restore them on exit.
+fn set_non_blocking(fd: RawFd) -> io::Result<libc::c_int> {
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL, 0) };
+    if flags < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    set_flags(fd, flags | libc::O_NONBLOCK)?;
+    Ok(flags)
+}
+
+/// Set the file status flags of `fd`.
+fn set_flags(fd: RawFd, flags: libc::c_int) -> io::Result<()> {
+    if unsafe { libc::fcntl(fd, libc::F_SETFL, flags) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Start the server in the background, in its own process session so that
+/// it isn't killed with the terminal of the client.
+fn start_server(config: Option<&str>) -> Result<()> {
+    let mut command = Command::new(env::current_exe().context("find terman executable")?);
+    if let Some(config) = config {
+        command.arg("-f").arg(config);
+    }
+    command.arg("server")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    command.spawn().context("start server")?;
+    Ok(())
+}
+
+/// Returns whether the client is run to create a session, which starts the
+/// server if it isn't running.
+fn starts_server(args: &[String]) -> bool {
+    match args.first().map(String::as_str) {
+        None | Some("new-session") | Some("new") => true,
+        Some(_) => false,
+    }
+}
+
+/// Connect to the server listening on `path`. If there is none, it is
+/// started with config file `config` when `start`.
+///
+/// Returns `None` if there is no server to connect to.
+async fn connect(path: &Path, config: Option<&str>, start: bool) -> Result<Option<UnixStream>> {
+    match UnixStream::connect(path).await {
+        Ok(stream) => return Ok(Some(stream)),
+        Err(e) if matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused) => {
+            if !start {
+                return Ok(None);
+            }
+        },
+        Err(e) => return Err(e).with_context(|| format!("connect to {}", path.display())),
+    }
+    trace!("starting server");
+    start_server(config)?;
+    let deadline = Instant::now() + START_TIMEOUT;
+    loop {
+        delay_for(CONNECT_INTERVAL).await;
+        match UnixStream::connect(path).await {
+            Ok(stream) => return Ok(Some(stream)),
+            Err(_) if Instant::now() < deadline => {},
+            Err(e) => return Err(e).with_context(|| format!("connect to {}", path.display())),
+        }
+    }
+}
+
+/// Get the color depth of the host, which `colors`, the value of
+/// `TERMAN_COLORS`, overrides with `16`, `256` or `truecolor`.
+fn color_depth(colors: Option<&str>) -> Result<ColorDepth> {
+    match colors {
+        Some(colors) => colors.parse().map_err(|_| anyhow!("bad TERMAN_COLORS: {}", colors)),
+        None => Ok(ColorDepth::detect()),
+    }
+}
+
+/// Read from the host, or wait forever without one.
+async fn read_host(host: &mut Option<Pty>, buf: &mut [u8]) -> io::Result<usize> {
+    match host {
+        Some(host) => host.read(buf).await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// Run the commands of `args` on the server. Creating a session starts it
+/// with config file `config` if it isn't running, other commands fail.
+/// When the client is attached to a session, the host input is sent to the
+/// server and its output is shown until the client detaches.
+///
+/// Returns the exit status of the client.
+pub async fn run(config: Option<String>, args: Vec<String>) -> Result<i32> {
+    // Clients can create detached sessions without a terminal.
+    let mut host = Pty::new(io::stdin()).ok();
+    let size = host.as_ref().and_then(|host| host.get_size().ok()).unwrap_or(DEFAULT_SIZE);
+    let cell_size = host.as_ref().and_then(|host| host.get_cell_size().ok()).unwrap_or_default();
+    let color_depth = color_depth(env::var("TERMAN_COLORS").ok().as_deref())?;
+
+    let path = server::socket_path();
+    let stream = match connect(&path, config.as_deref(), starts_server(&args)).await? {
+        Some(stream) => stream,
+        None => return Err(anyhow!("no server running on {}", path.display())),
+    };
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    write_message(&mut writer, &ClientMessage::Identify {
+        size,
+        cell_size,
+        color_depth,
+    }).await?;
+    write_message(&mut writer, &ClientMessage::Command(args)).await?;
+
+    // Messages are read by their own task, waiting for one can be given up
+    // without losing part of it.
+    let (sender, mut messages) = mpsc::unbounded_channel();
+    task::spawn(async move {
+        loop {
+            let message = read_message::<ServerMessage, _>(&mut reader).await;
+            let end = !matches!(message, Ok(Some(_)));
+            if sender.send(message).is_err() || end {
+                break;
+            }
+        }
+    });
+
+    let mut window_change = signal(SignalKind::window_change()).context("handle SIGWINCH")?;
+    let mut stdout = io::stdout();
+    let mut buf = [0u8; 128];
+    // Terminal settings and stdin flags to restore, while attached.
+    let mut old_tios = None;
+    let mut old_flags = None;
+    // The terminal is restored whatever happens.
+    let res: Result<(u8, String)> = async {
+        loop {
+            tokio::select! {
+                message = messages.recv() => match message {
+                    Some(Ok(Some(ServerMessage::Attached))) => {
+                        let fd = host.as_ref().ok_or_else(|| anyhow!("not a terminal"))?.as_raw_fd();
+                        old_tios = Some(set_raw_terminal(fd)?);
+                        // Reads are driven by readiness events, they must never
+                        // block a runtime thread.
+                        old_flags = Some(set_non_blocking(fd).context("set stdin non blocking")?);
+                    },
+                    Some(Ok(Some(ServerMessage::Output(bytes)))) => {
+                        stdout.write_all(&bytes)?;
+                        stdout.flush()?;
+                    },
+                    Some(Ok(Some(ServerMessage::Exit { code, message }))) => return Ok((code, message)),
+                    Some(Err(e)) => return Err(e),
+                    Some(Ok(None)) | None => return Ok((1, "lost server".to_owned())),
+                },
+                res = read_host(&mut host, &mut buf[..]), if old_tios.is_some() => {
+                    let n = res.context("stdin read")?;
+                    if n == 0 {
+                        return Ok((0, "exited".to_owned()));
+                    }
+                    write_message(&mut writer, &ClientMessage::Input(buf[..n].to_vec())).await?;
+                },
+                _ = window_change.recv() => {
+                    if let Some(size) = host.as_ref().and_then(|host| host.get_size().ok()) {
+                        write_message(&mut writer, &ClientMessage::Resize(size)).await?;
+                    }
+                },
+            }
+        }
+    }.await;
+
+    let attached = old_tios.is_some();
+    // The shell gets the terminal back, it must not be left non blocking.
+    if let Some(flags) = old_flags {
+        set_flags(io::stdin().as_raw_fd(), flags).context("restore stdin flags")?;
+    }
+    if let Some(old_tios) = old_tios {
+        tcsetattr(io::stdin().as_raw_fd(), TCSANOW, &old_tios)?;
+        print!("{}", MOUSE_OFF);
+        print!("\x1b[0m\x1b[2J\x1b[3J\x1b[H"); // Clear screen
+        if let Ok((_, message)) = &res {
+            println!("[{}]", message);
+        }
+    }
+    let (code, message) = res?;
+    if !attached && !message.is_empty() {
+        if code == 0 {
+            println!("{}", message);
+        } else {
+            eprintln!("terman: {}", message);
+        }
+    }
+    Ok(code as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ansi::ColorDepth;
+    use super::color_depth;
+
+    #[test]
+    fn test_color_depth() {
+        assert_eq!(color_depth(Some("16")).unwrap(), ColorDepth::Ansi16);
+        assert_eq!(color_depth(Some("256")).unwrap(), ColorDepth::Indexed256);
+        assert_eq!(color_depth(Some("truecolor")).unwrap(), ColorDepth::TrueColor);
+        assert_eq!(color_depth(Some("8")).unwrap_err().to_string(), "bad TERMAN_COLORS: 8");
+    }
+}
@@ -0,0 +1,801 @@
+pub mod parser;
+pub mod target;
+
+use {
+    std::{
+        env,
+        fs,
+        io::Write,
+        sync::{
+            Arc,
+            Mutex,
+            Weak,
+        },
+        time::Duration,
+    },
+    anyhow::{
+        anyhow,
+        Context,
+        Result,
+    },
+    futures::future::{
+        BoxFuture,
+        FutureExt,
+    },
+    log::trace,
+    crate::{
+        ansi::{
+            Handler,
+            TerminalMode,
+        },
+        buffer::PasteBuffers,
+        copy::{
+            CopyAction,
+            CopyMode,
+            CopyOutcome,
+            Search,
+        },
+        input::{
+            prompt::Prompt,
+            Input,
+            Key,
+        },
+        layout::{
+            Direction,
+            PaneId,
+            Side,
+        },
+        options::{
+            self,
+            Level,
+            Options,
+            Scope,
+            ScopedOptions,
+        },
+        screen::renderer::ScreenRendererNotifier,
+        server::{
+            Clients,
+            Server,
+        },
+        session::{
+            spawner,
+            Session,
+            Spawner,
+            Window,
+        },
+        term::Terminal,
+        util::Point,
+    },
+};
+
+pub use self::{
+    parser::parse,
+    target::Target,
+};
+
+/// How deep files can source other files.
+const MAX_SOURCE_DEPTH: usize = 16;
+
+/// Number of characters of paste buffers shown in lists.
+const BUFFER_SAMPLE_WIDTH: usize = 40;
+
+/// Markers around pasted text, for panes in bracketed paste mode.
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+/// Replace a leading `~/` in `path` with the home directory.
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home, rest),
+        _ => path.to_owned(),
+    }
+}
+
+/// A command that controls the session, bound to keys or entered in the
+/// command language.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Attach the client to a session, only watching it if read-only.
+    AttachSession {
+        read_only: bool,
+    },
+    /// Bind a key of a key table to a list of commands.
+    BindKey {
+        table: String,
+        key: Key,
+        repeat: bool,
+        commands: Vec<Command>,
+    },
+    UnbindKey {
+        table: String,
+        key: Key,
+    },
+    /// Open a prompt on the status line. The entered text replaces `%%` in
+    /// the template, or is run as a command without one.
+    Prompt {
+        label: Option<String>,
+        incremental: bool,
+        template: Option<String>,
+    },
+    /// Enter copy mode in the active pane, scrolling up a page if asked.
+    CopyMode {
+        page_up: bool,
+    },
+    /// Do something in the copy mode of the active pane.
+    CopyAction(CopyAction),
+    /// Search in the copy mode of the active pane.
+    CopySearch(Search),
+    /// Detach the client that runs the command, or every client attached
+    /// to the session, leaving the session running.
+    DetachClient,
+    /// Show a message on the status line.
+    DisplayMessage(String),
+    /// Show the paste buffers, the most recent first.
+    ListBuffers,
+    /// Show the sessions, in the order they were created.
+    ListSessions,
+    /// Kill every window of the session, which ends it.
+    KillSession,
+    /// Kill every session, which stops the server.
+    KillServer,
+    RenameSession(String),
+    /// Create a session, named automatically unless it is given a name,
+    /// and attach the client to it unless detached. Its first window runs
+    /// the shell command instead of the default shell.
+    NewSession {
+        name: Option<String>,
+        detached: bool,
+        command: Option<String>,
+    },
+    /// Prompt for the name of a paste buffer to paste.
+    ChooseBuffer,
+    /// Show the contents of a paste buffer, or of the most recent one.
+    ShowBuffer {
+        name: Option<String>,
+    },
+    /// Set a paste buffer, or add one named automatically.
+    SetBuffer {
+        name: Option<String>,
+        data: String,
+    },
+    DeleteBuffer {
+        name: Option<String>,
+    },
+    /// Write a paste buffer to a file, appending to it if asked.
+    SaveBuffer {
+        name: Option<String>,
+        path: String,
+        append: bool,
+    },
+    /// Read a paste buffer from a file.
+    LoadBuffer {
+        name: Option<String>,
+        path: String,
+    },
+    /// Paste a buffer into the active pane. New lines are sent as carriage
+    /// returns unless the paste is raw.
+    PasteBuffer {
+        name: Option<String>,
+        delete: bool,
+        raw: bool,
+    },
+    /// Send the prefix key to the active pane.
+    SendPrefix,
+    /// Send keys to the active pane, by name unless they are literal.
+    /// Words that aren't key names are sent as text.
+    SendKeys {
+        keys: Vec<String>,
+        literal: bool,
+    },
+    /// Set an option at a level, or at the level of its scope. Options
+    /// without a value are unset, so that they are inherited again.
+    SetOption {
+        name: String,
+        value: Option<String>,
+        level: Option<Level>,
+    },
+    /// Show the options in effect for the active pane, or the global ones,
+    /// with the level their values come from.
+    ShowOptions {
+        name: Option<String>,
+        global: bool,
+    },
+    /// Execute the commands of a file. Missing files are ignored if quiet.
+    SourceFile {
+        path: String,
+        quiet: bool,
+    },
+    /// Create a window running the shell command, or the default shell.
+    NewWindow {
+        name: Option<String>,
+        cwd: Option<String>,
+        command: Option<String>,
+    },
+    KillWindow,
+    NextWindow,
+    PreviousWindow,
+    SelectWindow(usize),
+    RenameWindow(String),
+    /// Change the index of the active window.
+    MoveWindow(usize),
+    /// Resize the windows, keeping the size of the missing directions. The
+    /// windows then keep their size whatever the clients attached.
+    ResizeWindow {
+        width: Option<usize>,
+        height: Option<usize>,
+    },
+    /// Split the active pane, adding a pane after it in the direction.
+    SplitWindow {
+        direction: Direction,
+        cwd: Option<String>,
+    },
+    KillPane,
+    /// Select the pane next to the active pane on a side.
+    SelectPane(Side),
+    /// Move a border of the active pane by a number of cells.
+    ResizePane(Side, usize),
+    /// Read the next key from another key table.
+    SwitchTable(String),
+}
+
+/// State on which commands are executed.
+///
+/// Every source of commands goes through it: key bindings, the command
+/// prompt and command lines.
+pub struct CommandContext {
+    pub session: Arc<Mutex<Session>>,
+    pub spawner: Spawner,
+    pub notifier: ScreenRendererNotifier,
+    pub input: Arc<Mutex<Input>>,
+    pub options: Arc<Mutex<Options>>,
+    pub buffers: Arc<Mutex<PasteBuffers>>,
+    /// The clients attached to the session.
+    pub clients: Clients,
+    /// The client that the commands come from, if any.
+    pub client: Option<usize>,
+    /// Whether the client only watches the session.
+    pub read_only: bool,
+    /// Lines printed by the client that runs commands from the command
+    /// line, which are shown on the status line for other sources.
+    pub printed: Option<Mutex<Vec<String>>>,
+    /// The window and pane that commands apply to instead of the active
+    /// ones.
+    pub target: Target,
+    pub server: Weak<Server>,
+}
+
+impl CommandContext {
+    /// Get a context for the commands of client `id`, which reads its keys
+    /// with `input`.
+    pub fn for_client(&self, id: usize, input: Input, read_only: bool) -> Self {
+        Self {
+            client: Some(id),
+            read_only,
+            ..self.with_input(input)
+        }
+    }
+
+    /// Get a context for commands run from the command line, which print
+    /// what they show and apply to `target`.
+    pub fn for_command_line(&self, input: Input, target: Target) -> Self {
+        Self {
+            printed: Some(Mutex::new(Vec::new())),
+            target,
+            ..self.with_input(input)
+        }
+    }
+
+    fn with_input(&self, input: Input) -> Self {
+        Self {
+            session: Arc::clone(&self.session),
+            spawner: self.spawner.clone(),
+            notifier: self.notifier.clone(),
+            input: Arc::new(Mutex::new(input)),
+            options: Arc::clone(&self.options),
+            buffers: Arc::clone(&self.buffers),
+            clients: self.clients.clone(),
+            client: None,
+            read_only: false,
+            printed: None,
+            target: Target::default(),
+            server: Weak::clone(&self.server),
+        }
+    }
+
+    fn server(&self) -> Result<Arc<Server>> {
+        self.server.upgrade().ok_or_else(|| anyhow!("server exiting"))
+    }
+
+    /// Get the index of the window that commands apply to: the target
+    /// window, or the active window.
+    fn window_index(&self, session: &Session) -> Result<usize> {
+        match self.target.window {
+            Some(index) => session.window(index).map(Window::index),
+            None => session.active_window().map(Window::index).ok_or_else(|| anyhow!("no window")),
+        }
+    }
+
+    fn window_mut<'a>(&self, session: &'a mut Session) -> Result<&'a mut Window> {
+        let index = self.window_index(session)?;
+        session.window_mut(index)
+    }
+
+    /// Get the pane of `window` that commands apply to: the target pane, or
+    /// the active pane.
+    fn pane(&self, window: &Window) -> Result<PaneId> {
+        match self.target.pane {
+            Some(position) => window.pane_at(position).ok_or_else(|| anyhow!("no such pane: {}", position)),
+            None => Ok(window.active_pane()),
+        }
+    }
+
+    fn terminal(&self, session: &Session) -> Result<Arc<Terminal>> {
+        let window = session.window(self.window_index(session)?)?;
+        window.terminal(self.pane(window)?).ok_or_else(|| anyhow!("no pane"))
+    }
+
+    /// Get the options in effect for the pane that commands apply to.
+    fn pane_options<'a>(&self, session: &'a Session, global: &'a Options) -> Result<ScopedOptions<'a>> {
+        let window = session.window(self.window_index(session)?)?;
+        Ok(window.pane_scoped_options(session.scoped_options(global), self.pane(window)?))
+    }
+
+    /// Show lines on the status line, or print them.
+    fn show(&self, session: &mut Session, lines: Vec<String>) {
+        match &self.printed {
+            Some(printed) => printed.lock().unwrap().extend(lines),
+            None => session.set_message(Some(lines.join(", "))),
+        }
+    }
+
+    /// Parse and execute a command line, stopping at the first command
+    /// that fails.
+    pub async fn execute_line(&self, line: &str) -> Result<()> {
+        for command in parse(line)? {
+            self.execute(&command).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn execute(&self, command: &Command) -> Result<()> {
+        trace!("[command] execute: {:?}", command);
+        match command {
+            Command::SendPrefix => {
+                let prefix = self.input.lock().unwrap().config().prefix;
+                self.send_keys(&[prefix.to_string()], false).await?;
+            },
+            Command::SendKeys { keys, literal } => self.send_keys(keys, *literal).await?,
+            Command::SourceFile { path, quiet } => self.source_file(path, *quiet, 0).await?,
+            Command::PasteBuffer { name, delete, raw } => {
+                self.paste_buffer(name.as_deref(), *delete, *raw).await?;
+            },
+            command => {
+                let result = self.execute_sync(command);
+                self.apply_options();
+                self.notifier.notify();
+                result?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Write keys to the target pane.
+    async fn send_keys(&self, keys: &[String], literal: bool) -> Result<()> {
+        let terminal = self.terminal(&self.session.lock().unwrap())?;
+        let cursor_keys = terminal.screen.lock().unwrap().mode(TerminalMode::CursorKeys);
+        let mut bytes = Vec::new();
+        for word in keys {
+            match word.parse::<Key>() {
+                Ok(key) if !literal => bytes.extend(key.encode(cursor_keys)),
+                _ => bytes.extend_from_slice(word.as_bytes()),
+            }
+        }
+        terminal.pty_write(&bytes).await.context("write keys")
+    }
+
+    /// Write a paste buffer to the target pane, between the bracketed paste
+    /// markers if the pane asked for them.
+    async fn paste_buffer(&self, name: Option<&str>, delete: bool, raw: bool) -> Result<()> {
+        let buffer = self.buffers.lock().unwrap().get(name)?.clone();
+        let terminal = self.terminal(&self.session.lock().unwrap())?;
+        let bracketed = terminal.screen.lock().unwrap().mode(TerminalMode::BracketedPaste);
+        let mut data = if raw {
+            buffer.data.clone()
+        } else {
+            buffer.data.replace('\n', "\r")
+        };
+        if bracketed {
+            // The text can't end the paste early.
+            data = format!("{}{}{}", PASTE_START, data.replace(PASTE_END, ""), PASTE_END);
+        }
+        terminal.pty_write(data.as_bytes()).await.context("paste")?;
+        if delete {
+            // The buffer may have been deleted meanwhile.
+            let _ = self.buffers.lock().unwrap().delete(Some(&buffer.name));
+        }
+        Ok(())
+    }
+
+    /// Execute each line of a file. Lines ending with a backslash are
+    /// continued on the next line.
+    ///
+    /// All the lines are executed, the errors are returned together.
+    fn source_file<'a>(&'a self, path: &'a str, quiet: bool, depth: usize) -> BoxFuture<'a, Result<()>> {
+        async move {
+            if depth >= MAX_SOURCE_DEPTH {
+                return Err(anyhow!("{}: too many nested files", path));
+            }
+            let expanded = expand_home(path);
+            let contents = match fs::read_to_string(&expanded) {
+                Ok(contents) => contents,
+                Err(e) if quiet && e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(anyhow!("{}: {}", path, e)),
+            };
+            trace!("[command] source file: {}", expanded);
+
+            let mut errors = Vec::new();
+            let mut line = String::new();
+            let mut first = 0;
+            for (number, text) in contents.lines().enumerate() {
+                if line.is_empty() {
+                    first = number + 1;
+                }
+                match text.strip_suffix('\\') {
+                    Some(text) => {
+                        line.push_str(text);
+                        continue;
+                    },
+                    None => line.push_str(text),
+                }
+                if let Err(e) = self.source_line(&line, depth).await {
+                    errors.push(format!("{}:{}: {:#}", path, first, e));
+                }
+                line.clear();
+            }
+            if !line.is_empty() {
+                if let Err(e) = self.source_line(&line, depth).await {
+                    errors.push(format!("{}:{}: {:#}", path, first, e));
+                }
+            }
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(anyhow!("{}", errors.join("; ")))
+            }
+        }.boxed()
+    }
+
+    /// Execute a line of a sourced file.
+    async fn source_line(&self, line: &str, depth: usize) -> Result<()> {
+        for command in parse(line)? {
+            match &command {
+                Command::SourceFile { path, quiet } => self.source_file(path, *quiet, depth + 1).await?,
+                command => self.execute(command).await?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Execute a command that doesn't need to wait.
+    fn execute_sync(&self, command: &Command) -> Result<()> {
+        match command {
+            Command::BindKey { table, key, repeat, commands } => {
+                self.input.lock().unwrap().tables()
+                    .get_mut(table)
+                    .bind(*key, commands.clone(), *repeat);
+            },
+            Command::UnbindKey { table, key } => {
+                self.input.lock().unwrap().tables()
+                    .get_mut(table)
+                    .unbind(key);
+            },
+            Command::SetOption { name, value, level } => self.set_option(name, value.as_deref(), *level)?,
+            Command::SwitchTable(table) => self.input.lock().unwrap().switch_table(table.clone()),
+            Command::SetBuffer { name, data } => self.set_buffer(name.as_deref(), data.clone()),
+            Command::DeleteBuffer { name } => {
+                self.buffers.lock().unwrap().delete(name.as_deref())?;
+            },
+            Command::SaveBuffer { name, path, append } => {
+                let buffers = self.buffers.lock().unwrap();
+                let buffer = buffers.get(name.as_deref())?;
+                let mut file = fs::OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(*append)
+                    .truncate(!*append)
+                    .open(expand_home(path))
+                    .map_err(|e| anyhow!("{}: {}", path, e))?;
+                file.write_all(buffer.data.as_bytes()).map_err(|e| anyhow!("{}: {}", path, e))?;
+            },
+            Command::LoadBuffer { name, path } => {
+                let data = fs::read_to_string(expand_home(path)).map_err(|e| anyhow!("{}: {}", path, e))?;
+                self.set_buffer(name.as_deref(), data);
+            },
+            Command::ListSessions => {
+                let lines = self.server()?.list_sessions();
+                self.show(&mut self.session.lock().unwrap(), lines);
+            },
+            Command::KillServer => self.server()?.kill_server(),
+            Command::RenameSession(name) => self.server()?.rename_session(&self.session, name.clone())?,
+            Command::NewSession { .. } | Command::AttachSession { .. } => {
+                return Err(anyhow!("only run from the command line"));
+            },
+            command => self.execute_session(command)?,
+        }
+        Ok(())
+    }
+
+    /// Set paste buffer `name`, or add a buffer named automatically.
+    fn set_buffer(&self, name: Option<&str>, data: String) {
+        let limit = ScopedOptions::new(&self.options.lock().unwrap()).number("buffer-limit");
+        let mut buffers = self.buffers.lock().unwrap();
+        match name {
+            Some(name) => buffers.set(name, data),
+            None => {
+                buffers.add(data, limit);
+            },
+        }
+    }
+
+    /// Set or unset an option.
+    fn set_option(&self, name: &str, value: Option<&str>, level: Option<Level>) -> Result<()> {
+        let def = options::definition(name)?;
+        let level = level.unwrap_or(match def.scope {
+            Scope::Server => Level::Global,
+            Scope::Session => Level::Session,
+            Scope::Window => Level::Window,
+            Scope::Pane => Level::Pane,
+        });
+        if !level.allows(def.scope) {
+            return Err(anyhow!("{}: can't be set for a {}", name, level));
+        }
+
+        let mut session = self.session.lock().unwrap();
+        let mut global = self.options.lock().unwrap();
+        let old_status = session.scoped_options(&global).flag("status");
+        let options = match level {
+            Level::Global => &mut *global,
+            Level::Session => session.options_mut(),
+            Level::Window => self.window_mut(&mut session)?.options_mut(),
+            Level::Pane => {
+                let window = self.window_mut(&mut session)?;
+                let id = self.pane(window)?;
+                window.pane_options_mut(id)
+            },
+        };
+        match value {
+            Some(value) => options.set(name, value)?,
+            None => options.unset(name)?,
+        }
+
+        let status = session.scoped_options(&global).flag("status");
+        if status != old_status {
+            // The windows take the line of the status line, or give it back.
+            let size = session.size();
+            let size = if status {
+                size - Point::new(0, 1)
+            } else {
+                size + Point::new(0, 1)
+            };
+            session.resize(size)?;
+        }
+        if status != old_status || name == "window-size" {
+            session.fit_clients(&global)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the options to the parts of the state that keep their own
+    /// copy: the input and the screens.
+    pub fn apply_options(&self) {
+        self.session.lock().unwrap().apply_options(&self.options.lock().unwrap());
+        self.configure_input();
+    }
+
+    /// Apply the options to the input. The input of other clients is
+    /// configured when it is read.
+    pub fn configure_input(&self) {
+        let session = self.session.lock().unwrap();
+        let global = self.options.lock().unwrap();
+        let options = session.scoped_options(&global);
+        let mut input = self.input.lock().unwrap();
+        let config = input.config_mut();
+        config.prefix = options.key("prefix");
+        config.repeat_time = Duration::from_millis(options.number("repeat-time") as u64);
+        config.escape_time = Duration::from_millis(options.number("escape-time") as u64);
+    }
+
+    /// Execute a command that changes the session.
+    fn execute_session(&self, command: &Command) -> Result<()> {
+        let mut session = self.session.lock().unwrap();
+        match command {
+            Command::Prompt { label, incremental, template } => {
+                if *incremental {
+                    // Incremental searches start again from the cursor.
+                    let terminal = self.terminal(&session)?;
+                    let mut copy_mode = terminal.copy_mode.lock().unwrap();
+                    if let Some(copy_mode) = copy_mode.as_mut() {
+                        copy_mode.finish_search();
+                    }
+                }
+                let mut prompt = Prompt::command();
+                if let Some(label) = label {
+                    prompt.label = label.clone();
+                }
+                prompt.template = template.clone();
+                prompt.incremental = *incremental;
+                session.set_prompt(Some(prompt));
+            },
+            Command::CopyMode { page_up } => {
+                let terminal = self.terminal(&session)?;
+                let separators = self.pane_options(&session, &self.options.lock().unwrap())?.string("word-separators");
+                let mut copy_mode = terminal.copy_mode.lock().unwrap();
+                let copy_mode = copy_mode.get_or_insert_with(|| {
+                    CopyMode::new(&terminal.screen.lock().unwrap(), &separators)
+                });
+                if *page_up {
+                    copy_mode.apply(CopyAction::PageUp);
+                }
+            },
+            Command::CopyAction(action) => {
+                let terminal = self.terminal(&session)?;
+                let outcome = terminal.copy_mode.lock().unwrap()
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("not in copy mode"))?
+                    .apply(*action);
+                let (text, cancel) = match outcome {
+                    CopyOutcome::Continue => (None, false),
+                    CopyOutcome::Cancel => (None, true),
+                    CopyOutcome::Copy(text) => (Some(text), false),
+                    CopyOutcome::CopyAndCancel(text) => (Some(text), true),
+                };
+                if let Some(text) = text {
+                    self.set_buffer(None, text);
+                }
+                if cancel {
+                    *terminal.copy_mode.lock().unwrap() = None;
+                    // The screen is shown again as it is now.
+                    terminal.screen.lock().unwrap().damage_all();
+                }
+            },
+            Command::CopySearch(search) => {
+                let terminal = self.terminal(&session)?;
+                let mut copy_mode = terminal.copy_mode.lock().unwrap();
+                copy_mode.as_mut()
+                    .ok_or_else(|| anyhow!("not in copy mode"))?
+                    .search(search.clone())?;
+            },
+            Command::DetachClient => {
+                let message = format!("detached (from session {})", session.name());
+                match self.client {
+                    Some(id) => self.clients.detach(id, message),
+                    None => self.clients.detach_all(message),
+                }
+            },
+            Command::DisplayMessage(message) => self.show(&mut session, vec![message.clone()]),
+            Command::ListBuffers => {
+                let list: Vec<String> = self.buffers.lock().unwrap().iter()
+                    .map(|buffer| format!(
+                        "{}: {} bytes: \"{}\"",
+                        buffer.name,
+                        buffer.data.len(),
+                        buffer.sample(BUFFER_SAMPLE_WIDTH),
+                    ))
+                    .collect();
+                if !list.is_empty() {
+                    self.show(&mut session, list);
+                }
+            },
+            Command::ChooseBuffer => {
+                let names: Vec<String> = self.buffers.lock().unwrap().iter()
+                    .map(|buffer| buffer.name.clone())
+                    .collect();
+                if names.is_empty() {
+                    return Err(anyhow!("no buffers"));
+                }
+                let mut prompt = Prompt::command();
+                prompt.label = format!("paste buffer ({}): ", names.join(", "));
+                prompt.template = Some("paste-buffer -b \"%%\"".to_owned());
+                session.set_prompt(Some(prompt));
+            },
+            Command::ShowBuffer { name } => {
+                let buffers = self.buffers.lock().unwrap();
+                let buffer = buffers.get(name.as_deref())?;
+                self.show(&mut session, vec![buffer.sample(usize::MAX)]);
+            },
+            Command::ShowOptions { name, global } => {
+                let lines = {
+                    let options = self.options.lock().unwrap();
+                    let scoped = if *global {
+                        ScopedOptions::new(&options)
+                    } else {
+                        self.pane_options(&session, &options)?
+                    };
+                    match name {
+                        Some(name) => vec![scoped.show_option(options::definition(name)?.name)],
+                        None => scoped.show(),
+                    }
+                };
+                self.show(&mut session, lines);
+            },
+            Command::NewWindow { name, cwd, command } => {
+                let shell = session.scoped_options(&self.options.lock().unwrap()).string("default-shell");
+                // Windows are named after the program they run.
+                let program = command.as_deref()
+                    .and_then(|command| command.split_whitespace().next())
+                    .unwrap_or(&shell);
+                let name = name.clone().unwrap_or_else(|| spawner::window_name(program));
+                session.new_window(name, |size| {
+                    self.spawner.spawn(size, &shell, cwd.as_deref(), command.as_deref())
+                })?;
+            },
+            Command::KillSession => {
+                session.kill();
+                self.spawner.removed(&session);
+            },
+            Command::KillWindow => {
+                let index = self.window_index(&session)?;
+                session.kill_window(index)?;
+                self.spawner.removed(&session);
+            },
+            // Clients that only watch the session change the window they
+            // show, not the one of the others.
+            Command::NextWindow | Command::PreviousWindow | Command::SelectWindow(_) if self.read_only => {
+                let id = self.client.ok_or_else(|| anyhow!("no client"))?;
+                match command {
+                    Command::SelectWindow(index) => session.show_window(id, *index)?,
+                    command => session.cycle_window(id, *command == Command::NextWindow)?,
+                }
+            },
+            Command::NextWindow => session.next_window(),
+            Command::PreviousWindow => session.previous_window(),
+            Command::SelectWindow(index) => session.select_window(*index)?,
+            Command::RenameWindow(name) => {
+                let index = self.window_index(&session)?;
+                session.rename_window(index, name.clone())?;
+            },
+            Command::MoveWindow(new_index) => {
+                let index = self.window_index(&session)?;
+                session.move_window(index, *new_index)?;
+            },
+            Command::ResizeWindow { width, height } => {
+                let size = session.size();
+                let size = Point::new(width.unwrap_or(size.x).max(1), height.unwrap_or(size.y).max(1));
+                session.options_mut().set("window-size", "manual")?;
+                session.resize(size)?;
+            },
+            Command::SplitWindow { direction, cwd } => {
+                let shell = self.pane_options(&session, &self.options.lock().unwrap())?.string("default-shell");
+                let window = self.window_mut(&mut session)?;
+                let id = self.pane(window)?;
+                window.split(id, *direction, |size| self.spawner.spawn(size, &shell, cwd.as_deref(), None))?;
+            },
+            Command::KillPane => {
+                // The pane goes even if `remain-on-exit` would keep it.
+                let terminal = self.terminal(&session)?;
+                terminal.kill()?;
+                session.remove_terminal(&terminal)?;
+                self.spawner.removed(&session);
+            },
+            Command::SelectPane(side) => {
+                let window = self.window_mut(&mut session)?;
+                let id = self.pane(window)?;
+                window.select_neighbour(id, *side);
+            },
+            Command::ResizePane(side, count) => {
+                let window = self.window_mut(&mut session)?;
+                let id = self.pane(window)?;
+                window.resize_pane(id, *side, *count)?;
+            },
+            Command::BindKey { .. } | Command::UnbindKey { .. } | Command::SetOption { .. } |
+            Command::SwitchTable(_) | Command::SendPrefix | Command::SendKeys { .. } |
+            Command::SourceFile { .. } | Command::SetBuffer { .. } | Command::DeleteBuffer { .. } |
+            Command::SaveBuffer { .. } | Command::LoadBuffer { .. } | Command::PasteBuffer { .. } |
+            Command::ListSessions | Command::KillServer | Command::RenameSession(_) |
+            Command::NewSession { .. } | Command::AttachSession { .. } => {
+                unreachable!()
+            },
+        }
+        Ok(())
+    }
+}
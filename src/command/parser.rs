@@ -0,0 +1,939 @@
+use {
+    std::collections::HashMap,
+    anyhow::{
+        anyhow,
+        Result,
+    },
+    crate::{
+        copy::Search,
+        input::{
+            Key,
+            PREFIX_TABLE,
+            ROOT_TABLE,
+        },
+        layout::{
+            Direction,
+            Side,
+        },
+        options::Level,
+    },
+    super::{
+        Command,
+        Target,
+    },
+};
+
+/// A word of a command line, or the `;` that separates commands.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Word(String),
+    Separator,
+}
+
+/// Split a command line into words, handling quotes and escapes.
+///
+/// Single quotes keep their content as is, double quotes and unquoted
+/// words allow escaping with a backslash. A `;` at the end of a word
+/// separates commands, unless it is escaped, and `#` starts a comment
+/// at the start of a word.
+fn tokenize(line: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|ch| ch.is_whitespace()) {
+            chars.next();
+        }
+        match chars.peek() {
+            None | Some('#') => break,
+            Some(';') => {
+                chars.next();
+                tokens.push(Token::Separator);
+                continue;
+            },
+            Some(_) => {},
+        }
+
+        let mut word = String::new();
+        let mut separator = false;
+        while let Some(ch) = chars.next() {
+            match ch {
+                ch if ch.is_whitespace() => break,
+                ';' if chars.peek().is_none_or(|ch| ch.is_whitespace()) => {
+                    separator = true;
+                    break;
+                },
+                '\'' => loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => word.push(ch),
+                        None => return Err(anyhow!("unterminated quote")),
+                    }
+                },
+                '"' => loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => word.push(escape(chars.next())?),
+                        Some(ch) => word.push(ch),
+                        None => return Err(anyhow!("unterminated quote")),
+                    }
+                },
+                '\\' => word.push(escape(chars.next())?),
+                ch => word.push(ch),
+            }
+        }
+        tokens.push(Token::Word(word));
+        if separator {
+            tokens.push(Token::Separator);
+        }
+    }
+    Ok(tokens)
+}
+
+fn escape(ch: Option<char>) -> Result<char> {
+    match ch {
+        Some('n') => Ok('\n'),
+        Some('r') => Ok('\r'),
+        Some('t') => Ok('\t'),
+        Some('e') => Ok('\x1b'),
+        Some(ch) => Ok(ch),
+        None => Err(anyhow!("unterminated escape")),
+    }
+}
+
+/// Parse a command line made of commands separated by `;`.
+pub fn parse(line: &str) -> Result<Vec<Command>> {
+    let mut commands = Vec::new();
+    let mut words = Vec::new();
+    for token in tokenize(line)?.into_iter().chain(Some(Token::Separator)) {
+        match token {
+            Token::Word(word) => words.push(word),
+            Token::Separator if words.is_empty() => {},
+            Token::Separator => {
+                commands.push(parse_words(&words)?);
+                words.clear();
+            },
+        }
+    }
+    Ok(commands)
+}
+
+/// Parse the commands of a list of words, where `;` words separate
+/// commands. This is used for commands that take other commands as
+/// arguments, like `bind-key`.
+pub fn parse_words_list(words: &[String]) -> Result<Vec<Command>> {
+    words.split(|word| word == ";")
+        .filter(|words| !words.is_empty())
+        .map(parse_words)
+        .collect()
+}
+
+/// Description of a command, used to parse it.
+struct CommandSpec {
+    name: &'static str,
+    alias: Option<&'static str>,
+    /// Flags accepted by the command, those followed by `:` take a value.
+    flags: &'static str,
+    /// Bounds of the number of positional arguments.
+    min: usize,
+    max: Option<usize>,
+    usage: &'static str,
+    build: fn(Args) -> Result<Command>,
+}
+
+impl CommandSpec {
+    fn usage(&self) -> String {
+        if self.usage.is_empty() {
+            self.name.to_owned()
+        } else {
+            format!("{} {}", self.name, self.usage)
+        }
+    }
+}
+
+/// Arguments of a command.
+#[derive(Debug, Default)]
+struct Args {
+    flags: HashMap<char, Option<String>>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn has(&self, flag: char) -> bool {
+        self.flags.contains_key(&flag)
+    }
+
+    fn value(&self, flag: char) -> Option<String> {
+        self.flags.get(&flag).cloned().flatten()
+    }
+
+    /// Get the value of a flag parsed as a number.
+    fn number(&self, flag: char) -> Result<Option<usize>> {
+        self.value(flag)
+            .map(|value| parse_number(&value))
+            .transpose()
+    }
+
+    /// Get the level given by one of the `-g`, `-w` and `-p` flags.
+    fn level(&self) -> Option<Level> {
+        [('g', Level::Global), ('w', Level::Window), ('p', Level::Pane)].iter()
+            .find(|(flag, _)| self.has(*flag))
+            .map(|(_, level)| *level)
+    }
+
+    /// Get the side given by one of the `-L`, `-R`, `-U` and `-D` flags.
+    fn side(&self) -> Option<Side> {
+        [('L', Side::Left), ('R', Side::Right), ('U', Side::Up), ('D', Side::Down)].iter()
+            .find(|(flag, _)| self.has(*flag))
+            .map(|(_, side)| *side)
+    }
+}
+
+fn parse_number(value: &str) -> Result<usize> {
+    value.parse().map_err(|_| anyhow!("not a number: {}", value))
+}
+
+fn parse_key(value: &str) -> Result<Key> {
+    value.parse().map_err(|_| anyhow!("unknown key: {}", value))
+}
+
+/// Get the command given after the arguments of a command, if any.
+fn shell_command(args: &Args) -> Option<String> {
+    Some(args.positional.join(" ")).filter(|command| !command.is_empty())
+}
+
+const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "attach-session",
+        alias: Some("attach"),
+        flags: "r",
+        min: 0,
+        max: Some(0),
+        usage: "[-r]",
+        build: |args| Ok(Command::AttachSession { read_only: args.has('r') }),
+    },
+    CommandSpec {
+        name: "bind-key",
+        alias: Some("bind"),
+        flags: "nrT:",
+        min: 2,
+        max: None,
+        usage: "[-nr] [-T key-table] key command [arguments]",
+        build: |args| {
+            let table = match args.value('T') {
+                Some(table) => table,
+                None if args.has('n') => ROOT_TABLE.to_owned(),
+                None => PREFIX_TABLE.to_owned(),
+            };
+            Ok(Command::BindKey {
+                table,
+                key: parse_key(&args.positional[0])?,
+                repeat: args.has('r'),
+                commands: parse_words_list(&args.positional[1..])?,
+            })
+        },
+    },
+    CommandSpec {
+        name: "choose-buffer",
+        alias: None,
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::ChooseBuffer),
+    },
+    CommandSpec {
+        name: "command-prompt",
+        alias: None,
+        flags: "ip:",
+        min: 0,
+        max: Some(1),
+        usage: "[-i] [-p prompt] [template]",
+        build: |mut args| Ok(Command::Prompt {
+            label: args.value('p'),
+            incremental: args.has('i'),
+            template: args.positional.pop(),
+        }),
+    },
+    CommandSpec {
+        name: "copy-mode",
+        alias: None,
+        flags: "u",
+        min: 0,
+        max: Some(0),
+        usage: "[-u]",
+        build: |args| Ok(Command::CopyMode {
+            page_up: args.has('u'),
+        }),
+    },
+    CommandSpec {
+        name: "delete-buffer",
+        alias: Some("deleteb"),
+        flags: "b:",
+        min: 0,
+        max: Some(0),
+        usage: "[-b buffer-name]",
+        build: |args| Ok(Command::DeleteBuffer {
+            name: args.value('b'),
+        }),
+    },
+    CommandSpec {
+        name: "detach-client",
+        alias: Some("detach"),
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::DetachClient),
+    },
+    CommandSpec {
+        name: "display-message",
+        alias: Some("display"),
+        flags: "",
+        min: 1,
+        max: Some(1),
+        usage: "message",
+        build: |args| Ok(Command::DisplayMessage(args.positional[0].clone())),
+    },
+    CommandSpec {
+        name: "kill-pane",
+        alias: Some("killp"),
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::KillPane),
+    },
+    CommandSpec {
+        name: "kill-server",
+        alias: None,
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::KillServer),
+    },
+    CommandSpec {
+        name: "kill-session",
+        alias: None,
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::KillSession),
+    },
+    CommandSpec {
+        name: "kill-window",
+        alias: Some("killw"),
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::KillWindow),
+    },
+    CommandSpec {
+        name: "list-buffers",
+        alias: Some("lsb"),
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::ListBuffers),
+    },
+    CommandSpec {
+        name: "list-sessions",
+        alias: Some("ls"),
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::ListSessions),
+    },
+    CommandSpec {
+        name: "load-buffer",
+        alias: Some("loadb"),
+        flags: "b:",
+        min: 1,
+        max: Some(1),
+        usage: "[-b buffer-name] path",
+        build: |args| Ok(Command::LoadBuffer {
+            name: args.value('b'),
+            path: args.positional[0].clone(),
+        }),
+    },
+    CommandSpec {
+        name: "move-window",
+        alias: Some("movew"),
+        flags: "t:",
+        min: 0,
+        max: Some(0),
+        usage: "-t dst-index",
+        build: |args| {
+            let index = args.number('t')?.ok_or_else(|| anyhow!("missing -t"))?;
+            Ok(Command::MoveWindow(index))
+        },
+    },
+    CommandSpec {
+        name: "new-session",
+        alias: Some("new"),
+        flags: "ds:",
+        min: 0,
+        max: None,
+        usage: "[-d] [-s session-name] [shell-command]",
+        build: |args| Ok(Command::NewSession {
+            name: args.value('s'),
+            detached: args.has('d'),
+            command: shell_command(&args),
+        }),
+    },
+    CommandSpec {
+        name: "new-window",
+        alias: Some("neww"),
+        flags: "c:n:",
+        min: 0,
+        max: None,
+        usage: "[-c start-directory] [-n window-name] [shell-command]",
+        build: |args| Ok(Command::NewWindow {
+            name: args.value('n'),
+            cwd: args.value('c'),
+            command: shell_command(&args),
+        }),
+    },
+    CommandSpec {
+        name: "next-window",
+        alias: Some("next"),
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::NextWindow),
+    },
+    CommandSpec {
+        name: "paste-buffer",
+        alias: Some("pasteb"),
+        flags: "b:dr",
+        min: 0,
+        max: Some(0),
+        usage: "[-dr] [-b buffer-name]",
+        build: |args| Ok(Command::PasteBuffer {
+            name: args.value('b'),
+            delete: args.has('d'),
+            raw: args.has('r'),
+        }),
+    },
+    CommandSpec {
+        name: "previous-window",
+        alias: Some("prev"),
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::PreviousWindow),
+    },
+    CommandSpec {
+        name: "rename-session",
+        alias: Some("rename"),
+        flags: "",
+        min: 1,
+        max: Some(1),
+        usage: "new-name",
+        build: |args| Ok(Command::RenameSession(args.positional[0].clone())),
+    },
+    CommandSpec {
+        name: "rename-window",
+        alias: Some("renamew"),
+        flags: "",
+        min: 1,
+        max: Some(1),
+        usage: "new-name",
+        build: |args| Ok(Command::RenameWindow(args.positional[0].clone())),
+    },
+    CommandSpec {
+        name: "resize-pane",
+        alias: Some("resizep"),
+        flags: "DLRU",
+        min: 0,
+        max: Some(1),
+        usage: "[-DLRU] [adjustment]",
+        build: |args| {
+            let side = args.side().ok_or_else(|| anyhow!("missing -D, -L, -R or -U"))?;
+            let count = match args.positional.first() {
+                Some(count) => parse_number(count)?,
+                None => 1,
+            };
+            Ok(Command::ResizePane(side, count))
+        },
+    },
+    CommandSpec {
+        name: "resize-window",
+        alias: Some("resizew"),
+        flags: "x:y:",
+        min: 0,
+        max: Some(0),
+        usage: "[-x width] [-y height]",
+        build: |args| Ok(Command::ResizeWindow {
+            width: args.number('x')?,
+            height: args.number('y')?,
+        }),
+    },
+    CommandSpec {
+        name: "save-buffer",
+        alias: Some("saveb"),
+        flags: "ab:",
+        min: 1,
+        max: Some(1),
+        usage: "[-a] [-b buffer-name] path",
+        build: |args| Ok(Command::SaveBuffer {
+            name: args.value('b'),
+            path: args.positional[0].clone(),
+            append: args.has('a'),
+        }),
+    },
+    CommandSpec {
+        name: "select-pane",
+        alias: Some("selectp"),
+        flags: "DLRU",
+        min: 0,
+        max: Some(0),
+        usage: "[-DLRU]",
+        build: |args| {
+            let side = args.side().ok_or_else(|| anyhow!("missing -D, -L, -R or -U"))?;
+            Ok(Command::SelectPane(side))
+        },
+    },
+    CommandSpec {
+        name: "select-window",
+        alias: Some("selectw"),
+        flags: "t:",
+        min: 0,
+        max: Some(0),
+        usage: "-t target-window",
+        build: |args| {
+            let index = args.number('t')?.ok_or_else(|| anyhow!("missing -t"))?;
+            Ok(Command::SelectWindow(index))
+        },
+    },
+    CommandSpec {
+        name: "send-keys",
+        alias: Some("send"),
+        flags: "lX",
+        min: 0,
+        max: None,
+        usage: "[-lX] key ...",
+        build: |args| {
+            if args.has('X') {
+                // Copy mode commands are sent by name, searches are
+                // followed by their pattern.
+                return match args.positional.as_slice() {
+                    [name, pattern] => Search::from_name(name, pattern.clone())
+                        .map(Command::CopySearch)
+                        .ok_or_else(|| anyhow!("too many arguments")),
+                    [name] => match Search::from_name(name, String::new()) {
+                        Some(search) => Ok(Command::CopySearch(search)),
+                        None => Ok(Command::CopyAction(name.parse()?)),
+                    },
+                    [] => Err(anyhow!("missing command")),
+                    _ => Err(anyhow!("too many arguments")),
+                };
+            }
+            Ok(Command::SendKeys {
+                literal: args.has('l'),
+                keys: args.positional,
+            })
+        },
+    },
+    CommandSpec {
+        name: "send-prefix",
+        alias: None,
+        flags: "",
+        min: 0,
+        max: Some(0),
+        usage: "",
+        build: |_| Ok(Command::SendPrefix),
+    },
+    CommandSpec {
+        name: "set-buffer",
+        alias: Some("setb"),
+        flags: "b:",
+        min: 1,
+        max: Some(1),
+        usage: "[-b buffer-name] data",
+        build: |args| Ok(Command::SetBuffer {
+            name: args.value('b'),
+            data: args.positional[0].clone(),
+        }),
+    },
+    CommandSpec {
+        name: "set-option",
+        alias: Some("set"),
+        flags: "gpuw",
+        min: 1,
+        max: Some(2),
+        usage: "[-gpuw] option [value]",
+        build: |args| {
+            let value = args.positional.get(1).cloned();
+            match (args.has('u'), &value) {
+                (true, Some(_)) => return Err(anyhow!("-u takes no value")),
+                (false, None) => return Err(anyhow!("missing value")),
+                _ => {},
+            }
+            Ok(Command::SetOption {
+                name: args.positional[0].clone(),
+                value,
+                level: args.level(),
+            })
+        },
+    },
+    CommandSpec {
+        name: "show-buffer",
+        alias: Some("showb"),
+        flags: "b:",
+        min: 0,
+        max: Some(0),
+        usage: "[-b buffer-name]",
+        build: |args| Ok(Command::ShowBuffer {
+            name: args.value('b'),
+        }),
+    },
+    CommandSpec {
+        name: "show-options",
+        alias: Some("show"),
+        flags: "g",
+        min: 0,
+        max: Some(1),
+        usage: "[-g] [option]",
+        build: |args| Ok(Command::ShowOptions {
+            name: args.positional.first().cloned(),
+            global: args.has('g'),
+        }),
+    },
+    CommandSpec {
+        name: "source-file",
+        alias: Some("source"),
+        flags: "q",
+        min: 1,
+        max: Some(1),
+        usage: "[-q] path",
+        build: |args| Ok(Command::SourceFile {
+            path: args.positional[0].clone(),
+            quiet: args.has('q'),
+        }),
+    },
+    CommandSpec {
+        name: "split-window",
+        alias: Some("splitw"),
+        flags: "c:hv",
+        min: 0,
+        max: Some(0),
+        usage: "[-hv] [-c start-directory]",
+        build: |args| Ok(Command::SplitWindow {
+            direction: if args.has('h') { Direction::Horizontal } else { Direction::Vertical },
+            cwd: args.value('c'),
+        }),
+    },
+    CommandSpec {
+        name: "switch-client",
+        alias: Some("switchc"),
+        flags: "T:",
+        min: 0,
+        max: Some(0),
+        usage: "-T key-table",
+        build: |args| {
+            let table = args.value('T').ok_or_else(|| anyhow!("missing -T"))?;
+            Ok(Command::SwitchTable(table))
+        },
+    },
+    CommandSpec {
+        name: "unbind-key",
+        alias: Some("unbind"),
+        flags: "nT:",
+        min: 1,
+        max: Some(1),
+        usage: "[-n] [-T key-table] key",
+        build: |args| {
+            let table = match args.value('T') {
+                Some(table) => table,
+                None if args.has('n') => ROOT_TABLE.to_owned(),
+                None => PREFIX_TABLE.to_owned(),
+            };
+            Ok(Command::UnbindKey {
+                table,
+                key: parse_key(&args.positional[0])?,
+            })
+        },
+    },
+];
+
+/// Parse a command made of its name and arguments.
+fn parse_words(words: &[String]) -> Result<Command> {
+    parse_targeted(words, false).map(|(command, _)| command)
+}
+
+/// Parse the commands given on the command line, where `;` words separate
+/// commands.
+///
+/// Commands that don't take `-t` themselves can be given a target with it.
+pub fn parse_command_line(words: &[String]) -> Result<Vec<(Command, Option<Target>)>> {
+    words.split(|word| word == ";")
+        .filter(|words| !words.is_empty())
+        .map(|words| parse_targeted(words, true))
+        .collect()
+}
+
+/// Parse a command, with a target if `target` and it doesn't take `-t`.
+fn parse_targeted(words: &[String], target: bool) -> Result<(Command, Option<Target>)> {
+    let name = &words[0];
+    let spec = COMMANDS.iter()
+        .find(|spec| spec.name == name || spec.alias == Some(name))
+        .ok_or_else(|| anyhow!("unknown command: {}", name))?;
+    let target = target && !spec.flags.contains('t');
+    let flags = if target {
+        format!("{}t:", spec.flags)
+    } else {
+        spec.flags.to_owned()
+    };
+    let mut args = parse_args(spec, &flags, &words[1..])
+        .map_err(|e| anyhow!("{}: {} (usage: {})", spec.name, e, spec.usage()))?;
+    let target = if target {
+        args.flags.remove(&'t').flatten()
+            .map(|value| value.parse().map_err(|e| anyhow!("{}: {}", spec.name, e)))
+            .transpose()?
+    } else {
+        None
+    };
+    let command = (spec.build)(args).map_err(|e| anyhow!("{}: {}", spec.name, e))?;
+    Ok((command, target))
+}
+
+/// Parse the flags and positional arguments of a command, which accepts
+/// `flags` in the format of the specs.
+///
+/// Flags come first and may be grouped, as in `-hv` or `-cDIR`. Parsing
+/// stops at the first positional argument, or after `--`.
+fn parse_args(spec: &CommandSpec, flags: &str, words: &[String]) -> Result<Args> {
+    let mut args = Args::default();
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        if word == "--" {
+            break;
+        }
+        if !word.starts_with('-') || word.len() == 1 {
+            args.positional.push(word.clone());
+            break;
+        }
+        let mut chars = word[1..].chars();
+        while let Some(flag) = chars.next() {
+            let position = flags.find(flag)
+                .filter(|_| flag != ':')
+                .ok_or_else(|| anyhow!("unknown flag -{}", flag))?;
+            if flags[position + 1..].starts_with(':') {
+                let value: String = chars.by_ref().collect();
+                let value = if value.is_empty() {
+                    words.next().cloned().ok_or_else(|| anyhow!("-{} expects a value", flag))?
+                } else {
+                    value
+                };
+                args.flags.insert(flag, Some(value));
+            } else {
+                args.flags.insert(flag, None);
+            }
+        }
+    }
+    args.positional.extend(words.cloned());
+
+    if args.positional.len() < spec.min {
+        return Err(anyhow!("too few arguments"));
+    }
+    if spec.max.is_some_and(|max| args.positional.len() > max) {
+        return Err(anyhow!("too many arguments"));
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        command::{
+            Command,
+            Target,
+        },
+        copy::{
+            CopyAction,
+            Search,
+            SearchDirection,
+        },
+        input::Key,
+        layout::{
+            Direction,
+            Side,
+        },
+        options::Level,
+    };
+    use super::{
+        parse,
+        parse_command_line,
+        tokenize,
+        Token,
+    };
+
+    fn words(words: &[&str]) -> Vec<Token> {
+        words.iter().map(|word| match *word {
+            ";" => Token::Separator,
+            word => Token::Word(word.to_owned()),
+        }).collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("split-window -h -c '/tmp/a b'").unwrap(),
+            words(&["split-window", "-h", "-c", "/tmp/a b"]));
+        assert_eq!(tokenize(r#"display "a \"b\"\t" # comment"#).unwrap(),
+            words(&["display", "a \"b\"\t"]));
+        assert_eq!(tokenize("neww; splitw ;killp").unwrap(),
+            words(&["neww", ";", "splitw", ";", "killp"]));
+        assert_eq!(tokenize(r"bind x neww \; splitw").unwrap()[3],
+            Token::Word(";".to_owned()));
+        assert_eq!(tokenize("send a;b").unwrap(), words(&["send", "a;b"]));
+        assert!(tokenize("display 'a").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("split-window -h -c /tmp").unwrap(), vec![Command::SplitWindow {
+            direction: Direction::Horizontal,
+            cwd: Some("/tmp".to_owned()),
+        }]);
+        assert_eq!(parse("splitw -c/tmp").unwrap(), vec![Command::SplitWindow {
+            direction: Direction::Vertical,
+            cwd: Some("/tmp".to_owned()),
+        }]);
+        assert_eq!(parse("select-pane -L ; resizep -D 5").unwrap(), vec![
+            Command::SelectPane(Side::Left),
+            Command::ResizePane(Side::Down, 5),
+        ]);
+        assert_eq!(parse("resizew -x 100").unwrap(), vec![Command::ResizeWindow {
+            width: Some(100),
+            height: None,
+        }]);
+        assert_eq!(parse("send-keys -l -- -x C-c").unwrap(), vec![Command::SendKeys {
+            keys: vec!["-x".to_owned(), "C-c".to_owned()],
+            literal: true,
+        }]);
+        assert_eq!(parse("copy-mode -u ; send -X next-word-end").unwrap(), vec![
+            Command::CopyMode { page_up: true },
+            Command::CopyAction(CopyAction::NextWordEnd),
+        ]);
+        assert_eq!(parse("send -X search-backward 'error\\d+'").unwrap(), vec![Command::CopySearch(Search {
+            pattern: "error\\d+".to_owned(),
+            direction: SearchDirection::Backward,
+            regex: true,
+            incremental: false,
+        })]);
+        assert_eq!(parse("command-prompt -i -p '(search up)' 'send -X search-backward-incremental \"%%\"'").unwrap(), vec![
+            Command::Prompt {
+                label: Some("(search up)".to_owned()),
+                incremental: true,
+                template: Some("send -X search-backward-incremental \"%%\"".to_owned()),
+            },
+        ]);
+        assert_eq!(parse("bind -r -T prefix C-x neww \\; next").unwrap(), vec![Command::BindKey {
+            table: "prefix".to_owned(),
+            key: Key::ctrl('x'),
+            repeat: true,
+            commands: vec![
+                Command::NewWindow { name: None, cwd: None, command: None },
+                Command::NextWindow,
+            ],
+        }]);
+        assert_eq!(parse("setb -b notes 'a b' ; pasteb -dr").unwrap(), vec![
+            Command::SetBuffer {
+                name: Some("notes".to_owned()),
+                data: "a b".to_owned(),
+            },
+            Command::PasteBuffer {
+                name: None,
+                delete: true,
+                raw: true,
+            },
+        ]);
+        assert_eq!(parse("save-buffer -a -b buffer3 log.txt").unwrap(), vec![Command::SaveBuffer {
+            name: Some("buffer3".to_owned()),
+            path: "log.txt".to_owned(),
+            append: true,
+        }]);
+        assert_eq!(parse("source -q ~/.terman.conf").unwrap(), vec![Command::SourceFile {
+            path: "~/.terman.conf".to_owned(),
+            quiet: true,
+        }]);
+        assert_eq!(parse("set -w synchronize-panes on ; set -gu status").unwrap(), vec![
+            Command::SetOption {
+                name: "synchronize-panes".to_owned(),
+                value: Some("on".to_owned()),
+                level: Some(Level::Window),
+            },
+            Command::SetOption {
+                name: "status".to_owned(),
+                value: None,
+                level: Some(Level::Global),
+            },
+        ]);
+        assert_eq!(parse("detach").unwrap(), vec![Command::DetachClient]);
+        assert_eq!(parse("").unwrap(), vec![]);
+        assert_eq!(parse("new -d -s work make test").unwrap(), vec![Command::NewSession {
+            name: Some("work".to_owned()),
+            detached: true,
+            command: Some("make test".to_owned()),
+        }]);
+        assert_eq!(parse("ls ; rename dev ; kill-session").unwrap(), vec![
+            Command::ListSessions,
+            Command::RenameSession("dev".to_owned()),
+            Command::KillSession,
+        ]);
+    }
+
+    #[test]
+    fn test_parse_command_line() {
+        let words = |line: &str| line.split(' ').map(str::to_owned).collect::<Vec<_>>();
+        let commands = parse_command_line(&words("send-keys -t work:1.0 make Enter ; attach -r -t work")).unwrap();
+        assert_eq!(commands, vec![
+            (Command::SendKeys {
+                keys: vec!["make".to_owned(), "Enter".to_owned()],
+                literal: false,
+            }, Some(Target {
+                session: Some("work".to_owned()),
+                window: Some(1),
+                pane: Some(0),
+            })),
+            (Command::AttachSession { read_only: true }, Some(Target {
+                session: Some("work".to_owned()),
+                window: None,
+                pane: None,
+            })),
+        ]);
+        // Commands that take -t keep it.
+        assert_eq!(parse_command_line(&words("selectw -t 2")).unwrap(), vec![(Command::SelectWindow(2), None)]);
+        // Targets are only given on the command line.
+        assert_eq!(parse("kill-session -t work").unwrap_err().to_string(),
+            "kill-session: unknown flag -t (usage: kill-session)");
+        assert_eq!(parse_command_line(&words("ls -t a:b")).unwrap_err().to_string(),
+            "list-sessions: bad window in target: a:b");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |line| parse(line).unwrap_err().to_string();
+        assert_eq!(error("foo"), "unknown command: foo");
+        assert_eq!(error("splitw -x"),
+            "split-window: unknown flag -x (usage: split-window [-hv] [-c start-directory])");
+        assert_eq!(error("splitw -c"),
+            "split-window: -c expects a value (usage: split-window [-hv] [-c start-directory])");
+        assert_eq!(error("renamew"), "rename-window: too few arguments (usage: rename-window new-name)");
+        assert_eq!(error("killp now"), "kill-pane: too many arguments (usage: kill-pane)");
+        assert_eq!(error("select-pane"), "select-pane: missing -D, -L, -R or -U");
+        assert_eq!(error("resizep -L x"), "resize-pane: not a number: x");
+        assert_eq!(error("set status"), "set-option: missing value");
+        assert_eq!(error("send -X jump"), "send-keys: unknown copy mode command: jump");
+        assert_eq!(error("set -u status off"), "set-option: -u takes no value");
+        assert_eq!(error("bind Foo killp"), "bind-key: unknown key: Foo");
+        assert_eq!(error("neww ; foo"), "unknown command: foo");
+    }
+}
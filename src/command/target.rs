@@ -0,0 +1,69 @@
+use {
+    std::str::FromStr,
+    anyhow::{
+        anyhow,
+        Result,
+    },
+};
+
+/// Where a command run from the command line applies, given with `-t`:
+/// `session`, or `[session]:[window][.pane]`, where windows are given by
+/// index and panes by their position in the window.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Target {
+    pub session: Option<String>,
+    pub window: Option<usize>,
+    pub pane: Option<usize>,
+}
+
+impl FromStr for Target {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (session, rest) = match s.find(':') {
+            Some(colon) => (&s[..colon], Some(&s[colon + 1..])),
+            None => (s, None),
+        };
+        let (window, pane) = match rest.map(|rest| (rest, rest.find('.'))) {
+            Some((rest, Some(dot))) => (&rest[..dot], &rest[dot + 1..]),
+            Some((rest, None)) => (rest, ""),
+            None => ("", ""),
+        };
+        let number = |value: &str, what: &str| match value {
+            "" => Ok(None),
+            value => value.parse()
+                .map(Some)
+                .map_err(|_| anyhow!("bad {} in target: {}", what, s)),
+        };
+        Ok(Self {
+            session: Some(session).filter(|session| !session.is_empty()).map(str::to_owned),
+            window: number(window, "window")?,
+            pane: number(pane, "pane")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Target;
+
+    fn target(session: Option<&str>, window: Option<usize>, pane: Option<usize>) -> Target {
+        Target {
+            session: session.map(str::to_owned),
+            window,
+            pane,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("work".parse::<Target>().unwrap(), target(Some("work"), None, None));
+        assert_eq!("work:1".parse::<Target>().unwrap(), target(Some("work"), Some(1), None));
+        assert_eq!("work:1.0".parse::<Target>().unwrap(), target(Some("work"), Some(1), Some(0)));
+        assert_eq!(":2".parse::<Target>().unwrap(), target(None, Some(2), None));
+        assert_eq!(":.1".parse::<Target>().unwrap(), target(None, None, Some(1)));
+        assert_eq!("".parse::<Target>().unwrap(), Target::default());
+        assert_eq!("work:x".parse::<Target>().unwrap_err().to_string(), "bad window in target: work:x");
+        assert_eq!("work:1.y".parse::<Target>().unwrap_err().to_string(), "bad pane in target: work:1.y");
+    }
+}
@@ -0,0 +1,827 @@
+mod search;
+
+use {
+    std::{
+        cmp::{
+            max,
+            min,
+        },
+        fmt,
+        str::FromStr,
+    },
+    anyhow::{
+        anyhow,
+        Error,
+        Result,
+    },
+    crate::{
+        ansi::{
+            Color,
+            Flags,
+            Handler,
+        },
+        grid::{
+            semantic::{
+                self,
+                CharClass,
+            },
+            Cell,
+            Grid,
+            Line,
+        },
+        screen::Screen,
+        util::Point,
+    },
+    self::search::Match,
+};
+
+pub use self::search::{
+    Search,
+    SearchDirection,
+};
+
+/// How the selection extends from where it started to the cursor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SelectionMode {
+    /// Every cell between the two ends, wrapping at the end of lines.
+    Char,
+    /// The whole lines of both ends and the lines in between.
+    Line,
+    /// The rectangle with the two ends as corners.
+    Block,
+}
+
+/// Something done in copy mode, bound to keys with `send-keys -X`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CopyAction {
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    StartOfLine,
+    EndOfLine,
+    /// Move to the first character of the line that isn't blank.
+    BackToIndentation,
+    NextWord,
+    PreviousWord,
+    NextWordEnd,
+    /// Move to the first line of the view.
+    TopLine,
+    MiddleLine,
+    BottomLine,
+    /// Move to the first line of the history.
+    HistoryTop,
+    /// Move to the last line of the screen.
+    HistoryBottom,
+    PageUp,
+    PageDown,
+    HalfpageUp,
+    HalfpageDown,
+    /// Move the view up a line, keeping the cursor on its line of text.
+    ScrollUp,
+    ScrollDown,
+    BeginSelection,
+    /// Select the lines of the cursor, whole lines of text when they
+    /// wrapped.
+    SelectLine,
+    /// Select the URL, path, quoted string or word under the cursor.
+    SelectWord,
+    RectangleToggle,
+    ClearSelection,
+    /// Move the cursor to the other end of the selection.
+    OtherEnd,
+    /// Move to the next match of the last search, in its direction.
+    SearchAgain,
+    /// Move to the next match of the last search, the other way.
+    SearchReverse,
+    CopySelection,
+    CopySelectionAndCancel,
+    Cancel,
+}
+
+const ACTION_NAMES: [(&str, CopyAction); 32] = [
+    ("cursor-left", CopyAction::CursorLeft),
+    ("cursor-right", CopyAction::CursorRight),
+    ("cursor-up", CopyAction::CursorUp),
+    ("cursor-down", CopyAction::CursorDown),
+    ("start-of-line", CopyAction::StartOfLine),
+    ("end-of-line", CopyAction::EndOfLine),
+    ("back-to-indentation", CopyAction::BackToIndentation),
+    ("next-word", CopyAction::NextWord),
+    ("previous-word", CopyAction::PreviousWord),
+    ("next-word-end", CopyAction::NextWordEnd),
+    ("top-line", CopyAction::TopLine),
+    ("middle-line", CopyAction::MiddleLine),
+    ("bottom-line", CopyAction::BottomLine),
+    ("history-top", CopyAction::HistoryTop),
+    ("history-bottom", CopyAction::HistoryBottom),
+    ("page-up", CopyAction::PageUp),
+    ("page-down", CopyAction::PageDown),
+    ("halfpage-up", CopyAction::HalfpageUp),
+    ("halfpage-down", CopyAction::HalfpageDown),
+    ("scroll-up", CopyAction::ScrollUp),
+    ("scroll-down", CopyAction::ScrollDown),
+    ("begin-selection", CopyAction::BeginSelection),
+    ("select-line", CopyAction::SelectLine),
+    ("select-word", CopyAction::SelectWord),
+    ("rectangle-toggle", CopyAction::RectangleToggle),
+    ("clear-selection", CopyAction::ClearSelection),
+    ("other-end", CopyAction::OtherEnd),
+    ("search-again", CopyAction::SearchAgain),
+    ("search-reverse", CopyAction::SearchReverse),
+    ("copy-selection", CopyAction::CopySelection),
+    ("copy-selection-and-cancel", CopyAction::CopySelectionAndCancel),
+    ("cancel", CopyAction::Cancel),
+];
+
+impl FromStr for CopyAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ACTION_NAMES.iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, action)| *action)
+            .ok_or_else(|| anyhow!("unknown copy mode command: {}", s))
+    }
+}
+
+impl fmt::Display for CopyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = ACTION_NAMES.iter().find(|(_, action)| action == self).unwrap();
+        write!(f, "{}", name)
+    }
+}
+
+/// What happens to copy mode after an action.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CopyOutcome {
+    Continue,
+    /// Leave copy mode.
+    Cancel,
+    /// Store the text in a paste buffer.
+    Copy(String),
+    /// Store the text in a paste buffer and leave copy mode.
+    CopyAndCancel(String),
+}
+
+/// The last search in copy mode and its matches.
+#[derive(Clone, Debug)]
+struct SearchState {
+    search: Search,
+    matches: Vec<Match>,
+    /// Where the cursor was when the search started, incremental searches
+    /// are done again from there.
+    origin: Point,
+}
+
+/// A pane in copy mode, which shows a frozen copy of its history and
+/// screen with a cursor of its own.
+///
+/// The pane keeps reading the output of its process meanwhile, copy mode
+/// shows it once it is entered again.
+#[derive(Clone, Debug)]
+pub struct CopyMode {
+    /// The history followed by the screen, as they were when copy mode was
+    /// entered.
+    grid: Grid,
+    /// Number of lines shown.
+    height: usize,
+    /// Line of `grid` shown at the top of the pane.
+    top: usize,
+    /// Position of the cursor in `grid`.
+    cursor: Point,
+    /// Where the selection started, if there is one.
+    anchor: Option<Point>,
+    mode: SelectionMode,
+    search: Option<SearchState>,
+    /// Characters that separate words, besides blanks.
+    separators: String,
+}
+
+impl CopyMode {
+    /// Enter copy mode over the history and contents of `screen`, showing
+    /// the screen as it is. Words are split at blanks and `separators`.
+    pub fn new(screen: &Screen, separators: &str) -> Self {
+        let shown = screen.grid();
+        let history = shown.history().len();
+        let lines = shown.history().iter()
+            .chain((0..shown.size.y).filter_map(|y| shown.line(y)))
+            .cloned();
+        let grid = Grid::from_lines(lines, shown.size.x);
+        let cursor = *screen.cursor();
+        Self {
+            grid,
+            height: shown.size.y,
+            top: history,
+            cursor: Point::new(cursor.x, cursor.y + history),
+            anchor: None,
+            mode: SelectionMode::Char,
+            search: None,
+            separators: separators.to_owned(),
+        }
+    }
+
+    /// Change the number of lines shown, when the pane is resized.
+    pub fn resize(&mut self, size: Point) {
+        self.height = max(size.y, 1);
+        self.top = min(self.top, self.max_top());
+        self.show_cursor();
+    }
+
+    /// Get the position of the cursor in the view.
+    pub fn cursor(&self) -> Point {
+        Point::new(self.cursor.x, self.cursor.y - self.top)
+    }
+
+    /// Move the cursor to `point` of the view.
+    pub fn move_to(&mut self, point: Point) {
+        let y = min(self.top + point.y, self.grid.size.y - 1);
+        self.cursor = Point::new(min(point.x, self.grid.size.x - 1), y);
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    /// Get the number of lines the view is above the bottom, and the
+    /// number of lines of history.
+    pub fn position(&self) -> (usize, usize) {
+        (self.max_top() - self.top, self.max_top())
+    }
+
+    /// Get the number of the match at the cursor, if it is on one, and the
+    /// number of matches of the last search.
+    pub fn search_position(&self) -> Option<(Option<usize>, usize)> {
+        let state = self.search.as_ref()?;
+        let current = state.matches.iter()
+            .position(|(start, _)| *start == self.cursor)
+            .map(|i| i + 1);
+        Some((current, state.matches.len()))
+    }
+
+    /// Get line `y` of the view, with the matches of the last search
+    /// highlighted and the selected cells in inverse video.
+    pub fn line(&self, y: usize) -> Option<Line> {
+        let y = self.top + y;
+        let mut line = self.grid.line(y)?.clone();
+        let matches = self.search.iter()
+            .flat_map(|state| state.matches.iter())
+            .filter(|(start, end)| start.y <= y && y <= end.y);
+        for &(start, end) in matches {
+            let bg = if start == self.cursor { Color::Magenta } else { Color::Cyan };
+            for (x, cell) in line.iter_mut().enumerate() {
+                let point = Point::new(x, y);
+                if start <= point && point <= end {
+                    cell.attributes.fg = Color::Black;
+                    cell.attributes.bg = bg;
+                }
+            }
+        }
+        if self.anchor.is_some() {
+            for (x, cell) in line.iter_mut().enumerate() {
+                if self.is_selected(Point::new(x, y)) {
+                    cell.attributes.flags.toggle(Flags::INVERSE);
+                }
+            }
+        }
+        Some(line)
+    }
+
+    /// Get the ends of the selection, in order.
+    fn selection(&self) -> Option<(Point, Point)> {
+        let anchor = self.anchor?;
+        let (start, end) = if anchor < self.cursor {
+            (anchor, self.cursor)
+        } else {
+            (self.cursor, anchor)
+        };
+        if self.mode != SelectionMode::Line {
+            return Some((start, end));
+        }
+        // Lines that wrapped are selected with the rest of their text.
+        let mut first = start.y;
+        while first > 0 && self.is_wrapped(first - 1) {
+            first -= 1;
+        }
+        let mut last = end.y;
+        while last + 1 < self.grid.size.y && self.is_wrapped(last) {
+            last += 1;
+        }
+        Some((Point::new(0, first), Point::new(self.grid.size.x - 1, last)))
+    }
+
+    /// Returns whether the text of line `y` goes on on the next line.
+    fn is_wrapped(&self, y: usize) -> bool {
+        self.grid.line(y).is_some_and(|line| line.wrapped)
+    }
+
+    fn is_selected(&self, point: Point) -> bool {
+        let (start, end) = match self.selection() {
+            Some(selection) => selection,
+            None => return false,
+        };
+        match self.mode {
+            SelectionMode::Char => start <= point && point <= end,
+            SelectionMode::Line => start.y <= point.y && point.y <= end.y,
+            SelectionMode::Block => {
+                let (left, right) = self.block_columns();
+                start.y <= point.y && point.y <= end.y && left <= point.x && point.x <= right
+            },
+        }
+    }
+
+    /// Get the first and last columns of a block selection.
+    fn block_columns(&self) -> (usize, usize) {
+        let anchor = self.anchor.unwrap_or(self.cursor);
+        (min(anchor.x, self.cursor.x), max(anchor.x, self.cursor.x))
+    }
+
+    /// Get the text of the selection. Each line ends with a new line,
+    /// without its trailing blanks.
+    pub fn selection_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        let width = self.grid.size.x;
+        let cells: Vec<(Point, &Cell)> = match self.mode {
+            SelectionMode::Char => self.grid.selection(start..=end).collect(),
+            SelectionMode::Line => {
+                self.grid.selection(Point::new(0, start.y)..=Point::new(width - 1, end.y)).collect()
+            },
+            SelectionMode::Block => {
+                let (left, right) = self.block_columns();
+                self.grid.block(Point::new(left, start.y)..=Point::new(right, end.y)).collect()
+            },
+        };
+        let mut lines: Vec<(usize, String)> = Vec::new();
+        for (point, cell) in cells {
+            if lines.last().is_none_or(|(y, _)| *y != point.y) {
+                lines.push((point.y, String::new()));
+            }
+            lines.last_mut().unwrap().1.push(cell.ch.unwrap_or(' '));
+        }
+        let mut text = String::new();
+        for (i, (y, line)) in lines.iter().enumerate() {
+            let last = i + 1 == lines.len();
+            // Lines that wrapped go on with the next one as they are.
+            if self.mode != SelectionMode::Block && !last && self.is_wrapped(*y) {
+                text.push_str(line);
+                continue;
+            }
+            text.push_str(line.trim_end());
+            // A selection that stops within a line doesn't take its end.
+            let line_end = match self.mode {
+                SelectionMode::Char => !last || end.x + 1 >= width,
+                _ => true,
+            };
+            if line_end {
+                text.push('\n');
+            }
+        }
+        Some(text)
+    }
+
+    /// Do `action`, returning what should happen to copy mode.
+    pub fn apply(&mut self, action: CopyAction) -> CopyOutcome {
+        let width = self.grid.size.x;
+        let last_line = self.grid.size.y - 1;
+        match action {
+            CopyAction::CursorLeft => self.cursor.x = self.cursor.x.saturating_sub(1),
+            CopyAction::CursorRight => self.cursor.x = min(self.cursor.x + 1, width - 1),
+            CopyAction::CursorUp => self.cursor.y = self.cursor.y.saturating_sub(1),
+            CopyAction::CursorDown => self.cursor.y = min(self.cursor.y + 1, last_line),
+            CopyAction::StartOfLine => self.cursor.x = 0,
+            CopyAction::EndOfLine => self.cursor.x = self.line_end(self.cursor.y),
+            CopyAction::BackToIndentation => {
+                self.cursor.x = (0..width)
+                    .find(|&x| self.class_at(Point::new(x, self.cursor.y)) != CharClass::Blank)
+                    .unwrap_or(0);
+            },
+            CopyAction::NextWord => self.next_word(),
+            CopyAction::PreviousWord => self.previous_word(),
+            CopyAction::NextWordEnd => self.next_word_end(),
+            CopyAction::TopLine => self.cursor.y = self.top,
+            CopyAction::MiddleLine => self.cursor.y = min(self.top + (self.height - 1) / 2, last_line),
+            CopyAction::BottomLine => self.cursor.y = min(self.top + self.height - 1, last_line),
+            CopyAction::HistoryTop => self.cursor = Point::new(0, 0),
+            CopyAction::HistoryBottom => {
+                self.cursor = Point::new(self.line_end(last_line), last_line);
+            },
+            CopyAction::PageUp => self.scroll_by(-(self.height as isize)),
+            CopyAction::PageDown => self.scroll_by(self.height as isize),
+            CopyAction::HalfpageUp => self.scroll_by(-(self.height as isize / 2)),
+            CopyAction::HalfpageDown => self.scroll_by(self.height as isize / 2),
+            CopyAction::ScrollUp => {
+                self.top = self.top.saturating_sub(1);
+                self.cursor.y = min(self.cursor.y, self.top + self.height - 1);
+            },
+            CopyAction::ScrollDown => {
+                self.top = min(self.top + 1, self.max_top());
+                self.cursor.y = max(self.cursor.y, self.top);
+            },
+            CopyAction::BeginSelection => self.begin_selection(SelectionMode::Char),
+            CopyAction::SelectLine => self.begin_selection(SelectionMode::Line),
+            CopyAction::SelectWord => {
+                if let Some((start, end)) = semantic::semantic_at(&self.grid, self.cursor, &self.separators) {
+                    self.anchor = Some(start);
+                    self.cursor = end;
+                    self.mode = SelectionMode::Char;
+                }
+            },
+            CopyAction::RectangleToggle => {
+                self.mode = match self.mode {
+                    SelectionMode::Block => SelectionMode::Char,
+                    _ => SelectionMode::Block,
+                };
+            },
+            CopyAction::ClearSelection => self.anchor = None,
+            CopyAction::OtherEnd => {
+                if let Some(anchor) = self.anchor.as_mut() {
+                    std::mem::swap(anchor, &mut self.cursor);
+                }
+            },
+            CopyAction::SearchAgain | CopyAction::SearchReverse => {
+                if let Some(state) = self.search.as_ref() {
+                    let direction = match action {
+                        CopyAction::SearchAgain => state.search.direction,
+                        _ => state.search.direction.reverse(),
+                    };
+                    self.jump_to_match(direction, self.cursor, false);
+                }
+            },
+            CopyAction::CopySelection => {
+                return match self.selection_text() {
+                    Some(text) => CopyOutcome::Copy(text),
+                    None => CopyOutcome::Continue,
+                };
+            },
+            CopyAction::CopySelectionAndCancel => {
+                return match self.selection_text() {
+                    Some(text) => CopyOutcome::CopyAndCancel(text),
+                    None => CopyOutcome::Cancel,
+                };
+            },
+            CopyAction::Cancel => return CopyOutcome::Cancel,
+        }
+        self.show_cursor();
+        CopyOutcome::Continue
+    }
+
+    /// Search the history and the screen, moving to the next match.
+    ///
+    /// An incremental search goes on from where the previous incremental
+    /// search started, so that the match follows the pattern as it is
+    /// typed. Other searches with an empty pattern are done again with the
+    /// last pattern.
+    pub fn search(&mut self, mut search: Search) -> Result<()> {
+        let previous = self.search.take();
+        let origin = match &previous {
+            Some(state) if search.incremental && state.search.incremental => state.origin,
+            _ => self.cursor,
+        };
+        if search.pattern.is_empty() {
+            match previous {
+                Some(state) if !search.incremental => search.pattern = state.search.pattern,
+                _ => {
+                    // Clearing an incremental search goes back to where it
+                    // started.
+                    if search.incremental {
+                        self.cursor = origin;
+                        self.show_cursor();
+                    }
+                    return Ok(());
+                },
+            }
+        }
+        let matches = search::find_matches(&self.grid, &search.compile()?);
+        let direction = search.direction;
+        let incremental = search.incremental;
+        self.search = Some(SearchState {
+            search,
+            matches,
+            origin,
+        });
+        self.jump_to_match(direction, origin, incremental);
+        Ok(())
+    }
+
+    /// Stop going on with the last incremental search, so that the next one
+    /// starts from the cursor.
+    pub fn finish_search(&mut self) {
+        if let Some(state) = self.search.as_mut() {
+            state.search.incremental = false;
+        }
+    }
+
+    /// Move to the first match after `from` in `direction`, or at `from` if
+    /// `inclusive`. The search goes on from the other end when there is no
+    /// match until the end.
+    fn jump_to_match(&mut self, direction: SearchDirection, from: Point, inclusive: bool) {
+        let matches = match self.search.as_ref() {
+            Some(state) => &state.matches,
+            None => return,
+        };
+        let found = match direction {
+            SearchDirection::Forward => matches.iter()
+                .find(|(start, _)| *start > from || inclusive && *start == from)
+                .or_else(|| matches.first()),
+            SearchDirection::Backward => matches.iter().rev()
+                .find(|(start, _)| *start < from || inclusive && *start == from)
+                .or_else(|| matches.last()),
+        };
+        if let Some(&(start, _)) = found {
+            self.cursor = start;
+            self.show_cursor();
+        }
+    }
+
+    fn begin_selection(&mut self, mode: SelectionMode) {
+        self.anchor = Some(self.cursor);
+        self.mode = mode;
+    }
+
+    /// Get the line shown at the top when the view is at the bottom.
+    fn max_top(&self) -> usize {
+        self.grid.size.y.saturating_sub(self.height)
+    }
+
+    /// Move the view and the cursor by `delta` lines.
+    fn scroll_by(&mut self, delta: isize) {
+        let last_line = self.grid.size.y - 1;
+        let shift = |value: usize, limit: usize| {
+            min(max(value as isize + delta, 0) as usize, limit)
+        };
+        self.top = shift(self.top, self.max_top());
+        self.cursor.y = shift(self.cursor.y, last_line);
+    }
+
+    /// Scroll the view so that the cursor is shown.
+    fn show_cursor(&mut self) {
+        if self.cursor.y < self.top {
+            self.top = self.cursor.y;
+        } else if self.cursor.y >= self.top + self.height {
+            self.top = self.cursor.y + 1 - self.height;
+        }
+    }
+
+    /// Get the column of the last character of line `y` that isn't blank.
+    fn line_end(&self, y: usize) -> usize {
+        (0..self.grid.size.x).rev()
+            .find(|&x| self.class_at(Point::new(x, y)) != CharClass::Blank)
+            .unwrap_or(0)
+    }
+
+    fn class_at(&self, point: Point) -> CharClass {
+        CharClass::of(self.grid.cell(point).and_then(|cell| cell.ch), &self.separators)
+    }
+
+    /// Get the point after `point`, going through the lines in order.
+    fn next_point(&self, point: Point) -> Option<Point> {
+        if point.x + 1 < self.grid.size.x {
+            Some(Point::new(point.x + 1, point.y))
+        } else if point.y + 1 < self.grid.size.y {
+            Some(Point::new(0, point.y + 1))
+        } else {
+            None
+        }
+    }
+
+    fn previous_point(&self, point: Point) -> Option<Point> {
+        if point.x > 0 {
+            Some(Point::new(point.x - 1, point.y))
+        } else if point.y > 0 {
+            Some(Point::new(self.grid.size.x - 1, point.y - 1))
+        } else {
+            None
+        }
+    }
+
+    /// Move to the start of the next word.
+    fn next_word(&mut self) {
+        let class = self.class_at(self.cursor);
+        let mut point = self.cursor;
+        // Leave the current word, then the blanks after it.
+        while let Some(next) = self.next_point(point) {
+            let leaving = next.y != point.y;
+            point = next;
+            if leaving || self.class_at(point) != class {
+                break;
+            }
+        }
+        while self.class_at(point) == CharClass::Blank {
+            match self.next_point(point) {
+                Some(next) => point = next,
+                None => break,
+            }
+        }
+        self.cursor = point;
+    }
+
+    /// Move to the start of the current word, or of the previous one when
+    /// already there.
+    fn previous_word(&mut self) {
+        let mut point = match self.previous_point(self.cursor) {
+            Some(point) => point,
+            None => return,
+        };
+        while self.class_at(point) == CharClass::Blank {
+            match self.previous_point(point) {
+                Some(previous) => point = previous,
+                None => break,
+            }
+        }
+        let class = self.class_at(point);
+        while let Some(previous) = self.previous_point(point) {
+            if previous.y != point.y || self.class_at(previous) != class {
+                break;
+            }
+            point = previous;
+        }
+        self.cursor = point;
+    }
+
+    /// Move to the end of the current word, or of the next one when
+    /// already there.
+    fn next_word_end(&mut self) {
+        let mut point = match self.next_point(self.cursor) {
+            Some(point) => point,
+            None => return,
+        };
+        while self.class_at(point) == CharClass::Blank {
+            match self.next_point(point) {
+                Some(next) => point = next,
+                None => break,
+            }
+        }
+        let class = self.class_at(point);
+        while let Some(next) = self.next_point(point) {
+            if next.y != point.y || self.class_at(next) != class {
+                break;
+            }
+            point = next;
+        }
+        self.cursor = point;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ansi::{
+            Color,
+            Flags,
+            Processor,
+        },
+        screen::Screen,
+        util::{
+            point::P,
+            Point,
+        },
+    };
+    use super::{
+        CopyAction,
+        CopyMode,
+        CopyOutcome,
+        Search,
+    };
+
+    const SEPARATORS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^`{|}~";
+
+    /// Get a screen of `size` after printing `text`, with a history.
+    fn screen(size: Point, text: &str) -> Screen {
+        let mut screen = Screen::new(size);
+        screen.set_history_limit(100);
+        Processor::default().advance(text.as_bytes(), &mut screen, &mut Vec::new());
+        screen
+    }
+
+    fn apply(copy: &mut CopyMode, actions: &[CopyAction]) -> CopyOutcome {
+        let mut outcome = CopyOutcome::Continue;
+        for action in actions {
+            outcome = copy.apply(*action);
+        }
+        outcome
+    }
+
+    #[test]
+    fn test_navigation() {
+        let screen = screen(P(10, 2), "one two\r\nthree\r\nfoo.bar x\r\n");
+        let mut copy = CopyMode::new(&screen, SEPARATORS);
+        // Two lines went to the history, the cursor is on the last line.
+        assert_eq!(copy.position(), (0, 2));
+        assert_eq!(copy.cursor(), P(0, 1));
+
+        apply(&mut copy, &[CopyAction::CursorUp]);
+        assert_eq!(copy.cursor, P(0, 2));
+        apply(&mut copy, &[CopyAction::NextWord]);
+        assert_eq!(copy.cursor, P(3, 2));
+        apply(&mut copy, &[CopyAction::NextWord]);
+        assert_eq!(copy.cursor, P(4, 2));
+        apply(&mut copy, &[CopyAction::NextWordEnd]);
+        assert_eq!(copy.cursor, P(6, 2));
+        apply(&mut copy, &[CopyAction::PreviousWord, CopyAction::PreviousWord]);
+        assert_eq!(copy.cursor, P(3, 2));
+        apply(&mut copy, &[CopyAction::EndOfLine]);
+        assert_eq!(copy.cursor, P(8, 2));
+
+        apply(&mut copy, &[CopyAction::HistoryTop]);
+        assert_eq!(copy.cursor, P(0, 0));
+        assert_eq!(copy.position(), (2, 2));
+        assert_eq!(copy.cursor(), P(0, 0));
+        apply(&mut copy, &[CopyAction::PageDown]);
+        assert_eq!(copy.position(), (0, 2));
+        apply(&mut copy, &[CopyAction::HistoryBottom]);
+        assert_eq!(copy.cursor, P(0, 3));
+    }
+
+    #[test]
+    fn test_selection() {
+        let screen = screen(P(10, 3), "one two\r\nthree  \r\nfour five");
+        let mut copy = CopyMode::new(&screen, SEPARATORS);
+        apply(&mut copy, &[CopyAction::HistoryTop, CopyAction::NextWord, CopyAction::BeginSelection]);
+        apply(&mut copy, &[CopyAction::CursorDown, CopyAction::CursorDown]);
+        assert_eq!(copy.selection_text().unwrap(), "two\nthree\nfour");
+
+        apply(&mut copy, &[CopyAction::SelectLine, CopyAction::CursorUp]);
+        assert_eq!(copy.selection_text().unwrap(), "three\nfour five\n");
+
+        apply(&mut copy, &[
+            CopyAction::HistoryTop,
+            CopyAction::CursorRight,
+            CopyAction::BeginSelection,
+            CopyAction::RectangleToggle,
+            CopyAction::CursorDown,
+            CopyAction::CursorDown,
+            CopyAction::CursorRight,
+        ]);
+        assert_eq!(copy.selection_text().unwrap(), "ne\nhr\nou\n");
+        assert!(copy.line(1).unwrap()[1].attributes.flags.contains(Flags::INVERSE));
+        assert!(!copy.line(1).unwrap()[3].attributes.flags.contains(Flags::INVERSE));
+
+        assert_eq!(copy.apply(CopyAction::CopySelectionAndCancel), CopyOutcome::CopyAndCancel("ne\nhr\nou\n".to_owned()));
+        apply(&mut copy, &[CopyAction::ClearSelection]);
+        assert_eq!(copy.apply(CopyAction::CopySelectionAndCancel), CopyOutcome::Cancel);
+    }
+
+    #[test]
+    fn test_semantic_selection() {
+        // The URL wraps onto the second line.
+        let screen = screen(P(10, 3), "ab https://x.io/p  \r\nnext");
+        let mut copy = CopyMode::new(&screen, SEPARATORS);
+        apply(&mut copy, &[CopyAction::HistoryTop, CopyAction::NextWord, CopyAction::SelectWord]);
+        assert_eq!(copy.cursor, P(6, 1));
+        assert_eq!(copy.selection_text().unwrap(), "https://x.io/p");
+        apply(&mut copy, &[CopyAction::SelectLine]);
+        assert_eq!(copy.selection_text().unwrap(), "ab https://x.io/p\n");
+        apply(&mut copy, &[CopyAction::CursorDown]);
+        assert_eq!(copy.selection_text().unwrap(), "ab https://x.io/p\nnext\n");
+    }
+
+    #[test]
+    fn test_move_to() {
+        let screen = screen(P(10, 3), "one\r\ntwo\r\nthree\r\nfour");
+        let mut copy = CopyMode::new(&screen, SEPARATORS);
+        apply(&mut copy, &[CopyAction::ScrollUp]);
+        copy.move_to(P(1, 1));
+        assert_eq!(copy.cursor, P(1, 1));
+        assert!(!copy.has_selection());
+        apply(&mut copy, &[CopyAction::BeginSelection]);
+        copy.move_to(P(20, 2));
+        assert_eq!(copy.cursor, P(9, 2));
+        assert!(copy.has_selection());
+        assert_eq!(copy.selection_text().unwrap(), "wo\nthree\n");
+    }
+
+    #[test]
+    fn test_search() {
+        let screen = screen(P(10, 3), "error: 1\r\nok\r\nno error\r\nerror: 2");
+        let mut copy = CopyMode::new(&screen, SEPARATORS);
+        let search = |name: &str, pattern: &str| Search::from_name(name, pattern.to_owned()).unwrap();
+        // The cursor is after the text, the search goes on from the top.
+        copy.search(search("search-forward", "error")).unwrap();
+        assert_eq!(copy.cursor, P(0, 0));
+        assert_eq!(copy.search_position(), Some((Some(1), 3)));
+        apply(&mut copy, &[CopyAction::SearchAgain]);
+        assert_eq!(copy.cursor, P(3, 2));
+        apply(&mut copy, &[CopyAction::SearchReverse, CopyAction::SearchReverse]);
+        assert_eq!(copy.cursor, P(0, 3));
+        // The view starts at the second line.
+        assert_eq!(copy.line(2).unwrap()[0].attributes.bg, Color::Magenta);
+        assert_eq!(copy.line(1).unwrap()[3].attributes.bg, Color::Cyan);
+        assert_eq!(copy.line(1).unwrap()[2].attributes.bg, Color::Background);
+
+        copy.search(search("search-backward", "^e\\w+: \\w")).unwrap();
+        assert_eq!(copy.cursor, P(0, 0));
+        assert_eq!(copy.search_position(), Some((Some(1), 2)));
+        assert!(copy.search(search("search-backward", "(")).is_err());
+
+        // Incremental searches follow the pattern from where they started.
+        apply(&mut copy, &[CopyAction::CursorDown]);
+        copy.finish_search();
+        for (pattern, cursor) in &[("o", P(0, 1)), ("or", P(6, 2)), ("o", P(0, 1)), ("or", P(6, 2))] {
+            copy.search(search("search-forward-incremental", pattern)).unwrap();
+            assert_eq!(copy.cursor, *cursor);
+        }
+        copy.search(search("search-forward-incremental", "")).unwrap();
+        assert_eq!(copy.cursor, P(0, 1));
+        assert_eq!(copy.search_position(), None);
+    }
+}
@@ -0,0 +1,134 @@
+use {
+    anyhow::Result,
+    crate::{
+        grid::{
+            semantic::LogicalLine,
+            Grid,
+        },
+        util::{
+            regex::Regex,
+            Point,
+        },
+    },
+};
+
+/// Which way a search goes through the lines.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+impl SearchDirection {
+    pub fn reverse(self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+        }
+    }
+}
+
+/// A search in copy mode, sent with `send-keys -X search-forward pattern`
+/// and the like.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Search {
+    pub pattern: String,
+    pub direction: SearchDirection,
+    /// Whether the pattern is a regular expression rather than text.
+    pub regex: bool,
+    /// Whether the search is done again as the pattern is typed, from
+    /// where it started.
+    pub incremental: bool,
+}
+
+const SEARCH_NAMES: [(&str, SearchDirection, bool, bool); 6] = [
+    ("search-forward", SearchDirection::Forward, true, false),
+    ("search-backward", SearchDirection::Backward, true, false),
+    ("search-forward-text", SearchDirection::Forward, false, false),
+    ("search-backward-text", SearchDirection::Backward, false, false),
+    ("search-forward-incremental", SearchDirection::Forward, false, true),
+    ("search-backward-incremental", SearchDirection::Backward, false, true),
+];
+
+impl Search {
+    /// Get the search done by copy mode command `name`, if it is a search
+    /// command.
+    pub fn from_name(name: &str, pattern: String) -> Option<Self> {
+        SEARCH_NAMES.iter()
+            .find(|(search_name, ..)| *search_name == name)
+            .map(|&(_, direction, regex, incremental)| Self {
+                pattern,
+                direction,
+                regex,
+                incremental,
+            })
+    }
+
+    pub fn compile(&self) -> Result<Regex> {
+        if self.regex {
+            Regex::new(&self.pattern)
+        } else {
+            Ok(Regex::literal(&self.pattern))
+        }
+    }
+}
+
+/// The first and last cells of a match.
+pub type Match = (Point, Point);
+
+/// Find the matches of `regex` in `grid`, in order.
+///
+/// Lines that wrapped are searched together with the next ones, so that
+/// matches can go on from one to the other.
+pub fn find_matches(grid: &Grid, regex: &Regex) -> Vec<Match> {
+    LogicalLine::all(grid).iter()
+        .flat_map(|line| {
+            regex.find_all(&line.text).into_iter()
+                .map(move |(start, end)| line.range(start, end))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ansi::Processor,
+        screen::Screen,
+        util::point::P,
+    };
+    use super::{
+        find_matches,
+        Regex,
+        Search,
+        SearchDirection,
+    };
+
+    #[test]
+    fn test_find_matches() {
+        let mut screen = Screen::new(P(6, 4));
+        // The first line wraps in the middle of "error".
+        Processor::default().advance(b"an error\r\ner or\r\nerror", &mut screen, &mut Vec::new());
+        let grid = screen.grid();
+        assert_eq!(find_matches(grid, &Regex::literal("error")), vec![
+            (P(3, 0), P(1, 1)),
+            (P(0, 3), P(4, 3)),
+        ]);
+        assert_eq!(find_matches(grid, &Regex::new("r +o").unwrap()), vec![(P(1, 2), P(3, 2))]);
+        assert_eq!(find_matches(grid, &Regex::new("r$").unwrap()), vec![
+            (P(1, 1), P(1, 1)),
+            (P(4, 2), P(4, 2)),
+            (P(4, 3), P(4, 3)),
+        ]);
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Search::from_name("search-backward-text", "a.b".to_owned()), Some(Search {
+            pattern: "a.b".to_owned(),
+            direction: SearchDirection::Backward,
+            regex: false,
+            incremental: false,
+        }));
+        assert_eq!(Search::from_name("next-word", String::new()), None);
+    }
+}
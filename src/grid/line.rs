@@ -1,15 +1,47 @@
 use std::ops::{Deref, DerefMut};
 use super::cell::Cell;
 
+/// Size of the characters in a line, as set by the DEC line attributes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LineSize {
+    /// Single width, single height line (DECSWL).
+    Normal,
+    /// Double width, single height line (DECDWL).
+    DoubleWidth,
+    /// Top half of a double width, double height line (DECDHL).
+    DoubleHeightTop,
+    /// Bottom half of a double width, double height line (DECDHL).
+    DoubleHeightBottom,
+}
+
+impl Default for LineSize {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+impl LineSize {
+    /// Returns whether each character takes up two cells.
+    pub fn is_double_width(&self) -> bool {
+        *self != Self::Normal
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Line {
     vec: Vec<Cell>,
+    pub size: LineSize,
+    /// Whether the text continues on the next line because the cursor
+    /// wrapped at the end of this one.
+    pub wrapped: bool,
 }
 
 impl Line {
     pub fn new(size: usize) -> Self {
         Self {
             vec: vec![Cell::default(); size],
+            size: LineSize::default(),
+            wrapped: false,
         }
     }
 }
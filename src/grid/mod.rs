@@ -1,11 +1,25 @@
 pub mod line;
 pub mod cell;
 pub mod iter;
+pub mod semantic;
 
-use std::ops::RangeBounds;
+use std::{
+    collections::VecDeque,
+    ops::{
+        Range,
+        RangeBounds,
+    },
+    sync::atomic::{
+        AtomicU64,
+        Ordering,
+    },
+};
 use crate::util::Point;
 pub use self::{
-    line::Line,
+    line::{
+        Line,
+        LineSize,
+    },
     cell::Cell,
     iter::{
         GridIterator,
@@ -15,10 +29,28 @@ pub use self::{
     },
 };
 
+/// Clock used to stamp modified lines. It is shared by all grids so that
+/// stamps stay comparable when a screen swaps its grids.
+static DAMAGE_CLOCK: AtomicU64 = AtomicU64::new(1);
+
+/// Start a new frame, returning its stamp.
+///
+/// Lines modified after this call are damaged relative to the returned
+/// stamp, see `Grid::is_damaged_since`.
+pub fn damage_frame() -> u64 {
+    DAMAGE_CLOCK.fetch_add(1, Ordering::SeqCst)
+}
+
 #[derive(Debug, Clone)]
 pub struct Grid {
     data: Vec<Line>,
     tabstops: Vec<bool>,
+    /// Stamp of the last modification of each line.
+    damage: Vec<u64>,
+    /// Lines scrolled off the top, oldest first.
+    history: VecDeque<Line>,
+    /// Maximum number of lines kept in `history`.
+    history_limit: usize,
     pub size: Point,
 }
 
@@ -26,26 +58,141 @@ impl Grid {
     pub fn new(size: Point) -> Self {
         let data = vec![Line::new(size.x); size.y];
         let tabstops = vec![false; size.x];
+        let damage = vec![DAMAGE_CLOCK.load(Ordering::SeqCst); size.y];
         Self {
             data,
             tabstops,
+            damage,
+            history: VecDeque::new(),
+            history_limit: 0,
             size,
         }
     }
 
+    /// Create a grid made of `lines`, resized to `width` columns.
+    pub fn from_lines<I: IntoIterator<Item = Line>>(lines: I, width: usize) -> Self {
+        let data: Vec<Line> = lines.into_iter()
+            .map(|mut line| {
+                line.resize(width, Cell::default());
+                line
+            })
+            .collect();
+        let size = Point::new(width, data.len());
+        Self {
+            damage: vec![DAMAGE_CLOCK.load(Ordering::SeqCst); size.y],
+            tabstops: vec![false; size.x],
+            data,
+            history: VecDeque::new(),
+            history_limit: 0,
+            size,
+        }
+    }
+
+    /// Get the lines scrolled off the top, oldest first.
+    pub fn history(&self) -> &VecDeque<Line> {
+        &self.history
+    }
+
+    /// Set the maximum number of lines kept when they are scrolled off the
+    /// top, dropping the oldest ones if there are more.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = limit;
+        let excess = self.history.len().saturating_sub(limit);
+        self.history.drain(..excess);
+    }
+
     fn add_line(&mut self) {
         self.data.push(Line::new(self.size.x));
+        self.damage.push(DAMAGE_CLOCK.load(Ordering::SeqCst));
+    }
+
+    /// Mark a line as modified.
+    fn damage_line(&mut self, y: usize) {
+        if let Some(stamp) = self.damage.get_mut(y) {
+            *stamp = DAMAGE_CLOCK.load(Ordering::SeqCst);
+        }
+    }
+
+    /// Mark every line as modified.
+    pub fn damage_all(&mut self) {
+        let now = DAMAGE_CLOCK.load(Ordering::SeqCst);
+        self.damage.iter_mut().for_each(|stamp| *stamp = now);
+    }
+
+    /// Returns whether line `y` was modified after the frame `frame` started.
+    pub fn is_damaged_since(&self, y: usize, frame: u64) -> bool {
+        self.damage.get(y).is_some_and(|stamp| *stamp > frame)
+    }
+
+    /// Resize the grid, keeping the contents of the top left corner.
+    pub fn resize(&mut self, size: Point) {
+        for line in self.data.iter_mut() {
+            line.resize(size.x, Cell::default());
+        }
+        self.data.resize(size.y, Line::new(size.x));
+        self.tabstops.resize(size.x, false);
+        self.damage.resize(size.y, 0);
+        self.size = size;
+        self.damage_all();
+    }
+
+    /// Remove `count` lines from the top of the grid, adding blank lines at
+    /// the bottom.
+    pub fn scroll_up(&mut self, count: usize) {
+        self.scroll_region_up(0..self.size.y, count);
+    }
+
+    /// Remove `count` lines from the top of `region`, adding blank lines at
+    /// its bottom.
+    ///
+    /// Lines that leave the top of the grid are kept in the history.
+    pub fn scroll_region_up(&mut self, region: Range<usize>, count: usize) {
+        let region = region.start..region.end.min(self.size.y);
+        let count = count.min(region.len());
+        let blank = Line::new(self.size.x);
+        let removed = self.data.splice(region.start..region.start + count, None);
+        if region.start == 0 && self.history_limit > 0 {
+            self.history.extend(removed);
+            let excess = self.history.len().saturating_sub(self.history_limit);
+            self.history.drain(..excess);
+        } else {
+            drop(removed);
+        }
+        let end = region.end - count;
+        self.data.splice(end..end, std::iter::repeat_n(blank, count));
+        region.for_each(|y| self.damage_line(y));
+    }
+
+    /// Remove `count` lines from the bottom of `region`, adding blank lines
+    /// at its top.
+    pub fn scroll_region_down(&mut self, region: Range<usize>, count: usize) {
+        let region = region.start..region.end.min(self.size.y);
+        let count = count.min(region.len());
+        let blank = Line::new(self.size.x);
+        self.data.drain(region.end - count..region.end);
+        self.data.splice(region.start..region.start, std::iter::repeat_n(blank, count));
+        region.for_each(|y| self.damage_line(y));
     }
 
     fn point_to_index(&self, p: Point) -> usize {
         p.y * self.size.x + p.x
     }
 
+    pub fn line(&self, y: usize) -> Option<&Line> {
+        self.data.get(y)
+    }
+
+    pub fn line_mut(&mut self, y: usize) -> Option<&mut Line> {
+        self.damage_line(y);
+        self.data.get_mut(y)
+    }
+
     pub fn cell(&self, point: Point) -> Option<&Cell> {
         self.data.get(point.y).and_then(|gl| gl.get(point.x))
     }
 
     pub fn cell_mut(&mut self, point: Point) -> Option<&mut Cell> {
+        self.damage_line(point.y);
         self.data.get_mut(point.y).and_then(|gl| gl.get_mut(point.x))
     }
 
@@ -73,3 +220,75 @@ impl Grid {
         GridMutIterator::block(self, range)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::util::point::P;
+    use super::{
+        damage_frame,
+        Grid,
+    };
+
+    #[test]
+    fn test_damage_tracking() {
+        let mut grid = Grid::new(P(3, 3));
+        let frame = damage_frame();
+        assert!((0..3).all(|y| !grid.is_damaged_since(y, frame)));
+
+        grid.cell_mut(P(1, 1)).unwrap().ch = Some('a');
+        assert!(!grid.is_damaged_since(0, frame));
+        assert!(grid.is_damaged_since(1, frame));
+        assert!(!grid.is_damaged_since(2, frame));
+
+        let next_frame = damage_frame();
+        assert!(!grid.is_damaged_since(1, next_frame));
+        grid.damage_all();
+        assert!((0..3).all(|y| grid.is_damaged_since(y, next_frame)));
+        // Damage is not consumed by checking it.
+        assert!(grid.is_damaged_since(1, frame));
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut grid = Grid::new(P(3, 3));
+        grid.cell_mut(P(0, 0)).unwrap().ch = Some('a');
+        grid.cell_mut(P(2, 2)).unwrap().ch = Some('b');
+        let frame = damage_frame();
+
+        grid.resize(P(2, 4));
+        assert_eq!(grid.cell(P(0, 0)).unwrap().ch, Some('a'));
+        assert!(grid.cell(P(2, 2)).is_none());
+        assert_eq!(grid.line(3).unwrap().len(), 2);
+        assert!((0..4).all(|y| grid.is_damaged_since(y, frame)));
+
+        grid.scroll_up(1);
+        assert_eq!(grid.cell(P(0, 0)).unwrap().ch, None);
+        assert_eq!(grid.line(3).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_scroll_history() {
+        let mut grid = Grid::new(P(2, 3));
+        grid.set_history_limit(2);
+        for y in 0..3 {
+            grid.cell_mut(P(0, y)).unwrap().ch = std::char::from_digit(y as u32, 10);
+        }
+        let first = |grid: &Grid, y| grid.cell(P(0, y)).unwrap().ch;
+
+        // Scrolling a region below the top doesn't keep the lines.
+        grid.scroll_region_up(1..3, 1);
+        assert_eq!([first(&grid, 0), first(&grid, 1), first(&grid, 2)], [Some('0'), Some('2'), None]);
+        assert!(grid.history().is_empty());
+
+        grid.scroll_region_down(0..3, 1);
+        assert_eq!([first(&grid, 0), first(&grid, 1), first(&grid, 2)], [None, Some('0'), Some('2')]);
+
+        grid.scroll_up(2);
+        grid.scroll_up(1);
+        assert_eq!(grid.history().len(), 2);
+        assert_eq!(grid.history()[0][0].ch, Some('0'));
+        assert_eq!(grid.history()[1][0].ch, Some('2'));
+        grid.set_history_limit(1);
+        assert_eq!(grid.history()[0][0].ch, Some('2'));
+    }
+}
@@ -0,0 +1,297 @@
+use {
+    std::sync::OnceLock,
+    crate::util::{
+        regex::Regex,
+        Point,
+    },
+    super::Grid,
+};
+
+/// Characters that end a URL when they come last, like the period of a
+/// sentence.
+const URL_TRAILING: &str = ".,;:!?'\"";
+/// Characters that end a path when they come last.
+const PATH_TRAILING: &str = ".,;:";
+const URL_PATTERN: &str = "[a-zA-Z][a-zA-Z0-9+.-]*://[^ \t\"'<>`]+";
+/// Paths with a slash, or file names with an extension followed by a line
+/// number, both with an optional `:line:col` suffix.
+const PATH_PATTERN: &str = "~?[\\w.+-]*/[\\w.+/-]*(:\\d+){0,2}|[\\w.+-]+\\.\\w+(:\\d+){1,2}";
+const QUOTES: [char; 3] = ['"', '\'', '`'];
+/// How far from the point matches are looked for, in characters, so that
+/// a very long line doesn't cost more than a usual one.
+const MATCH_DISTANCE: usize = 1024;
+
+/// The patterns, compiled once.
+struct Patterns {
+    url: Regex,
+    path: Regex,
+    /// A pattern for each of the `QUOTES`.
+    quoted: Vec<Regex>,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        url: Regex::new(URL_PATTERN).expect("invalid pattern"),
+        path: Regex::new(PATH_PATTERN).expect("invalid pattern"),
+        quoted: QUOTES.iter()
+            .map(|quote| Regex::new(&format!("{0}[^{0}]*{0}", quote)).expect("invalid pattern"))
+            .collect(),
+    })
+}
+
+/// Kind of a character, words are made of characters of the same kind.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CharClass {
+    Blank,
+    Word,
+    /// One of the word separators, each is a word on its own.
+    Separator,
+}
+
+impl CharClass {
+    pub fn of(ch: Option<char>, separators: &str) -> Self {
+        match ch {
+            None => Self::Blank,
+            Some(ch) if ch.is_whitespace() => Self::Blank,
+            Some(ch) if separators.contains(ch) => Self::Separator,
+            Some(_) => Self::Word,
+        }
+    }
+}
+
+/// A line of text, which spans several lines of a grid when they wrapped.
+///
+/// The blanks at its end are left out.
+#[derive(Clone, Debug, Default)]
+pub struct LogicalLine {
+    pub text: Vec<char>,
+    /// Position of each character in the grid.
+    pub points: Vec<Point>,
+}
+
+impl LogicalLine {
+    /// Get the logical line that goes through line `y` of `grid`.
+    pub fn at(grid: &Grid, y: usize) -> Self {
+        let mut start = y;
+        while start > 0 && grid.line(start - 1).is_some_and(|line| line.wrapped) {
+            start -= 1;
+        }
+        let mut logical = Self::default();
+        for y in start..grid.size.y {
+            if !logical.push(grid, y) {
+                break;
+            }
+        }
+        logical
+    }
+
+    /// Get all the logical lines of `grid`, in order.
+    pub fn all(grid: &Grid) -> Vec<Self> {
+        let mut lines = Vec::new();
+        let mut logical = Self::default();
+        for y in 0..grid.size.y {
+            if !logical.push(grid, y) {
+                lines.push(std::mem::take(&mut logical));
+            }
+        }
+        if !logical.points.is_empty() {
+            lines.push(logical);
+        }
+        lines
+    }
+
+    /// Add line `y` of `grid`, returning whether it wrapped onto the next
+    /// line.
+    fn push(&mut self, grid: &Grid, y: usize) -> bool {
+        let line = match grid.line(y) {
+            Some(line) => line,
+            None => return false,
+        };
+        let end = if line.wrapped {
+            line.len()
+        } else {
+            line.iter()
+                .rposition(|cell| cell.ch.is_some_and(|ch| !ch.is_whitespace()))
+                .map_or(0, |x| x + 1)
+        };
+        for (x, cell) in line.iter().take(end).enumerate() {
+            self.text.push(cell.ch.unwrap_or(' '));
+            self.points.push(Point::new(x, y));
+        }
+        line.wrapped
+    }
+
+    /// Get the first and last cells of the characters in `start..end`.
+    pub fn range(&self, start: usize, end: usize) -> (Point, Point) {
+        (self.points[start], self.points[end - 1])
+    }
+
+    fn index_of(&self, point: Point) -> Option<usize> {
+        self.points.iter().position(|p| *p == point)
+    }
+}
+
+/// Get the URL, path, quoted string or word around `point`, the first of
+/// them that is found. Words are made of characters of the same class,
+/// they all go on across lines that wrapped.
+///
+/// Paths keep a `:line:col` suffix. Quoted strings are taken without their
+/// quotes, unless `point` is on one of them.
+pub fn semantic_at(grid: &Grid, point: Point, separators: &str) -> Option<(Point, Point)> {
+    let line = LogicalLine::at(grid, point.y);
+    let index = line.index_of(point)?;
+    let (start, end) = url_around(&line, index)
+        .or_else(|| path_around(&line, index))
+        .or_else(|| quoted_around(&line, index))
+        .unwrap_or_else(|| word_around(&line, index, separators));
+    Some(line.range(start, end))
+}
+
+fn word_around(line: &LogicalLine, index: usize, separators: &str) -> (usize, usize) {
+    let class = |i: usize| CharClass::of(Some(line.text[i]), separators);
+    let target = class(index);
+    if target == CharClass::Separator {
+        return (index, index + 1);
+    }
+    let mut start = index;
+    while start > 0 && class(start - 1) == target {
+        start -= 1;
+    }
+    let mut end = index + 1;
+    while end < line.text.len() && class(end) == target {
+        end += 1;
+    }
+    (start, end)
+}
+
+/// Find the match of `regex` that holds `index`, without the characters
+/// of `trailing` at its end.
+fn match_around(line: &LogicalLine, index: usize, regex: &Regex, trailing: &str) -> Option<(usize, usize)> {
+    let offset = index.saturating_sub(MATCH_DISTANCE);
+    let text = &line.text[offset..line.text.len().min(index + MATCH_DISTANCE)];
+    regex.find_all(text).into_iter()
+        .map(|(start, end)| (start + offset, end + offset))
+        .map(|(start, mut end)| {
+            while end > start + 1 && trailing.contains(line.text[end - 1]) {
+                end -= 1;
+            }
+            (start, end)
+        })
+        .find(|&(start, end)| start <= index && index < end)
+}
+
+fn url_around(line: &LogicalLine, index: usize) -> Option<(usize, usize)> {
+    let (start, mut end) = match_around(line, index, &patterns().url, URL_TRAILING)?;
+    // Leave out a closing parenthesis that isn't part of the URL, as when
+    // the URL is written between parentheses.
+    let count = |ch| line.text[start..end].iter().filter(|c| **c == ch).count();
+    if line.text[end - 1] == ')' && count(')') > count('(') {
+        end -= 1;
+    }
+    Some((start, end)).filter(|&(_, end)| index < end)
+}
+
+fn path_around(line: &LogicalLine, index: usize) -> Option<(usize, usize)> {
+    match_around(line, index, &patterns().path, PATH_TRAILING)
+        // A lone slash isn't a path.
+        .filter(|&(start, end)| end - start > 1)
+}
+
+fn quoted_around(line: &LogicalLine, index: usize) -> Option<(usize, usize)> {
+    patterns().quoted.iter().find_map(|regex| {
+        let (start, end) = match_around(line, index, regex, "")?;
+        if index == start || index + 1 == end || end - start == 2 {
+            Some((start, end))
+        } else {
+            Some((start + 1, end - 1))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ansi::Processor,
+        screen::Screen,
+        util::{
+            point::P,
+            Point,
+        },
+    };
+    use super::{
+        semantic_at,
+        LogicalLine,
+    };
+
+    const SEPARATORS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^`{|}~";
+
+    fn screen(text: &str) -> Screen {
+        let mut screen = Screen::new(P(20, 4));
+        Processor::default().advance(text.as_bytes(), &mut screen, &mut Vec::new());
+        screen
+    }
+
+    /// Get the text between two points of a logical line.
+    fn text(screen: &Screen, range: Option<(Point, Point)>) -> String {
+        let (start, end) = range.unwrap();
+        let line = LogicalLine::at(screen.grid(), start.y);
+        line.text.iter().zip(line.points.iter())
+            .filter(|(_, point)| start <= **point && **point <= end)
+            .map(|(ch, _)| *ch)
+            .collect()
+    }
+
+    #[test]
+    fn test_logical_lines() {
+        let screen = screen("a long line that wraps around\r\nshort  \r\n");
+        let grid = screen.grid();
+        let line = LogicalLine::at(grid, 1);
+        assert_eq!((line.points[0], *line.points.last().unwrap()), (P(0, 0), P(8, 1)));
+        assert_eq!(LogicalLine::at(grid, 2).points.last(), Some(&P(4, 2)));
+        assert!(LogicalLine::at(grid, 3).text.is_empty());
+        let lines = LogicalLine::all(grid);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].text.iter().collect::<String>(), "a long line that wraps around");
+    }
+
+    #[test]
+    fn test_words() {
+        let screen = screen("foo.bar_baz   x\r\nnext_word_that_wraps_over");
+        let grid = screen.grid();
+        let word = |point, separators| text(&screen, semantic_at(grid, point, separators));
+        assert_eq!(word(P(5, 0), SEPARATORS), "bar_baz");
+        assert_eq!(word(P(3, 0), SEPARATORS), ".");
+        assert_eq!(word(P(12, 0), SEPARATORS), "   ");
+        assert_eq!(word(P(2, 2), SEPARATORS), "next_word_that_wraps_over");
+        assert_eq!(word(P(5, 0), " "), "foo.bar_baz");
+        assert_eq!(semantic_at(grid, P(18, 0), SEPARATORS), None);
+    }
+
+    #[test]
+    fn test_semantic() {
+        let screen = screen("see (https://x.io/a_(b)) src/main.rs:12:5, \"two words\" it's");
+        let grid = screen.grid();
+        let at = |x, y| text(&screen, semantic_at(grid, P(x, y), SEPARATORS));
+        // The URL wraps onto the second line.
+        assert_eq!(at(8, 0), "https://x.io/a_(b)");
+        assert_eq!(at(2, 1), "https://x.io/a_(b)");
+        assert_eq!(at(10, 1), "src/main.rs:12:5");
+        assert_eq!(at(0, 2), "src/main.rs:12:5");
+        assert_eq!(at(4, 2), "two words");
+        assert_eq!(at(8, 2), "two words");
+        assert_eq!(at(3, 2), "\"two words\"");
+        assert_eq!(at(15, 2), "it");
+        assert_eq!(at(0, 0), "see");
+        assert_eq!(at(4, 0), "(");
+    }
+
+    #[test]
+    fn test_long_line() {
+        let mut screen = Screen::new(P(500, 200));
+        let text = format!("{} https://a.io/b", "a".repeat(99_500));
+        Processor::default().advance(text.as_bytes(), &mut screen, &mut Vec::new());
+        let (start, end) = semantic_at(screen.grid(), P(5, 199), SEPARATORS).unwrap();
+        assert_eq!((start, end), (P(1, 199), P(14, 199)));
+    }
+}
@@ -0,0 +1,299 @@
+use std::str;
+use super::{
+    key::{
+        Key,
+        KeyCode,
+        Modifiers,
+    },
+    mouse::MouseEvent,
+};
+
+/// A key read from the host, with the bytes it was read from.
+///
+/// Sequences that aren't keys, like mouse reports, have no key and are
+/// forwarded as they are.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeyEvent {
+    pub key: Option<Key>,
+    /// The mouse report, if the sequence is one in the SGR encoding.
+    pub mouse: Option<MouseEvent>,
+    pub bytes: Vec<u8>,
+}
+
+impl KeyEvent {
+    /// Get the bytes to send to a pane, in its cursor keys mode.
+    ///
+    /// Only the keys whose encoding depends on the mode are encoded again,
+    /// anything else is sent as it was read.
+    pub fn encode(&self, cursor_keys: bool) -> Vec<u8> {
+        match self.key {
+            Some(key) if key.modifiers.is_empty() => match key.code {
+                KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right |
+                KeyCode::Home | KeyCode::End => key.encode(cursor_keys),
+                _ => self.bytes.clone(),
+            },
+            _ => self.bytes.clone(),
+        }
+    }
+}
+
+/// Splits the host input into keys.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    /// Start of a sequence that needs more input to be decoded.
+    pending: Vec<u8>,
+}
+
+/// Result of decoding the start of the input.
+enum Decoded {
+    /// A key, or an unknown sequence, and its length.
+    Event(Option<Key>, usize),
+    /// A mouse report and its length.
+    Mouse(MouseEvent, usize),
+    /// The input ends in the middle of a sequence.
+    Incomplete,
+}
+
+impl Decoder {
+    /// Returns whether the input ended in the middle of a sequence.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Decode `input`, keeping incomplete sequences until more input
+    /// arrives or `flush` is called.
+    pub fn feed(&mut self, input: &[u8]) -> Vec<KeyEvent> {
+        self.pending.extend_from_slice(input);
+        let mut events = Vec::new();
+        let mut start = 0;
+        while start < self.pending.len() {
+            match decode(&self.pending[start..]) {
+                Decoded::Event(key, len) => {
+                    events.push(KeyEvent {
+                        key,
+                        mouse: None,
+                        bytes: self.pending[start..start + len].to_vec(),
+                    });
+                    start += len;
+                },
+                Decoded::Mouse(mouse, len) => {
+                    events.push(KeyEvent {
+                        key: None,
+                        mouse: Some(mouse),
+                        bytes: self.pending[start..start + len].to_vec(),
+                    });
+                    start += len;
+                },
+                Decoded::Incomplete => break,
+            }
+        }
+        self.pending.drain(..start);
+        events
+    }
+
+    /// Decode the incomplete sequence, when no more input arrived in time.
+    /// A lone escape is the escape key, anything else is forwarded as is.
+    pub fn flush(&mut self) -> Vec<KeyEvent> {
+        let bytes = std::mem::take(&mut self.pending);
+        match bytes.as_slice() {
+            [] => Vec::new(),
+            [0x1b] => vec![KeyEvent { key: Some(Key::plain(KeyCode::Escape)), mouse: None, bytes }],
+            _ => vec![KeyEvent { key: None, mouse: None, bytes }],
+        }
+    }
+}
+
+fn decode(input: &[u8]) -> Decoded {
+    match input[0] {
+        0x1b => decode_escape(input),
+        byte if byte < 0x80 => Decoded::Event(Some(decode_byte(byte)), 1),
+        byte => {
+            let len = match byte {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => return Decoded::Event(None, 1),
+            };
+            if input.len() < len {
+                return Decoded::Incomplete;
+            }
+            match str::from_utf8(&input[..len]).ok().and_then(|s| s.chars().next()) {
+                Some(ch) => Decoded::Event(Some(Key::plain(KeyCode::Char(ch))), len),
+                None => Decoded::Event(None, 1),
+            }
+        },
+    }
+}
+
+/// Decode a single byte key.
+fn decode_byte(byte: u8) -> Key {
+    match byte {
+        0x00 => Key::ctrl(' '),
+        b'\t' => Key::plain(KeyCode::Tab),
+        b'\r' => Key::plain(KeyCode::Enter),
+        0x1b => Key::plain(KeyCode::Escape),
+        0x7f => Key::plain(KeyCode::Backspace),
+        0x01..=0x1a => Key::ctrl((b'a' + byte - 1) as char),
+        0x1c..=0x1f => Key::ctrl((b'@' + byte) as char),
+        _ => Key::plain(KeyCode::Char(byte as char)),
+    }
+}
+
+fn decode_escape(input: &[u8]) -> Decoded {
+    match input.get(1) {
+        None => Decoded::Incomplete,
+        Some(b'[') => decode_csi(input),
+        Some(b'O') => match input.get(2) {
+            None => Decoded::Incomplete,
+            Some(&byte) => Decoded::Event(decode_final(byte, Modifiers::empty()), 3),
+        },
+        // Escape followed by a key is the key with meta held.
+        Some(_) => match decode(&input[1..]) {
+            Decoded::Event(Some(mut key), len) => {
+                key.modifiers |= Modifiers::META;
+                Decoded::Event(Some(key), len + 1)
+            },
+            Decoded::Event(None, _) | Decoded::Mouse(..) => Decoded::Event(None, 1),
+            Decoded::Incomplete => Decoded::Incomplete,
+        },
+    }
+}
+
+fn decode_csi(input: &[u8]) -> Decoded {
+    let end = match input[2..].iter().position(|byte| (0x40..=0x7e).contains(byte)) {
+        Some(position) => position + 2,
+        None if input[2..].iter().all(|byte| (0x20..=0x3f).contains(byte)) => {
+            return Decoded::Incomplete;
+        },
+        None => return Decoded::Event(None, input.len()),
+    };
+    let len = end + 1;
+    let parameters = match str::from_utf8(&input[2..end]) {
+        Ok(parameters) => parameters,
+        Err(_) => return Decoded::Event(None, len),
+    };
+    if let Some(parameters) = parameters.strip_prefix('<') {
+        return match MouseEvent::parse_sgr(parameters, input[end]) {
+            Some(mouse) => Decoded::Mouse(mouse, len),
+            None => Decoded::Event(None, len),
+        };
+    }
+    let mut parameters = parameters.split(';').map(|parameter| parameter.parse::<u8>().ok());
+    let number = parameters.next().flatten();
+    let modifiers = match parameters.next().flatten() {
+        Some(parameter) if parameter > 0 => Modifiers::from_bits_truncate(parameter - 1),
+        _ => Modifiers::empty(),
+    };
+    let key = match input[end] {
+        b'~' => number.and_then(|number| {
+            let code = match number {
+                1 | 7 => KeyCode::Home,
+                2 => KeyCode::Insert,
+                3 => KeyCode::Delete,
+                4 | 8 => KeyCode::End,
+                5 => KeyCode::PageUp,
+                6 => KeyCode::PageDown,
+                11..=15 => KeyCode::F(number - 10),
+                17..=21 => KeyCode::F(number - 11),
+                23 | 24 => KeyCode::F(number - 12),
+                _ => return None,
+            };
+            Some(Key::new(code, modifiers))
+        }),
+        // Parameters of other sequences, like mouse reports, aren't
+        // modifiers.
+        byte if number.is_none() || number == Some(1) => decode_final(byte, modifiers),
+        _ => None,
+    };
+    Decoded::Event(key, len)
+}
+
+/// Decode the final byte of a cursor or function key sequence.
+fn decode_final(byte: u8, modifiers: Modifiers) -> Option<Key> {
+    let code = match byte {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'Z' => return Some(Key::plain(KeyCode::BackTab)),
+        b'P'..=b'S' => KeyCode::F(byte - b'P' + 1),
+        _ => return None,
+    };
+    Some(Key::new(code, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        input::key::{
+            Key,
+            KeyCode,
+            Modifiers,
+        },
+        util::point::P,
+    };
+    use super::Decoder;
+
+    fn keys(decoder: &mut Decoder, input: &[u8]) -> Vec<Option<Key>> {
+        decoder.feed(input).into_iter().map(|event| event.key).collect()
+    }
+
+    #[test]
+    fn test_decode_keys() {
+        let mut decoder = Decoder::default();
+        assert_eq!(keys(&mut decoder, b"a\x02\r\x7f"), vec![
+            Some(Key::plain(KeyCode::Char('a'))),
+            Some(Key::ctrl('b')),
+            Some(Key::plain(KeyCode::Enter)),
+            Some(Key::plain(KeyCode::Backspace)),
+        ]);
+        assert_eq!(keys(&mut decoder, b"\x1b[A\x1bOB\x1b[1;5C\x1b[6~\x1b[15;2~"), vec![
+            Some(Key::plain(KeyCode::Up)),
+            Some(Key::plain(KeyCode::Down)),
+            Some(Key::new(KeyCode::Right, Modifiers::CTRL)),
+            Some(Key::plain(KeyCode::PageDown)),
+            Some(Key::new(KeyCode::F(5), Modifiers::SHIFT)),
+        ]);
+        assert_eq!(keys(&mut decoder, "\x1bxé".as_bytes()), vec![
+            Some(Key::new(KeyCode::Char('x'), Modifiers::META)),
+            Some(Key::plain(KeyCode::Char('é'))),
+        ]);
+        // Mouse reports aren't keys, but are kept whole.
+        let events = decoder.feed(b"\x1b[<0;1;2M\x1b[<35;1;2M");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].key, None);
+        assert_eq!(events[0].mouse.map(|mouse| mouse.point), Some(P(0, 1)));
+        assert_eq!(events[0].bytes, b"\x1b[<0;1;2M");
+        assert_eq!(events[1].mouse, None);
+        assert_eq!(events[1].bytes, b"\x1b[<35;1;2M");
+    }
+
+    #[test]
+    fn test_decode_incomplete() {
+        let mut decoder = Decoder::default();
+        assert!(keys(&mut decoder, b"\x1b[1;").is_empty());
+        assert!(decoder.has_pending());
+        assert_eq!(keys(&mut decoder, b"5A"), vec![Some(Key::new(KeyCode::Up, Modifiers::CTRL))]);
+        assert!(!decoder.has_pending());
+
+        assert!(keys(&mut decoder, "é".as_bytes()[..1].as_ref()).is_empty());
+        assert_eq!(keys(&mut decoder, &"é".as_bytes()[1..]), vec![Some(Key::plain(KeyCode::Char('é')))]);
+
+        assert!(keys(&mut decoder, b"\x1b").is_empty());
+        let events = decoder.flush();
+        assert_eq!(events[0].key, Some(Key::plain(KeyCode::Escape)));
+        assert!(!decoder.has_pending());
+    }
+
+    #[test]
+    fn test_encode_event() {
+        let mut decoder = Decoder::default();
+        let events = decoder.feed(b"\x1b[A\x1b[1~x");
+        assert_eq!(events[0].encode(true), b"\x1bOA");
+        assert_eq!(events[0].encode(false), b"\x1b[A");
+        assert_eq!(events[1].encode(true), b"\x1bOH");
+        assert_eq!(events[2].encode(true), b"x");
+    }
+}
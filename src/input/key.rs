@@ -0,0 +1,278 @@
+use std::{
+    fmt,
+    str,
+};
+
+bitflags::bitflags! {
+    pub struct Modifiers: u8 {
+        const SHIFT = 0b001;
+        const META  = 0b010;
+        const CTRL  = 0b100;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum KeyCode {
+    Char(char),
+    Enter,
+    Tab,
+    BackTab,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    Insert,
+    Delete,
+    PageUp,
+    PageDown,
+    /// Function key, from 1 to 12.
+    F(u8),
+}
+
+/// A key pressed on the host, with its modifiers.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: Modifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    pub fn plain(code: KeyCode) -> Self {
+        Self::new(code, Modifiers::empty())
+    }
+
+    pub fn ctrl(ch: char) -> Self {
+        Self::new(KeyCode::Char(ch), Modifiers::CTRL)
+    }
+
+    /// Encode the key as the bytes a terminal sends for it.
+    ///
+    /// `cursor_keys` selects the application cursor keys encoding (DECCKM).
+    pub fn encode(&self, cursor_keys: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        // Parameter that xterm uses to encode modifiers in sequences, the
+        // modifier bits match its encoding.
+        let parameter = 1 + self.modifiers.bits();
+        let letter = |bytes: &mut Vec<u8>, letter: u8| {
+            if self.modifiers.is_empty() {
+                bytes.extend_from_slice(if cursor_keys { b"\x1bO" } else { b"\x1b[" });
+                bytes.push(letter);
+            } else {
+                bytes.extend_from_slice(format!("\x1b[1;{}", parameter).as_bytes());
+                bytes.push(letter);
+            }
+        };
+        let tilde = |bytes: &mut Vec<u8>, number: u8| {
+            if self.modifiers.is_empty() {
+                bytes.extend_from_slice(format!("\x1b[{}~", number).as_bytes());
+            } else {
+                bytes.extend_from_slice(format!("\x1b[{};{}~", number, parameter).as_bytes());
+            }
+        };
+        match self.code {
+            KeyCode::Char(ch) => {
+                if self.modifiers.contains(Modifiers::META) {
+                    bytes.push(0x1b);
+                }
+                match ctrl_byte(ch) {
+                    Some(byte) if self.modifiers.contains(Modifiers::CTRL) => bytes.push(byte),
+                    _ => {
+                        let mut buf = [0; 4];
+                        bytes.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+                    },
+                }
+            },
+            KeyCode::Enter | KeyCode::Tab | KeyCode::Backspace | KeyCode::Escape => {
+                if self.modifiers.contains(Modifiers::META) {
+                    bytes.push(0x1b);
+                }
+                bytes.push(match self.code {
+                    KeyCode::Enter => b'\r',
+                    KeyCode::Tab => b'\t',
+                    KeyCode::Backspace => 0x7f,
+                    _ => 0x1b,
+                });
+            },
+            KeyCode::BackTab => bytes.extend_from_slice(b"\x1b[Z"),
+            KeyCode::Up => letter(&mut bytes, b'A'),
+            KeyCode::Down => letter(&mut bytes, b'B'),
+            KeyCode::Right => letter(&mut bytes, b'C'),
+            KeyCode::Left => letter(&mut bytes, b'D'),
+            KeyCode::Home => letter(&mut bytes, b'H'),
+            KeyCode::End => letter(&mut bytes, b'F'),
+            KeyCode::Insert => tilde(&mut bytes, 2),
+            KeyCode::Delete => tilde(&mut bytes, 3),
+            KeyCode::PageUp => tilde(&mut bytes, 5),
+            KeyCode::PageDown => tilde(&mut bytes, 6),
+            KeyCode::F(n @ 1..=4) => {
+                if self.modifiers.is_empty() {
+                    bytes.extend_from_slice(b"\x1bO");
+                } else {
+                    bytes.extend_from_slice(format!("\x1b[1;{}", parameter).as_bytes());
+                }
+                bytes.push(b'P' + n - 1);
+            },
+            KeyCode::F(n) => {
+                let number = match n {
+                    5 => 15,
+                    6..=10 => n + 11,
+                    11 | 12 => n + 12,
+                    _ => return bytes,
+                };
+                tilde(&mut bytes, number);
+            },
+        }
+        bytes
+    }
+}
+
+/// Get the control character sent for `ch` with the control key held.
+pub fn ctrl_byte(ch: char) -> Option<u8> {
+    match ch {
+        ' ' | '@' => Some(0),
+        'a'..='z' => Some(ch as u8 - b'a' + 1),
+        '[' | '\\' | ']' | '^' | '_' => Some(ch as u8 - b'@'),
+        '?' => Some(0x7f),
+        _ => None,
+    }
+}
+
+const KEY_NAMES: [(&str, KeyCode); 17] = [
+    ("Enter", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("BTab", KeyCode::BackTab),
+    ("BSpace", KeyCode::Backspace),
+    ("Escape", KeyCode::Escape),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("IC", KeyCode::Insert),
+    ("DC", KeyCode::Delete),
+    ("PPage", KeyCode::PageUp),
+    ("NPage", KeyCode::PageDown),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+];
+
+impl str::FromStr for Key {
+    type Err = ();
+
+    /// Parse a key name such as `C-b`, `M-Left` or `F5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Modifiers::empty();
+        let mut name = s;
+        while name.len() > 2 && name.as_bytes()[1] == b'-' {
+            modifiers |= match name.as_bytes()[0] {
+                b'C' => Modifiers::CTRL,
+                b'M' => Modifiers::META,
+                b'S' => Modifiers::SHIFT,
+                _ => break,
+            };
+            name = &name[2..];
+        }
+        if name.len() == 2 && name.starts_with('^') {
+            modifiers |= Modifiers::CTRL;
+            name = &name[1..];
+        }
+
+        let code = if let Some((_, code)) = KEY_NAMES.iter().find(|(key, _)| *key == name) {
+            *code
+        } else if name == "Space" {
+            KeyCode::Char(' ')
+        } else if name.starts_with('F') && name.len() > 1 {
+            match name[1..].parse() {
+                Ok(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(()),
+            }
+        } else {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => KeyCode::Char(ch),
+                _ => return Err(()),
+            }
+        };
+        // Control keys are case insensitive.
+        let code = match code {
+            KeyCode::Char(ch) if modifiers.contains(Modifiers::CTRL) => {
+                KeyCode::Char(ch.to_ascii_lowercase())
+            },
+            code => code,
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(Modifiers::CTRL) {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.contains(Modifiers::META) {
+            write!(f, "M-")?;
+        }
+        if self.modifiers.contains(Modifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(ch) => write!(f, "{}", ch),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => {
+                let (name, _) = KEY_NAMES.iter().find(|(_, c)| *c == code).unwrap();
+                write!(f, "{}", name)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Key,
+        KeyCode,
+        Modifiers,
+    };
+
+    #[test]
+    fn test_key_names() {
+        assert_eq!("C-b".parse(), Ok(Key::ctrl('b')));
+        assert_eq!("^B".parse(), Ok(Key::ctrl('b')));
+        assert_eq!("M-Left".parse(), Ok(Key::new(KeyCode::Left, Modifiers::META)));
+        assert_eq!("F12".parse(), Ok(Key::plain(KeyCode::F(12))));
+        assert_eq!("-".parse(), Ok(Key::plain(KeyCode::Char('-'))));
+        assert_eq!("Space".parse(), Ok(Key::plain(KeyCode::Char(' '))));
+        assert_eq!("F13".parse::<Key>(), Err(()));
+        assert_eq!("Foo".parse::<Key>(), Err(()));
+        for name in &["C-b", "M-Left", "C-M-Up", "F5", "Space", "NPage", "%"] {
+            assert_eq!(name.parse::<Key>().unwrap().to_string(), *name);
+        }
+    }
+
+    #[test]
+    fn test_key_encoding() {
+        assert_eq!(Key::ctrl('b').encode(false), b"\x02");
+        assert_eq!(Key::ctrl(' ').encode(false), b"\x00");
+        assert_eq!(Key::new(KeyCode::Char('x'), Modifiers::META).encode(false), b"\x1bx");
+        assert_eq!(Key::plain(KeyCode::Char('é')).encode(false), "é".as_bytes());
+        assert_eq!(Key::plain(KeyCode::Up).encode(false), b"\x1b[A");
+        assert_eq!(Key::plain(KeyCode::Up).encode(true), b"\x1bOA");
+        assert_eq!(Key::new(KeyCode::Up, Modifiers::CTRL).encode(true), b"\x1b[1;5A");
+        assert_eq!(Key::plain(KeyCode::PageDown).encode(false), b"\x1b[6~");
+        assert_eq!(Key::new(KeyCode::Delete, Modifiers::SHIFT).encode(false), b"\x1b[3;2~");
+        assert_eq!(Key::plain(KeyCode::F(1)).encode(false), b"\x1bOP");
+        assert_eq!(Key::plain(KeyCode::F(5)).encode(false), b"\x1b[15~");
+        assert_eq!(Key::plain(KeyCode::F(12)).encode(false), b"\x1b[24~");
+        assert_eq!(Key::plain(KeyCode::Enter).encode(false), b"\r");
+    }
+}
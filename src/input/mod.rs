@@ -0,0 +1,354 @@
+pub mod decoder;
+pub mod key;
+pub mod mouse;
+pub mod prompt;
+pub mod table;
+
+use {
+    std::{
+        sync::{
+            Arc,
+            Mutex,
+            MutexGuard,
+        },
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+    log::trace,
+    crate::command::Command,
+};
+
+pub use self::{
+    decoder::{
+        Decoder,
+        KeyEvent,
+    },
+    key::Key,
+    mouse::MouseState,
+    table::{
+        KeyTables,
+        COPY_MODE_TABLE,
+        COPY_MODE_VI_TABLE,
+        PREFIX_TABLE,
+        ROOT_TABLE,
+    },
+};
+
+#[derive(Clone, Debug)]
+pub struct InputConfig {
+    /// Key that switches to the prefix table.
+    pub prefix: Key,
+    /// Time during which a repeatable key can be pressed again without the
+    /// prefix key.
+    pub repeat_time: Duration,
+    /// Time to wait for the rest of a sequence after an escape, before
+    /// reading it as the escape key.
+    pub escape_time: Duration,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            prefix: Key::ctrl('b'),
+            repeat_time: Duration::from_millis(500),
+            escape_time: Duration::from_millis(10),
+        }
+    }
+}
+
+/// What to do with a key read from the host.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputAction {
+    /// Send the key to the active pane.
+    Forward(KeyEvent),
+    Execute(Command),
+}
+
+/// Routes the host input through the key tables.
+///
+/// The key tables are shared with the input of every other session.
+pub struct Input {
+    config: InputConfig,
+    tables: Arc<Mutex<KeyTables>>,
+    decoder: Decoder,
+    /// Table that keys are read from when no other table was switched to.
+    base_table: String,
+    /// Table in which the next key is looked up.
+    table: String,
+    /// End of the repeat time after a repeatable key.
+    repeat_until: Option<Instant>,
+    mouse: MouseState,
+}
+
+impl Input {
+    pub fn new(config: InputConfig, tables: Arc<Mutex<KeyTables>>) -> Self {
+        Self {
+            config,
+            tables,
+            decoder: Decoder::default(),
+            base_table: ROOT_TABLE.to_owned(),
+            table: ROOT_TABLE.to_owned(),
+            repeat_until: None,
+            mouse: MouseState::default(),
+        }
+    }
+
+    pub fn config(&self) -> &InputConfig {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut InputConfig {
+        &mut self.config
+    }
+
+    pub fn tables(&self) -> MutexGuard<'_, KeyTables> {
+        self.tables.lock().unwrap()
+    }
+
+    /// Get what is left of the previous mouse events, when the mouse is
+    /// used by terman rather than by the panes.
+    pub fn mouse_mut(&mut self) -> &mut MouseState {
+        &mut self.mouse
+    }
+
+    /// Returns whether the input ended in the middle of a sequence, which
+    /// must be flushed if nothing follows within the escape time.
+    pub fn has_pending(&self) -> bool {
+        self.decoder.has_pending()
+    }
+
+    /// Read the next key from table `table`.
+    pub fn switch_table(&mut self, table: String) {
+        self.table = table;
+        self.repeat_until = None;
+    }
+
+    /// Read keys from table `table` when no other table was switched to,
+    /// instead of the root table. Copy mode uses it to take the keys of
+    /// the pane.
+    pub fn set_base_table(&mut self, table: &str) {
+        if self.base_table == table {
+            return;
+        }
+        if self.table == self.base_table {
+            self.table = table.to_owned();
+        }
+        self.base_table = table.to_owned();
+    }
+
+    /// Split `bytes` read from the host into keys.
+    pub fn decode(&mut self, bytes: &[u8]) -> Vec<KeyEvent> {
+        self.decoder.feed(bytes)
+    }
+
+    /// Decode the incomplete sequence left by `decode`.
+    pub fn flush(&mut self) -> Vec<KeyEvent> {
+        self.decoder.flush()
+    }
+
+    /// Look up a key read from the host at `now` in the key tables.
+    pub fn handle(&mut self, event: KeyEvent, now: Instant) -> Vec<InputAction> {
+        let mut actions = Vec::new();
+        self.handle_event(event, now, &mut actions);
+        actions
+    }
+
+    fn handle_event(&mut self, event: KeyEvent, now: Instant, actions: &mut Vec<InputAction>) {
+        if let Some(repeat_until) = self.repeat_until {
+            if now >= repeat_until {
+                self.reset();
+            }
+        }
+        let key = match event.key {
+            Some(key) => key,
+            None => {
+                // Sequences that aren't keys can't be bound.
+                if self.table == ROOT_TABLE {
+                    actions.push(InputAction::Forward(event));
+                } else {
+                    self.reset();
+                }
+                return;
+            },
+        };
+        trace!("[input] key: table={:?}, key={}", self.table, key);
+
+        let repeating = self.repeat_until.is_some();
+        // The prefix key comes before the bindings of the base table, which
+        // may bind it for something else.
+        if self.table == self.base_table && !repeating && key == self.config.prefix {
+            self.table = PREFIX_TABLE.to_owned();
+            return;
+        }
+        let binding = self.tables().lookup(&self.table, &key).cloned();
+        match binding {
+            Some(binding) => {
+                if repeating && !binding.repeat {
+                    // Only repeatable keys are read without the prefix key.
+                    self.reset();
+                    return self.handle_event(event, now, actions);
+                }
+                let table = std::mem::replace(&mut self.table, self.base_table.clone());
+                self.repeat_until = None;
+                if binding.repeat {
+                    self.table = table;
+                    self.repeat_until = Some(now + self.config.repeat_time);
+                }
+                for command in binding.commands {
+                    match command {
+                        Command::SwitchTable(table) => self.table = table,
+                        command => actions.push(InputAction::Execute(command)),
+                    }
+                }
+            },
+            None if repeating => {
+                self.reset();
+                self.handle_event(event, now, actions);
+            },
+            None if self.table == ROOT_TABLE => actions.push(InputAction::Forward(event)),
+            // Keys that copy mode doesn't bind are discarded.
+            None if self.table == self.base_table => {},
+            // Unbound keys after the prefix key are discarded.
+            None => self.reset(),
+        }
+    }
+
+    /// Go back to the base table.
+    fn reset(&mut self) {
+        self.table = self.base_table.clone();
+        self.repeat_until = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{
+            sync::{
+                Arc,
+                Mutex,
+            },
+            time::{
+                Duration,
+                Instant,
+            },
+        },
+        crate::{
+            command::Command,
+            copy::CopyAction,
+            layout::Side,
+        },
+    };
+    use super::{
+        Input,
+        InputAction,
+        InputConfig,
+        Key,
+        KeyTables,
+        COPY_MODE_VI_TABLE,
+        PREFIX_TABLE,
+        ROOT_TABLE,
+    };
+
+    fn input() -> Input {
+        Input::new(InputConfig::default(), Arc::new(Mutex::new(KeyTables::default())))
+    }
+
+    fn feed(input: &mut Input, bytes: &[u8], now: Instant) -> Vec<InputAction> {
+        input.decode(bytes).into_iter()
+            .flat_map(|event| input.handle(event, now))
+            .collect()
+    }
+
+    fn commands(actions: Vec<InputAction>) -> Vec<Command> {
+        actions.into_iter()
+            .filter_map(|action| match action {
+                InputAction::Execute(command) => Some(command),
+                InputAction::Forward(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_prefix() {
+        let mut input = input();
+        let now = Instant::now();
+        let actions = feed(&mut input, b"ab", now);
+        assert_eq!(actions.len(), 2);
+        assert!(actions.iter().all(|action| matches!(action, InputAction::Forward(_))));
+
+        assert!(feed(&mut input, b"\x02", now).is_empty());
+        assert_eq!(input.table, PREFIX_TABLE);
+        assert_eq!(commands(feed(&mut input, b"c", now)), vec![
+            Command::NewWindow { name: None, cwd: None, command: None },
+        ]);
+        assert_eq!(input.table, ROOT_TABLE);
+
+        assert_eq!(commands(feed(&mut input, b"\x02\x02", now)), vec![Command::SendPrefix]);
+        // Unbound keys after the prefix are discarded.
+        assert!(feed(&mut input, b"\x02z", now).is_empty());
+        assert_eq!(input.table, ROOT_TABLE);
+    }
+
+    #[test]
+    fn test_custom_prefix() {
+        let mut input = Input::new(
+            InputConfig { prefix: Key::ctrl('a'), ..InputConfig::default() },
+            Arc::new(Mutex::new(KeyTables::default())),
+        );
+        let now = Instant::now();
+        assert_eq!(feed(&mut input, b"\x02", now).len(), 1);
+        assert_eq!(commands(feed(&mut input, b"\x01n", now)), vec![Command::NextWindow]);
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mut input = input();
+        let now = Instant::now();
+        assert_eq!(commands(feed(&mut input, b"\x02\x1b[A", now)), vec![Command::SelectPane(Side::Up)]);
+        let later = now + Duration::from_millis(100);
+        assert_eq!(commands(feed(&mut input, b"\x1b[B", later)), vec![Command::SelectPane(Side::Down)]);
+        // Keys that don't repeat are read from the root table.
+        let actions = feed(&mut input, b"n", later);
+        assert!(matches!(actions.as_slice(), [InputAction::Forward(_)]));
+
+        assert_eq!(commands(feed(&mut input, b"\x02\x1b[A", now)).len(), 1);
+        let expired = now + Duration::from_secs(1);
+        let actions = feed(&mut input, b"\x1b[A", expired);
+        assert!(matches!(actions.as_slice(), [InputAction::Forward(_)]));
+    }
+
+    #[test]
+    fn test_switch_table() {
+        let mut input = input();
+        let now = Instant::now();
+        input.tables().get_mut(ROOT_TABLE)
+            .bind(Key::ctrl('t'), vec![Command::SwitchTable("custom".to_owned())], false);
+        input.tables().get_mut("custom")
+            .bind("x".parse().unwrap(), vec![Command::KillPane], false);
+        assert!(feed(&mut input, b"\x14", now).is_empty());
+        assert_eq!(input.table, "custom");
+        assert_eq!(commands(feed(&mut input, b"x", now)), vec![Command::KillPane]);
+        assert_eq!(input.table, ROOT_TABLE);
+    }
+
+    #[test]
+    fn test_base_table() {
+        let mut input = input();
+        let now = Instant::now();
+        input.set_base_table(COPY_MODE_VI_TABLE);
+        assert_eq!(commands(feed(&mut input, b"w", now)), vec![Command::CopyAction(CopyAction::NextWord)]);
+        // Unbound keys aren't sent to the pane.
+        assert!(feed(&mut input, b"z", now).is_empty());
+        assert_eq!(commands(feed(&mut input, b"\x02c", now)), vec![
+            Command::NewWindow { name: None, cwd: None, command: None },
+        ]);
+        assert_eq!(input.table, COPY_MODE_VI_TABLE);
+
+        input.set_base_table(ROOT_TABLE);
+        assert_eq!(input.table, ROOT_TABLE);
+        assert_eq!(feed(&mut input, b"w", now).len(), 1);
+    }
+}
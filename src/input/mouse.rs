@@ -0,0 +1,204 @@
+use {
+    std::time::{
+        Duration,
+        Instant,
+    },
+    crate::{
+        layout::PaneId,
+        util::Point,
+    },
+    super::key::Modifiers,
+};
+
+/// Time within which clicks at the same place make a double or triple
+/// click.
+const CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MouseAction {
+    Press,
+    Release,
+    /// The mouse moved with the button held.
+    Drag,
+}
+
+/// A mouse report read from the host.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MouseEvent {
+    pub button: MouseButton,
+    pub action: MouseAction,
+    /// 0-based host cell under the mouse.
+    pub point: Point,
+    pub modifiers: Modifiers,
+}
+
+impl MouseEvent {
+    /// Parse the parameters and final byte of a report in the SGR encoding,
+    /// `ESC [ < button ; x ; y M`, which ends with `m` on release.
+    ///
+    /// Motion without a button held isn't asked from the host, it isn't
+    /// parsed.
+    pub fn parse_sgr(parameters: &str, end: u8) -> Option<Self> {
+        let mut parameters = parameters.split(';').map(|parameter| parameter.parse::<usize>().ok());
+        let mut next = || parameters.next().flatten();
+        let (code, x, y) = (next()?, next()?, next()?);
+        let button = match code & !0b0001_1100 & !32 {
+            0 => MouseButton::Left,
+            1 => MouseButton::Middle,
+            2 => MouseButton::Right,
+            64 => MouseButton::WheelUp,
+            65 => MouseButton::WheelDown,
+            _ => return None,
+        };
+        let action = match end {
+            b'm' => MouseAction::Release,
+            b'M' if code & 32 != 0 => MouseAction::Drag,
+            b'M' => MouseAction::Press,
+            _ => return None,
+        };
+        Some(Self {
+            button,
+            action,
+            point: Point::new(x.checked_sub(1)?, y.checked_sub(1)?),
+            modifiers: Modifiers::from_bits_truncate(((code >> 2) & 0b111) as u8),
+        })
+    }
+
+    /// Get the report of the event to send to a pane, at `point` of the
+    /// pane, in the SGR encoding or in the older X10 one.
+    ///
+    /// The X10 encoding doesn't tell which button was released, and can't
+    /// go past column 223.
+    pub fn encode(&self, point: Point, sgr: bool) -> Vec<u8> {
+        let mut code = match self.button {
+            MouseButton::Left => 0,
+            MouseButton::Middle => 1,
+            MouseButton::Right => 2,
+            MouseButton::WheelUp => 64,
+            MouseButton::WheelDown => 65,
+        };
+        if self.action == MouseAction::Drag {
+            code += 32;
+        }
+        code += (self.modifiers.bits() as usize) << 2;
+        if sgr {
+            let end = if self.action == MouseAction::Release { 'm' } else { 'M' };
+            return format!("\x1b[<{};{};{}{}", code, point.x + 1, point.y + 1, end).into_bytes();
+        }
+        if self.action == MouseAction::Release {
+            code = code & !0b11 | 3;
+        }
+        let byte = |value: usize| (value + 32).min(255) as u8;
+        vec![0x1b, b'[', b'M', byte(code), byte(point.x + 1), byte(point.y + 1)]
+    }
+}
+
+/// What dragging the mouse with the left button does, from where it was
+/// pressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Drag {
+    /// Moves the border of the active window at a point.
+    Border(Point),
+    /// Selects the text of a pane from a point of the pane. Copy mode is
+    /// only entered once the mouse moves.
+    Select {
+        pane: PaneId,
+        start: Point,
+        selecting: bool,
+    },
+    /// Is reported to a pane that asked for mouse reports.
+    Report(PaneId),
+}
+
+/// What is left of the mouse events between them.
+#[derive(Debug, Default)]
+pub struct MouseState {
+    pub drag: Option<Drag>,
+    /// Time and place of the last click, and the number of clicks in a row
+    /// until it.
+    last_click: Option<(Instant, Point, usize)>,
+}
+
+impl MouseState {
+    /// Count a click at `point`, returning 1 for a single click, 2 for a
+    /// double click and 3 for a triple click.
+    pub fn click(&mut self, point: Point, now: Instant) -> usize {
+        let count = match self.last_click {
+            Some((time, last, count)) if last == point && now.duration_since(time) < CLICK_INTERVAL => {
+                count % 3 + 1
+            },
+            _ => 1,
+        };
+        self.last_click = Some((now, point, count));
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::time::{
+            Duration,
+            Instant,
+        },
+        crate::{
+            input::key::Modifiers,
+            util::point::P,
+        },
+    };
+    use super::{
+        MouseAction,
+        MouseButton,
+        MouseEvent,
+        MouseState,
+    };
+
+    #[test]
+    fn test_parse_sgr() {
+        assert_eq!(MouseEvent::parse_sgr("0;1;2", b'M'), Some(MouseEvent {
+            button: MouseButton::Left,
+            action: MouseAction::Press,
+            point: P(0, 1),
+            modifiers: Modifiers::empty(),
+        }));
+        let drag = MouseEvent::parse_sgr("48;10;5", b'M').unwrap();
+        assert_eq!((drag.button, drag.action, drag.modifiers), (MouseButton::Left, MouseAction::Drag, Modifiers::CTRL));
+        assert_eq!(MouseEvent::parse_sgr("65;3;3", b'M').unwrap().button, MouseButton::WheelDown);
+        assert_eq!(MouseEvent::parse_sgr("2;3;3", b'm').unwrap().action, MouseAction::Release);
+        // Motion without a button.
+        assert_eq!(MouseEvent::parse_sgr("35;3;3", b'M'), None);
+        assert_eq!(MouseEvent::parse_sgr("0;0;3", b'M'), None);
+    }
+
+    #[test]
+    fn test_encode() {
+        let event = MouseEvent::parse_sgr("32;300;2", b'M').unwrap();
+        assert_eq!(event.encode(P(4, 0), true), b"\x1b[<32;5;1M");
+        assert_eq!(event.encode(P(300, 0), false), b"\x1b[M@\xff!");
+        let release = MouseEvent::parse_sgr("6;1;1", b'm').unwrap();
+        assert_eq!(release.encode(P(0, 0), true), b"\x1b[<6;1;1m");
+        assert_eq!(release.encode(P(0, 0), false), b"\x1b[M'!!");
+    }
+
+    #[test]
+    fn test_click() {
+        let mut state = MouseState::default();
+        let now = Instant::now();
+        let later = |ms| now + Duration::from_millis(ms);
+        assert_eq!(state.click(P(1, 1), now), 1);
+        assert_eq!(state.click(P(1, 1), later(100)), 2);
+        assert_eq!(state.click(P(1, 1), later(200)), 3);
+        assert_eq!(state.click(P(1, 1), later(300)), 1);
+        assert_eq!(state.click(P(2, 1), later(400)), 1);
+        assert_eq!(state.click(P(2, 1), later(1000)), 1);
+    }
+}
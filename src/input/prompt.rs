@@ -0,0 +1,126 @@
+use super::key::{
+    Key,
+    KeyCode,
+    Modifiers,
+};
+
+/// A prompt open on the status line, which runs a command with the text
+/// entered.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Prompt {
+    /// Text shown before the entered text.
+    pub label: String,
+    pub text: String,
+    /// Command to run, where `%%` is replaced by the entered text. Without
+    /// it, the text is run as a command.
+    pub template: Option<String>,
+    /// Whether the command is run after each change of the text, rather
+    /// than when it is entered.
+    pub incremental: bool,
+}
+
+impl Prompt {
+    /// Get a prompt for a command.
+    pub fn command() -> Self {
+        Self {
+            label: ":".to_owned(),
+            text: String::new(),
+            template: None,
+            incremental: false,
+        }
+    }
+
+    /// Get the command to run for `text`.
+    ///
+    /// `%%` is replaced by the text with the characters that split words
+    /// escaped, so that it stays a single word in or out of double quotes.
+    pub fn command_line(&self, text: &str) -> String {
+        let template = match &self.template {
+            Some(template) => template,
+            None => return text.to_owned(),
+        };
+        let mut escaped = String::new();
+        for ch in text.chars() {
+            if ch.is_whitespace() || "\\\"';#".contains(ch) {
+                escaped.push('\\');
+            }
+            escaped.push(ch);
+        }
+        template.replace("%%", &escaped)
+    }
+}
+
+/// Result of a key pressed in a prompt.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PromptEdit {
+    /// The text was edited, or the key was ignored.
+    Edited,
+    /// The text was entered.
+    Submit(String),
+    Cancel,
+}
+
+/// Edit the text of a prompt with `key`, readline style.
+pub fn edit(text: &mut String, key: &Key) -> PromptEdit {
+    let ctrl = key.modifiers == Modifiers::CTRL;
+    match key.code {
+        KeyCode::Enter => return PromptEdit::Submit(std::mem::take(text)),
+        KeyCode::Escape => return PromptEdit::Cancel,
+        KeyCode::Char('c') | KeyCode::Char('g') if ctrl => return PromptEdit::Cancel,
+        KeyCode::Backspace => {
+            text.pop();
+        },
+        KeyCode::Char('h') if ctrl => {
+            text.pop();
+        },
+        KeyCode::Char('u') if ctrl => text.clear(),
+        KeyCode::Char('w') if ctrl => {
+            let end = text.trim_end().len();
+            let start = text[..end].rfind(' ').map_or(0, |i| i + 1);
+            text.truncate(start);
+        },
+        KeyCode::Char(ch) if key.modifiers.is_empty() || key.modifiers == Modifiers::SHIFT => {
+            text.push(ch);
+        },
+        _ => {},
+    }
+    PromptEdit::Edited
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::key::{
+        Key,
+        KeyCode,
+    };
+    use super::{
+        edit,
+        Prompt,
+        PromptEdit,
+    };
+
+    #[test]
+    fn test_edit() {
+        let mut text = String::new();
+        for ch in "neww -n x".chars() {
+            assert_eq!(edit(&mut text, &Key::plain(KeyCode::Char(ch))), PromptEdit::Edited);
+        }
+        edit(&mut text, &Key::plain(KeyCode::Backspace));
+        assert_eq!(text, "neww -n ");
+        edit(&mut text, &Key::ctrl('w'));
+        assert_eq!(text, "neww ");
+        edit(&mut text, &Key::ctrl('x'));
+        assert_eq!(text, "neww ");
+        assert_eq!(edit(&mut text, &Key::plain(KeyCode::Enter)), PromptEdit::Submit("neww ".to_owned()));
+        assert!(text.is_empty());
+        assert_eq!(edit(&mut text, &Key::ctrl('c')), PromptEdit::Cancel);
+    }
+
+    #[test]
+    fn test_command_line() {
+        let mut prompt = Prompt::command();
+        assert_eq!(prompt.command_line("neww -n x"), "neww -n x");
+        prompt.template = Some("send -X search-forward \"%%\"".to_owned());
+        assert_eq!(prompt.command_line("a \"b\"; c"), "send -X search-forward \"a\\ \\\"b\\\"\\;\\ c\"");
+    }
+}
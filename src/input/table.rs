@@ -0,0 +1,275 @@
+use {
+    std::collections::HashMap,
+    crate::{
+        command::Command,
+        copy::CopyAction,
+        layout::{
+            Direction,
+            Side,
+        },
+    },
+    super::key::{
+        Key,
+        KeyCode,
+        Modifiers,
+    },
+};
+
+/// Table of keys read before the prefix key.
+pub const ROOT_TABLE: &str = "root";
+/// Table of keys read after the prefix key.
+pub const PREFIX_TABLE: &str = "prefix";
+/// Table of keys read while a pane is in copy mode, with emacs keys.
+pub const COPY_MODE_TABLE: &str = "copy-mode";
+/// Table of keys read while a pane is in copy mode, with vi keys.
+pub const COPY_MODE_VI_TABLE: &str = "copy-mode-vi";
+
+const COPY_MODE_KEYS: &[(&str, CopyAction)] = &[
+    ("Left", CopyAction::CursorLeft),
+    ("C-b", CopyAction::CursorLeft),
+    ("Right", CopyAction::CursorRight),
+    ("C-f", CopyAction::CursorRight),
+    ("Up", CopyAction::CursorUp),
+    ("C-p", CopyAction::CursorUp),
+    ("Down", CopyAction::CursorDown),
+    ("C-n", CopyAction::CursorDown),
+    ("Home", CopyAction::StartOfLine),
+    ("C-a", CopyAction::StartOfLine),
+    ("End", CopyAction::EndOfLine),
+    ("C-e", CopyAction::EndOfLine),
+    ("M-m", CopyAction::BackToIndentation),
+    ("M-f", CopyAction::NextWordEnd),
+    ("M-b", CopyAction::PreviousWord),
+    ("M-R", CopyAction::TopLine),
+    ("M-r", CopyAction::MiddleLine),
+    ("M-<", CopyAction::HistoryTop),
+    ("M->", CopyAction::HistoryBottom),
+    ("PPage", CopyAction::PageUp),
+    ("M-v", CopyAction::PageUp),
+    ("NPage", CopyAction::PageDown),
+    ("C-v", CopyAction::PageDown),
+    ("C-Up", CopyAction::ScrollUp),
+    ("C-Down", CopyAction::ScrollDown),
+    ("C-Space", CopyAction::BeginSelection),
+    ("R", CopyAction::RectangleToggle),
+    ("C-g", CopyAction::ClearSelection),
+    ("n", CopyAction::SearchAgain),
+    ("N", CopyAction::SearchReverse),
+    ("M-w", CopyAction::CopySelectionAndCancel),
+    ("Escape", CopyAction::Cancel),
+    ("q", CopyAction::Cancel),
+];
+
+const COPY_MODE_VI_KEYS: &[(&str, CopyAction)] = &[
+    ("h", CopyAction::CursorLeft),
+    ("Left", CopyAction::CursorLeft),
+    ("l", CopyAction::CursorRight),
+    ("Right", CopyAction::CursorRight),
+    ("k", CopyAction::CursorUp),
+    ("Up", CopyAction::CursorUp),
+    ("j", CopyAction::CursorDown),
+    ("Down", CopyAction::CursorDown),
+    ("0", CopyAction::StartOfLine),
+    ("$", CopyAction::EndOfLine),
+    ("^", CopyAction::BackToIndentation),
+    ("w", CopyAction::NextWord),
+    ("b", CopyAction::PreviousWord),
+    ("e", CopyAction::NextWordEnd),
+    ("H", CopyAction::TopLine),
+    ("M", CopyAction::MiddleLine),
+    ("L", CopyAction::BottomLine),
+    ("g", CopyAction::HistoryTop),
+    ("G", CopyAction::HistoryBottom),
+    ("C-b", CopyAction::PageUp),
+    ("PPage", CopyAction::PageUp),
+    ("C-f", CopyAction::PageDown),
+    ("NPage", CopyAction::PageDown),
+    ("C-u", CopyAction::HalfpageUp),
+    ("C-d", CopyAction::HalfpageDown),
+    ("C-y", CopyAction::ScrollUp),
+    ("C-e", CopyAction::ScrollDown),
+    ("Space", CopyAction::BeginSelection),
+    ("v", CopyAction::BeginSelection),
+    ("V", CopyAction::SelectLine),
+    ("C-v", CopyAction::RectangleToggle),
+    ("o", CopyAction::OtherEnd),
+    ("n", CopyAction::SearchAgain),
+    ("N", CopyAction::SearchReverse),
+    ("Escape", CopyAction::ClearSelection),
+    ("Enter", CopyAction::CopySelectionAndCancel),
+    ("y", CopyAction::CopySelectionAndCancel),
+    ("q", CopyAction::Cancel),
+    ("C-c", CopyAction::Cancel),
+];
+
+/// Commands bound to a key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Binding {
+    pub commands: Vec<Command>,
+    /// Whether the key can be pressed again without the prefix key until
+    /// the repeat time runs out.
+    pub repeat: bool,
+}
+
+/// Bindings of keys to commands.
+#[derive(Clone, Debug, Default)]
+pub struct KeyTable {
+    bindings: HashMap<Key, Binding>,
+}
+
+impl KeyTable {
+    pub fn get(&self, key: &Key) -> Option<&Binding> {
+        self.bindings.get(key)
+    }
+
+    pub fn bind(&mut self, key: Key, commands: Vec<Command>, repeat: bool) {
+        self.bindings.insert(key, Binding { commands, repeat });
+    }
+
+    pub fn unbind(&mut self, key: &Key) -> Option<Binding> {
+        self.bindings.remove(key)
+    }
+}
+
+/// Key tables by name.
+#[derive(Clone, Debug)]
+pub struct KeyTables {
+    tables: HashMap<String, KeyTable>,
+}
+
+impl KeyTables {
+    pub fn get(&self, name: &str) -> Option<&KeyTable> {
+        self.tables.get(name)
+    }
+
+    /// Get table `name`, creating it if it doesn't exist.
+    pub fn get_mut(&mut self, name: &str) -> &mut KeyTable {
+        self.tables.entry(name.to_owned()).or_default()
+    }
+
+    /// Get the command bound to `key` in table `name`.
+    pub fn lookup(&self, name: &str, key: &Key) -> Option<&Binding> {
+        self.get(name).and_then(|table| table.get(key))
+    }
+}
+
+/// Get a copy mode table binding keys by name to copy mode commands.
+fn copy_mode_table(keys: &[(&str, CopyAction)]) -> KeyTable {
+    let mut table = KeyTable::default();
+    for (name, action) in keys {
+        let key = name.parse().expect("invalid key name");
+        table.bind(key, vec![Command::CopyAction(*action)], false);
+    }
+    table
+}
+
+/// Get a command that prompts for the pattern of copy mode search `name`.
+fn search_prompt(label: &str, name: &str, incremental: bool) -> Vec<Command> {
+    vec![Command::Prompt {
+        label: Some(label.to_owned()),
+        incremental,
+        template: Some(format!("send -X {} \"%%\"", name)),
+    }]
+}
+
+impl Default for KeyTables {
+    fn default() -> Self {
+        let mut tables = HashMap::new();
+        tables.insert(ROOT_TABLE.to_owned(), KeyTable::default());
+        let mut copy_mode = copy_mode_table(COPY_MODE_KEYS);
+        copy_mode.bind(Key::ctrl('s'), search_prompt("(search down)", "search-forward-incremental", true), false);
+        copy_mode.bind(Key::ctrl('r'), search_prompt("(search up)", "search-backward-incremental", true), false);
+        tables.insert(COPY_MODE_TABLE.to_owned(), copy_mode);
+        let mut copy_mode_vi = copy_mode_table(COPY_MODE_VI_KEYS);
+        copy_mode_vi.bind(Key::plain(KeyCode::Char('/')), search_prompt("(search down)", "search-forward", false), false);
+        copy_mode_vi.bind(Key::plain(KeyCode::Char('?')), search_prompt("(search up)", "search-backward", false), false);
+        tables.insert(COPY_MODE_VI_TABLE.to_owned(), copy_mode_vi);
+
+        let mut prefix = KeyTable::default();
+        let char_key = |ch| Key::plain(KeyCode::Char(ch));
+        prefix.bind(Key::ctrl('b'), vec![Command::SendPrefix], false);
+        prefix.bind(char_key('c'), vec![Command::NewWindow { name: None, cwd: None, command: None }], false);
+        prefix.bind(char_key('&'), vec![Command::KillWindow], false);
+        prefix.bind(char_key('n'), vec![Command::NextWindow], false);
+        prefix.bind(char_key('p'), vec![Command::PreviousWindow], false);
+        for index in 0..10 {
+            let ch = std::char::from_digit(index as u32, 10).unwrap();
+            prefix.bind(char_key(ch), vec![Command::SelectWindow(index)], false);
+        }
+        prefix.bind(char_key('%'), vec![Command::SplitWindow {
+            direction: Direction::Horizontal,
+            cwd: None,
+        }], false);
+        prefix.bind(char_key('"'), vec![Command::SplitWindow {
+            direction: Direction::Vertical,
+            cwd: None,
+        }], false);
+        prefix.bind(char_key('x'), vec![Command::KillPane], false);
+        prefix.bind(char_key('d'), vec![Command::DetachClient], false);
+        prefix.bind(char_key(':'), vec![Command::Prompt {
+            label: None,
+            incremental: false,
+            template: None,
+        }], false);
+        prefix.bind(char_key('['), vec![Command::CopyMode { page_up: false }], false);
+        prefix.bind(Key::plain(KeyCode::PageUp), vec![Command::CopyMode { page_up: true }], false);
+        prefix.bind(char_key(']'), vec![Command::PasteBuffer {
+            name: None,
+            delete: false,
+            raw: false,
+        }], false);
+        prefix.bind(char_key('#'), vec![Command::ListBuffers], false);
+        prefix.bind(char_key('='), vec![Command::ChooseBuffer], false);
+        prefix.bind(char_key('-'), vec![Command::DeleteBuffer { name: None }], false);
+        let sides = [
+            (KeyCode::Left, Side::Left),
+            (KeyCode::Right, Side::Right),
+            (KeyCode::Up, Side::Up),
+            (KeyCode::Down, Side::Down),
+        ];
+        for &(code, side) in sides.iter() {
+            prefix.bind(Key::plain(code), vec![Command::SelectPane(side)], true);
+            prefix.bind(Key::new(code, Modifiers::CTRL), vec![Command::ResizePane(side, 1)], true);
+            prefix.bind(Key::new(code, Modifiers::META), vec![Command::ResizePane(side, 5)], true);
+        }
+        tables.insert(PREFIX_TABLE.to_owned(), prefix);
+
+        Self { tables }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        command::Command,
+        copy::CopyAction,
+        input::key::Key,
+    };
+    use super::{
+        KeyTables,
+        COPY_MODE_TABLE,
+        COPY_MODE_VI_TABLE,
+    };
+
+    #[test]
+    fn test_copy_mode_tables() {
+        let tables = KeyTables::default();
+        let lookup = |table, key: &str| {
+            tables.lookup(table, &key.parse::<Key>().unwrap()).map(|binding| binding.commands.clone())
+        };
+        assert_eq!(lookup(COPY_MODE_TABLE, "C-Space"),
+            Some(vec![Command::CopyAction(CopyAction::BeginSelection)]));
+        assert_eq!(lookup(COPY_MODE_TABLE, "M-<"),
+            Some(vec![Command::CopyAction(CopyAction::HistoryTop)]));
+        assert_eq!(lookup(COPY_MODE_VI_TABLE, "$"),
+            Some(vec![Command::CopyAction(CopyAction::EndOfLine)]));
+        assert_eq!(lookup(COPY_MODE_VI_TABLE, "C-v"),
+            Some(vec![Command::CopyAction(CopyAction::RectangleToggle)]));
+        assert_eq!(lookup(COPY_MODE_VI_TABLE, "x"), None);
+        assert_eq!(lookup(COPY_MODE_TABLE, "C-s"), Some(vec![Command::Prompt {
+            label: Some("(search down)".to_owned()),
+            incremental: true,
+            template: Some("send -X search-forward-incremental \"%%\"".to_owned()),
+        }]));
+    }
+}
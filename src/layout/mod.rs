@@ -0,0 +1,710 @@
+use {
+    std::{
+        cmp::{
+            max,
+            min,
+        },
+        mem,
+        sync::Arc,
+    },
+    anyhow::{
+        anyhow,
+        Result,
+    },
+    crate::util::Point,
+};
+
+/// Identifier of a pane in a layout.
+pub type PaneId = usize;
+
+/// A pane of a layout, which is told its new size whenever the layout
+/// changes it.
+pub trait Pane {
+    fn resize(&self, size: Point) -> Result<()>;
+}
+
+impl<T: Pane> Pane for Arc<T> {
+    fn resize(&self, size: Point) -> Result<()> {
+        T::resize(self, size)
+    }
+}
+
+/// Direction in which the panes of a split are laid out.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    /// Panes are side by side, separated by vertical borders.
+    Horizontal,
+    /// Panes are stacked on top of each other, separated by horizontal
+    /// borders.
+    Vertical,
+}
+
+impl Direction {
+    /// Get the length of `point` along this direction.
+    fn along(self, point: Point) -> usize {
+        match self {
+            Self::Horizontal => point.x,
+            Self::Vertical => point.y,
+        }
+    }
+
+    /// Get `point` with its length along this direction set to `length`.
+    fn with(self, point: Point, length: usize) -> Point {
+        match self {
+            Self::Horizontal => Point::new(length, point.y),
+            Self::Vertical => Point::new(point.x, length),
+        }
+    }
+}
+
+/// Side of a pane, used to find its neighbours.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Side {
+    /// Get the direction of the splits that have panes on this side.
+    pub fn direction(self) -> Direction {
+        match self {
+            Self::Left | Self::Right => Direction::Horizontal,
+            Self::Up | Self::Down => Direction::Vertical,
+        }
+    }
+}
+
+/// A rectangle of host cells.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub start: Point,
+    pub size: Point,
+}
+
+impl Rect {
+    pub fn new(start: Point, size: Point) -> Self {
+        Self { start, size }
+    }
+
+    /// Returns whether `point` is inside the rectangle.
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.start.x && point.x < self.start.x + self.size.x &&
+            point.y >= self.start.y && point.y < self.start.y + self.size.y
+    }
+
+    /// Get the position of `point` from the start of the rectangle, moved
+    /// inside it when it is outside.
+    pub fn relative(&self, point: Point) -> Point {
+        Point::new(
+            min(point.x.saturating_sub(self.start.x), self.size.x.saturating_sub(1)),
+            min(point.y.saturating_sub(self.start.y), self.size.y.saturating_sub(1)),
+        )
+    }
+}
+
+enum Node<T> {
+    Pane {
+        id: PaneId,
+        pane: T,
+        rect: Rect,
+    },
+    /// Children of a split, with their length along the split direction.
+    Split {
+        direction: Direction,
+        children: Vec<(Node<T>, usize)>,
+    },
+}
+
+impl<T: Pane> Node<T> {
+    fn contains(&self, target: PaneId) -> bool {
+        match self {
+            Self::Pane { id, .. } => *id == target,
+            Self::Split { children, .. } => children.iter().any(|(child, _)| child.contains(target)),
+        }
+    }
+
+    fn find(&self, target: PaneId) -> Option<(&T, Rect)> {
+        match self {
+            Self::Pane { id, pane, rect } if *id == target => Some((pane, *rect)),
+            Self::Pane { .. } => None,
+            Self::Split { children, .. } => children.iter().find_map(|(child, _)| child.find(target)),
+        }
+    }
+
+    /// Get the rectangle covered by the node, with the borders between
+    /// its panes.
+    fn rect(&self) -> Rect {
+        match self {
+            Self::Pane { rect, .. } => *rect,
+            Self::Split { children, .. } => {
+                let start = children[0].0.rect().start;
+                let last = children[children.len() - 1].0.rect();
+                Rect::new(start, last.start + last.size - start)
+            },
+        }
+    }
+
+    /// Move the border at `point` towards `to`, growing the children
+    /// before it. Returns where the border is afterwards, if there is one
+    /// at `point`.
+    fn move_border(&mut self, point: Point, to: Point) -> Option<Point> {
+        let (direction, children) = match self {
+            Self::Pane { .. } => return None,
+            Self::Split { direction, children } => (*direction, children),
+        };
+        for i in 0..children.len() {
+            let rect = children[i].0.rect();
+            if rect.contains(point) {
+                return children[i].0.move_border(point, to);
+            }
+            let end = direction.along(rect.start + rect.size);
+            if i + 1 < children.len() && direction.along(point) == end {
+                let total = children[i].1 + children[i + 1].1;
+                let delta = direction.along(to) as isize - end as isize;
+                let length = (children[i].1 as isize + delta)
+                    .max(1)
+                    .min(total as isize - 1) as usize;
+                let moved = length as isize - children[i].1 as isize;
+                children[i].1 = length;
+                children[i + 1].1 = total - length;
+                return Some(direction.with(point, (end as isize + moved) as usize));
+            }
+        }
+        None
+    }
+
+    fn panes<'a>(&'a self, panes: &mut Vec<(PaneId, &'a T, Rect)>) {
+        match self {
+            Self::Pane { id, pane, rect } => panes.push((*id, pane, *rect)),
+            Self::Split { children, .. } => children.iter().for_each(|(child, _)| child.panes(panes)),
+        }
+    }
+
+    /// Lay out the node inside `rect`, resizing every pane whose size
+    /// changed. All panes are resized even if some of them fail.
+    fn arrange(&mut self, area: Rect) -> Result<()> {
+        match self {
+            Self::Pane { pane, rect, .. } => {
+                let resized = rect.size != area.size;
+                *rect = area;
+                if resized {
+                    pane.resize(area.size)?;
+                }
+                Ok(())
+            },
+            Self::Split { direction, children } => {
+                let direction = *direction;
+                let borders = children.len() - 1;
+                let available = direction.along(area.size).saturating_sub(borders);
+                let mut lengths: Vec<usize> = children.iter().map(|(_, length)| *length).collect();
+                distribute(&mut lengths, available);
+
+                let mut result = Ok(());
+                let mut offset = direction.along(area.start);
+                for ((child, length), new_length) in children.iter_mut().zip(lengths) {
+                    *length = new_length;
+                    let start = direction.with(area.start, offset);
+                    let size = direction.with(area.size, new_length);
+                    if let Err(e) = child.arrange(Rect::new(start, size)) {
+                        result = result.and(Err(e));
+                    }
+                    offset += new_length + 1;
+                }
+                result
+            },
+        }
+    }
+}
+
+/// Scale `lengths` so that they add up to `total`, keeping every length at
+/// least 1 when possible.
+fn distribute(lengths: &mut [usize], total: usize) {
+    let sum: usize = lengths.iter().sum();
+    if sum == total || lengths.is_empty() {
+        return;
+    }
+    for length in lengths.iter_mut() {
+        *length = max(1, *length * total / max(sum, 1));
+    }
+    let mut sum: usize = lengths.iter().sum();
+    if sum < total {
+        *lengths.last_mut().unwrap() += total - sum;
+    }
+    while sum > total {
+        let largest = lengths.iter_mut().max().unwrap();
+        if *largest <= 1 {
+            break;
+        }
+        *largest -= 1;
+        sum -= 1;
+    }
+}
+
+/// Tree of horizontal and vertical splits whose leaves are panes.
+///
+/// Panes are separated by one cell borders, which are the cells of the
+/// layout that aren't covered by any pane.
+pub struct Layout<T: Pane> {
+    root: Option<Node<T>>,
+    size: Point,
+    next_id: PaneId,
+}
+
+impl<T: Pane> Layout<T> {
+    /// Create a layout with a single pane that covers an area of `size`.
+    pub fn new(size: Point, pane: T) -> Self {
+        Self {
+            root: Some(Node::Pane {
+                id: 0,
+                pane,
+                rect: Rect::new(Point::default(), size),
+            }),
+            size,
+            next_id: 1,
+        }
+    }
+
+    /// Returns whether the last pane of the layout was closed.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Get a pane and its rectangle.
+    pub fn pane(&self, id: PaneId) -> Option<(&T, Rect)> {
+        self.root.as_ref().and_then(|root| root.find(id))
+    }
+
+    /// Get every pane with its rectangle, from left to right and from top
+    /// to bottom.
+    pub fn panes(&self) -> Vec<(PaneId, &T, Rect)> {
+        let mut panes = Vec::new();
+        if let Some(root) = &self.root {
+            root.panes(&mut panes);
+        }
+        panes
+    }
+
+    /// Get the pane that covers `point`, if it isn't on a border.
+    pub fn pane_at(&self, point: Point) -> Option<PaneId> {
+        self.panes().into_iter()
+            .find(|(_, _, rect)| rect.contains(point))
+            .map(|(id, _, _)| id)
+    }
+
+    /// Get the pane next to pane `id` on `side`, across the border.
+    pub fn neighbour(&self, id: PaneId, side: Side) -> Option<PaneId> {
+        let (_, rect) = self.pane(id)?;
+        let end = rect.start + rect.size;
+        // The cell just past the border, if it is inside the layout.
+        let across = match side {
+            Side::Left if rect.start.x >= 2 => rect.start.x - 2,
+            Side::Right if end.x + 1 < self.size.x => end.x + 1,
+            Side::Up if rect.start.y >= 2 => rect.start.y - 2,
+            Side::Down if end.y + 1 < self.size.y => end.y + 1,
+            _ => return None,
+        };
+        let direction = side.direction();
+        // Walk along the border, as it may be crossed by borders of other
+        // splits.
+        let (from, to) = match direction {
+            Direction::Horizontal => (rect.start.y, end.y),
+            Direction::Vertical => (rect.start.x, end.x),
+        };
+        (from..to).find_map(|along| {
+            let point = match direction {
+                Direction::Horizontal => Point::new(across, along),
+                Direction::Vertical => Point::new(along, across),
+            };
+            self.pane_at(point)
+        })
+    }
+
+    /// Resize the area covered by the layout, resizing its panes.
+    pub fn resize(&mut self, size: Point) -> Result<()> {
+        self.size = size;
+        self.arrange()
+    }
+
+    /// Split pane `id` in two, adding a pane after it in `direction`.
+    ///
+    /// `spawn` is called with the size of the new pane to create it.
+    pub fn split<F>(&mut self, id: PaneId, direction: Direction, spawn: F) -> Result<PaneId>
+        where F: FnOnce(Point) -> Result<T>
+    {
+        let (_, rect) = self.pane(id).ok_or_else(|| anyhow!("no such pane: {}", id))?;
+        let length = direction.along(rect.size);
+        if length < 3 {
+            return Err(anyhow!("pane {} is too small to split", id));
+        }
+        let new_length = (length - 1) / 2;
+        let old_length = length - 1 - new_length;
+        let new_start = direction.with(rect.start, direction.along(rect.start) + old_length + 1);
+        let new_rect = Rect::new(new_start, direction.with(rect.size, new_length));
+        let new_id = self.next_id;
+        let new_node = Node::Pane {
+            id: new_id,
+            pane: spawn(new_rect.size)?,
+            rect: new_rect,
+        };
+        self.next_id += 1;
+
+        let root = self.root.as_mut().unwrap();
+        let mut new_node = Some(new_node);
+        if !Self::insert_into_split(root, id, direction, old_length, &mut new_node) {
+            Self::replace_with_split(root, id, direction, old_length, &mut new_node);
+        }
+        self.arrange()?;
+        Ok(new_id)
+    }
+
+    /// Insert `new_node` after pane `id` if its parent is split in
+    /// `direction`.
+    fn insert_into_split(
+        node: &mut Node<T>,
+        id: PaneId,
+        direction: Direction,
+        old_length: usize,
+        new_node: &mut Option<Node<T>>,
+    ) -> bool {
+        if let Node::Split { direction: split_direction, children } = node {
+            let position = children.iter().position(|(child, _)| match child {
+                Node::Pane { id: child_id, .. } => *child_id == id,
+                _ => false,
+            });
+            match position {
+                Some(i) if *split_direction == direction => {
+                    let new_length = children[i].1 - 1 - old_length;
+                    children[i].1 = old_length;
+                    children.insert(i + 1, (new_node.take().unwrap(), new_length));
+                    true
+                },
+                Some(_) => false,
+                None => children.iter_mut().any(|(child, _)| {
+                    Self::insert_into_split(child, id, direction, old_length, new_node)
+                }),
+            }
+        } else {
+            false
+        }
+    }
+
+    /// Replace pane `id` with a split in `direction` that contains the pane
+    /// and `new_node`.
+    fn replace_with_split(
+        node: &mut Node<T>,
+        id: PaneId,
+        direction: Direction,
+        old_length: usize,
+        new_node: &mut Option<Node<T>>,
+    ) -> bool {
+        match node {
+            Node::Pane { id: pane_id, rect, .. } if *pane_id == id => {
+                let new_length = direction.along(rect.size) - 1 - old_length;
+                let placeholder = Node::Split { direction, children: Vec::new() };
+                let old_node = mem::replace(node, placeholder);
+                if let Node::Split { children, .. } = node {
+                    children.push((old_node, old_length));
+                    children.push((new_node.take().unwrap(), new_length));
+                }
+                true
+            },
+            Node::Pane { .. } => false,
+            Node::Split { children, .. } => children.iter_mut().any(|(child, _)| {
+                Self::replace_with_split(child, id, direction, old_length, new_node)
+            }),
+        }
+    }
+
+    /// Close pane `id`, giving its space to a neighbour, and return it.
+    pub fn close(&mut self, id: PaneId) -> Result<T> {
+        let root = self.root.take().ok_or_else(|| anyhow!("no such pane: {}", id))?;
+        let (root, pane) = Self::remove(root, id);
+        self.root = root;
+        let pane = pane.ok_or_else(|| anyhow!("no such pane: {}", id))?;
+        self.arrange()?;
+        Ok(pane)
+    }
+
+    /// Remove pane `id` from `node`, returning what is left of the node and
+    /// the removed pane.
+    fn remove(node: Node<T>, id: PaneId) -> (Option<Node<T>>, Option<T>) {
+        match node {
+            Node::Pane { id: pane_id, pane, .. } if pane_id == id => (None, Some(pane)),
+            Node::Pane { .. } => (Some(node), None),
+            Node::Split { direction, mut children } => {
+                let i = match children.iter().position(|(child, _)| child.contains(id)) {
+                    Some(i) => i,
+                    None => return (Some(Node::Split { direction, children }), None),
+                };
+                let (child, length) = children.remove(i);
+                let (child, pane) = Self::remove(child, id);
+                match child {
+                    Some(child) => children.insert(i, (child, length)),
+                    None => {
+                        // The previous pane takes the freed space, or the
+                        // next one when the first pane was closed.
+                        let neighbour = if i > 0 { i - 1 } else { 0 };
+                        children[neighbour].1 += length + 1;
+                    },
+                }
+                if children.len() == 1 {
+                    (children.pop().map(|(child, _)| child), pane)
+                } else {
+                    (Some(Node::Split { direction, children }), pane)
+                }
+            },
+        }
+    }
+
+    /// Grow pane `id` by `delta` cells in `direction`, shrinking its
+    /// neighbour. Negative values shrink the pane.
+    ///
+    /// Returns whether the pane has a neighbour in that direction.
+    pub fn resize_pane(&mut self, id: PaneId, direction: Direction, delta: isize) -> Result<bool> {
+        let resized = match &mut self.root {
+            Some(root) => Self::adjust(root, id, direction, delta) == Adjust::Done,
+            None => false,
+        };
+        if resized {
+            self.arrange()?;
+        }
+        Ok(resized)
+    }
+
+    /// Move the border at `point` towards `to`, resizing the panes on
+    /// both sides.
+    ///
+    /// Returns where the border is afterwards, which is short of `to` when
+    /// the panes can't shrink any more, or `None` if there is no border at
+    /// `point`.
+    pub fn move_border(&mut self, point: Point, to: Point) -> Result<Option<Point>> {
+        if point.x >= self.size.x || point.y >= self.size.y || self.pane_at(point).is_some() {
+            return Ok(None);
+        }
+        let moved = self.root.as_mut().and_then(|root| root.move_border(point, to));
+        if moved.is_some() {
+            self.arrange()?;
+        }
+        Ok(moved)
+    }
+
+    fn adjust(node: &mut Node<T>, id: PaneId, direction: Direction, delta: isize) -> Adjust {
+        match node {
+            Node::Pane { id: pane_id, .. } if *pane_id == id => Adjust::Pending,
+            Node::Pane { .. } => Adjust::NotFound,
+            Node::Split { direction: split_direction, children } => {
+                for i in 0..children.len() {
+                    match Self::adjust(&mut children[i].0, id, direction, delta) {
+                        Adjust::NotFound => continue,
+                        Adjust::Pending if *split_direction == direction => {
+                            let neighbour = if i + 1 < children.len() { i + 1 } else { i - 1 };
+                            let total = children[i].1 + children[neighbour].1;
+                            let length = (children[i].1 as isize + delta)
+                                .max(1)
+                                .min(total as isize - 1) as usize;
+                            children[i].1 = length;
+                            children[neighbour].1 = total - length;
+                            return Adjust::Done;
+                        },
+                        result => return result,
+                    }
+                }
+                Adjust::NotFound
+            },
+        }
+    }
+
+    fn arrange(&mut self) -> Result<()> {
+        let rect = Rect::new(Point::default(), self.size);
+        match &mut self.root {
+            Some(root) => root.arrange(rect),
+            None => Ok(()),
+        }
+    }
+}
+
+/// State of a pane resize while looking for the pane.
+#[derive(Debug, Eq, PartialEq)]
+enum Adjust {
+    NotFound,
+    /// The pane was found, but none of the splits containing it so far was
+    /// in the right direction.
+    Pending,
+    Done,
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{
+            cell::Cell,
+            rc::Rc,
+        },
+        anyhow::Result,
+        crate::util::{
+            point::P,
+            Point,
+        },
+    };
+    use super::{
+        Direction,
+        Layout,
+        Pane,
+        Rect,
+        Side,
+    };
+
+    /// Pane that records its size.
+    #[derive(Clone, Default)]
+    struct TestPane(Rc<Cell<Point>>);
+
+    impl Pane for TestPane {
+        fn resize(&self, size: Point) -> Result<()> {
+            self.0.set(size);
+            Ok(())
+        }
+    }
+
+    fn spawn(pane: &TestPane) -> impl FnOnce(Point) -> Result<TestPane> + '_ {
+        move |size| {
+            pane.0.set(size);
+            Ok(pane.clone())
+        }
+    }
+
+    fn rects(layout: &Layout<TestPane>) -> Vec<(usize, Rect)> {
+        layout.panes().into_iter().map(|(id, _, rect)| (id, rect)).collect()
+    }
+
+    #[test]
+    fn test_split() {
+        let first = TestPane::default();
+        let second = TestPane::default();
+        let third = TestPane::default();
+        let mut layout = Layout::new(P(81, 24), first.clone());
+
+        let id = layout.split(0, Direction::Horizontal, spawn(&second)).unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(rects(&layout), vec![
+            (0, Rect::new(P(0, 0), P(40, 24))),
+            (1, Rect::new(P(41, 0), P(40, 24))),
+        ]);
+        assert_eq!(first.0.get(), P(40, 24));
+        assert_eq!(second.0.get(), P(40, 24));
+
+        layout.split(1, Direction::Vertical, spawn(&third)).unwrap();
+        assert_eq!(rects(&layout), vec![
+            (0, Rect::new(P(0, 0), P(40, 24))),
+            (1, Rect::new(P(41, 0), P(40, 12))),
+            (2, Rect::new(P(41, 13), P(40, 11))),
+        ]);
+        assert_eq!(second.0.get(), P(40, 12));
+        assert_eq!(third.0.get(), P(40, 11));
+        assert_eq!(layout.pane_at(P(50, 20)), Some(2));
+        assert_eq!(layout.pane_at(P(40, 20)), None);
+    }
+
+    #[test]
+    fn test_split_same_direction() {
+        let mut layout = Layout::new(P(80, 24), TestPane::default());
+        layout.split(0, Direction::Horizontal, spawn(&TestPane::default())).unwrap();
+        layout.split(0, Direction::Horizontal, spawn(&TestPane::default())).unwrap();
+        assert_eq!(rects(&layout), vec![
+            (0, Rect::new(P(0, 0), P(20, 24))),
+            (2, Rect::new(P(21, 0), P(19, 24))),
+            (1, Rect::new(P(41, 0), P(39, 24))),
+        ]);
+        assert!(layout.split(2, Direction::Horizontal, spawn(&TestPane::default())).is_ok());
+        let mut tiny = Layout::new(P(2, 2), TestPane::default());
+        assert!(tiny.split(0, Direction::Vertical, spawn(&TestPane::default())).is_err());
+    }
+
+    #[test]
+    fn test_close() {
+        let first = TestPane::default();
+        let mut layout = Layout::new(P(81, 24), first.clone());
+        layout.split(0, Direction::Horizontal, spawn(&TestPane::default())).unwrap();
+        layout.split(1, Direction::Vertical, spawn(&TestPane::default())).unwrap();
+
+        layout.close(0).unwrap();
+        assert_eq!(rects(&layout), vec![
+            (1, Rect::new(P(0, 0), P(81, 12))),
+            (2, Rect::new(P(0, 13), P(81, 11))),
+        ]);
+        layout.close(2).unwrap();
+        assert_eq!(rects(&layout), vec![(1, Rect::new(P(0, 0), P(81, 24)))]);
+        assert!(layout.close(0).is_err());
+        layout.close(1).unwrap();
+        assert!(layout.is_empty());
+    }
+
+    #[test]
+    fn test_resize_pane() {
+        let first = TestPane::default();
+        let second = TestPane::default();
+        let mut layout = Layout::new(P(81, 24), first.clone());
+        layout.split(0, Direction::Horizontal, spawn(&second)).unwrap();
+
+        assert!(layout.resize_pane(1, Direction::Horizontal, 5).unwrap());
+        assert_eq!(first.0.get(), P(35, 24));
+        assert_eq!(second.0.get(), P(45, 24));
+        assert!(layout.resize_pane(0, Direction::Horizontal, 100).unwrap());
+        assert_eq!(first.0.get(), P(79, 24));
+        assert_eq!(second.0.get(), P(1, 24));
+        assert!(!layout.resize_pane(0, Direction::Vertical, 1).unwrap());
+    }
+
+    #[test]
+    fn test_neighbour() {
+        let mut layout = Layout::new(P(81, 24), TestPane::default());
+        layout.split(0, Direction::Horizontal, spawn(&TestPane::default())).unwrap();
+        layout.split(1, Direction::Vertical, spawn(&TestPane::default())).unwrap();
+
+        assert_eq!(layout.neighbour(0, Side::Right), Some(1));
+        assert_eq!(layout.neighbour(0, Side::Left), None);
+        assert_eq!(layout.neighbour(0, Side::Down), None);
+        assert_eq!(layout.neighbour(2, Side::Left), Some(0));
+        assert_eq!(layout.neighbour(2, Side::Up), Some(1));
+        assert_eq!(layout.neighbour(1, Side::Down), Some(2));
+        assert_eq!(layout.neighbour(1, Side::Right), None);
+    }
+
+    #[test]
+    fn test_move_border() {
+        let first = TestPane::default();
+        let second = TestPane::default();
+        let mut layout = Layout::new(P(81, 24), first.clone());
+        layout.split(0, Direction::Horizontal, spawn(&TestPane::default())).unwrap();
+        layout.split(1, Direction::Vertical, spawn(&second)).unwrap();
+
+        assert_eq!(layout.move_border(P(40, 5), P(30, 9)).unwrap(), Some(P(30, 5)));
+        assert_eq!(first.0.get(), P(30, 24));
+        assert_eq!(layout.pane(1).unwrap().1, Rect::new(P(31, 0), P(50, 12)));
+        assert_eq!(layout.move_border(P(50, 12), P(50, 20)).unwrap(), Some(P(50, 20)));
+        assert_eq!(second.0.get(), P(50, 3));
+        // The panes keep at least one cell.
+        assert_eq!(layout.move_border(P(30, 0), P(0, 0)).unwrap(), Some(P(1, 0)));
+        assert_eq!(first.0.get(), P(1, 24));
+        assert_eq!(layout.move_border(P(50, 12), P(50, 0)).unwrap(), None);
+        assert_eq!(layout.move_border(P(100, 0), P(50, 0)).unwrap(), None);
+
+        let rect = layout.pane(1).unwrap().1;
+        assert_eq!(rect.relative(P(40, 3)), P(38, 3));
+        assert_eq!(rect.relative(P(0, 30)), P(0, 19));
+    }
+
+    #[test]
+    fn test_resize_layout() {
+        let first = TestPane::default();
+        let second = TestPane::default();
+        let mut layout = Layout::new(P(81, 24), first.clone());
+        layout.split(0, Direction::Horizontal, spawn(&second)).unwrap();
+
+        layout.resize(P(41, 10)).unwrap();
+        assert_eq!(first.0.get(), P(20, 10));
+        assert_eq!(second.0.get(), P(20, 10));
+        assert_eq!(rects(&layout)[1].1, Rect::new(P(21, 0), P(20, 10)));
+    }
+}
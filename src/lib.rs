@@ -4,9 +4,18 @@ extern crate base64;
 extern crate vte;
 
 mod ansi;
+mod buffer;
+mod client;
+mod command;
+mod copy;
 mod grid;
+mod input;
+mod layout;
 #[macro_use] mod macros;
+mod options;
 mod pty;
 mod screen;
+mod server;
+mod session;
 mod term;
 mod util;
@@ -10,157 +10,102 @@ extern crate tokio;
 extern crate vte;
 
 mod ansi;
+mod buffer;
+mod client;
+mod command;
+mod copy;
 mod grid;
+mod input;
+mod layout;
 #[macro_use] mod macros;
+mod options;
 mod pty;
 mod screen;
+mod server;
+mod session;
 mod term;
 mod util;
 
 use {
     std::{
-        io,
-        sync::Arc,
-        os::unix::io::{
-            RawFd,
-            AsRawFd,
-        },
+        env,
+        path::PathBuf,
+        process,
         time::Duration,
     },
     anyhow::{
+        anyhow,
         Context,
         Result,
     },
     log::trace,
-    tokio::{
-        io::{
-            self as aio,
-            AsyncReadExt,
-        },
-        process::Command,
-        runtime::Builder as RuntimeBuilder,
-        task,
-    },
-    termios::*,
-    crate::{
-        pty::Pty,
-        screen::renderer::renderer,
-        term::{
-            Terminal,
-            terminal_task,
-        },
-    },
+    tokio::runtime::Builder as RuntimeBuilder,
+    crate::command::Command,
 };
 
-fn set_raw_terminal(fd: i32) -> io::Result<Termios> {
-    let old_tios = Termios::from_fd(fd)?;
-    let mut new_tios = old_tios.clone();
-    // cfmakeraw(&mut new_tios);
-    new_tios.c_lflag &= !(ECHO | ICANON | IEXTEN | ISIG);
-    new_tios.c_iflag &= !(BRKINT | ICRNL | INPCK | ISTRIP | IXON);
-    new_tios.c_cflag &= !(CSIZE | PARENB);
-    new_tios.c_cflag |= CS8;
-    new_tios.c_oflag = !(OPOST);
-
-    // new_tios.c_iflag = ICRNL|IXANY;
-    // new_tios.c_oflag = OPOST|ONLCR;
-    // new_tios.c_cflag = CREAD|CS8|HUPCL;
-    new_tios.c_cc[VMIN] = 1;
-    new_tios.c_cc[VTIME] = 0;
-    cfsetispeed(&mut new_tios, cfgetispeed(&old_tios))?;
-    cfsetospeed(&mut new_tios, cfgetospeed(&old_tios))?;
-    tcsetattr(fd, TCSANOW, &new_tios)?;
-    Ok(old_tios)
-}
-
-fn set_non_blocking(fd: RawFd) -> io::Result<()> {
-    unsafe {
-        let flags = libc::fcntl(fd, libc::F_GETFL, 0);
-        if flags < 0 {
-            return Err(io::Error::last_os_error());
-        }
-        let ret = libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
-        if ret == -1 {
-            return Err(io::Error::last_os_error());
+/// Get the path of the config file given with `-f`, and the command the
+/// client is run with.
+fn parse_args() -> Result<(Option<String>, Vec<String>)> {
+    let mut args = env::args().skip(1);
+    let mut config = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-f" => config = Some(args.next().ok_or_else(|| anyhow!("-f expects a path"))?),
+            arg if arg.starts_with('-') => return Err(anyhow!("unknown argument: {}", arg)),
+            _ => return Ok((config, Some(arg).into_iter().chain(args).collect())),
         }
-
-        Ok(())
     }
+    Ok((config, Vec::new()))
 }
 
-async fn async_main() -> Result<()> {
-    simple_logging::log_to_file("terman.log", log::LevelFilter::Trace)?;
+/// Get the path of the default config file, which is only loaded if it
+/// exists.
+fn default_config() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")),
+    }.map(|dir| dir.join("terman").join("terman.conf"))
+}
 
-    let mut stdin = Pty::new(std::io::stdin()).context("stdin is not a pty")?;
-    let old_tios = set_raw_terminal(stdin.as_raw_fd())?;
-    let current_size = stdin.get_size().context("get stdin size")?;
-    let slave_size = current_size / 2;
-    let start_point = slave_size / 2;
-    let command = Command::new("/bin/bash");
-    let terminal = Terminal::spawn(command, slave_size).context("create terminal")?;
-    let terminal = Arc::new(terminal);
-    trace!("starting loop");
-    let (mut renderer, notifier) = renderer(
-        Arc::clone(&terminal),
-        io::stdout(),
-        start_point,
-    );
-    let render_screen = {
-        let terminal = Arc::clone(&terminal);
-        task::spawn(async move {
-            trace!("starting render task");
-            let res = renderer.run_loop().await;
-            trace!("render task finished");
-            if let Err(_) = res {
-                terminal.kill()?;
-            }
-            res
-        })
-    };
-    let stdin_read = {
-        let terminal = Arc::clone(&terminal);
-        task::spawn(async move {
-            let mut buf = [0u8; 128];
-            while terminal.alive() {
-                trace!("reading from stdin");
-                let n = stdin.read(&mut buf[..]).await.context("stdin read")?;
-                trace!("read {} bytes from stdin", n);
-                trace!("writing terminal pty");
-                let res = terminal.pty_write(&buf[..n]).await;
-                terminal.pty_flush().await?;
-                trace!("wrote terminal pty");
-                match res {
-                    Ok(_) => {},
-                    Err(e) if e.kind() == io::ErrorKind::Interrupted => return Ok(()),
-                    Err(e) => return Err(e).context("pty write"),
-                }
-            };
-            trace!("stdin read task finished");
-            Ok(())
-        })
-    };
+async fn async_main() -> Result<i32> {
+    // Logging is only enabled when asked for, with the level as value.
+    if let Ok(level) = env::var("TERMAN_LOG") {
+        let level = level.parse().map_err(|_| anyhow!("TERMAN_LOG: unknown level: {}", level))?;
+        simple_logging::log_to_file("terman.log", level)?;
+    }
+    let (config, args) = parse_args()?;
+    if args.first().map(String::as_str) != Some("server") {
+        return client::run(config, args).await;
+    }
 
-    let exit_status = terminal_task(&*terminal, notifier).await;
-    drop(stdin_read);
-    drop(render_screen);
-    tcsetattr(aio::stdin().as_raw_fd(), TCSANOW, &old_tios)?;
-    print!("\x1b[2J\x1b[3J\x1b[H"); // Clear screen
-    println!("process exit: {}", exit_status?);
-    println!("there are {} references to terminal", Arc::strong_count(&terminal));
-    Ok(())
+    // The server runs in the foreground, clients start it in the
+    // background when it isn't running.
+    let source = match config {
+        Some(path) => Some(Command::SourceFile { path, quiet: false }),
+        None => default_config().map(|path| Command::SourceFile {
+            path: path.to_string_lossy().into_owned(),
+            quiet: true,
+        }),
+    };
+    server::run(&server::socket_path(), source).await?;
+    Ok(0)
 }
 
 fn main() -> Result<()> {
     let mut runtime = RuntimeBuilder::new()
         .threaded_scheduler()
-        .max_threads(4)
-        .core_threads(2)
         .enable_all()
         .build().context("create tokio runtime")?;
 
-    runtime.block_on(async_main())?;
+    let code = match runtime.block_on(async_main()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("terman: {:#}", e);
+            1
+        },
+    };
     runtime.shutdown_timeout(Duration::from_secs(0));
     trace!("runtime was shutdown");
 
-    Ok(())
+    process::exit(code)
 }
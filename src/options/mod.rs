@@ -0,0 +1,450 @@
+use {
+    std::{
+        collections::HashMap,
+        env,
+        fmt,
+    },
+    anyhow::{
+        anyhow,
+        Result,
+    },
+    crate::{
+        ansi::Color,
+        input::Key,
+    },
+};
+
+/// Type of the value of an option, which decides how values are parsed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OptionType {
+    String,
+    Number,
+    /// `on` or `off`.
+    Flag,
+    Key,
+    Color,
+    /// One of a list of words.
+    Choice(&'static [&'static str]),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Number(usize),
+    Flag(bool),
+    Key(Key),
+    Color(Color),
+}
+
+impl Value {
+    /// Parse the value of an option of type `kind`.
+    pub fn parse(kind: OptionType, value: &str) -> Result<Self> {
+        match kind {
+            OptionType::String => Ok(Self::String(value.to_owned())),
+            OptionType::Number => value.parse()
+                .map(Self::Number)
+                .map_err(|_| anyhow!("not a number: {}", value)),
+            OptionType::Flag => match value {
+                "on" | "yes" | "1" => Ok(Self::Flag(true)),
+                "off" | "no" | "0" => Ok(Self::Flag(false)),
+                _ => Err(anyhow!("not on or off: {}", value)),
+            },
+            OptionType::Key => value.parse()
+                .map(Self::Key)
+                .map_err(|_| anyhow!("unknown key: {}", value)),
+            OptionType::Choice(choices) => match choices.iter().find(|choice| **choice == value) {
+                Some(_) => Ok(Self::String(value.to_owned())),
+                None => Err(anyhow!("not one of {}: {}", choices.join(", "), value)),
+            },
+            // Any special color is drawn as the default color of the layer
+            // it is used for.
+            OptionType::Color => match value {
+                "default" => Ok(Self::Color(Color::Foreground)),
+                _ => value.parse()
+                    .map(Self::Color)
+                    .map_err(|_| anyhow!("unknown color: {}", value)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(value) => write!(f, "{}", value),
+            Self::Number(value) => write!(f, "{}", value),
+            Self::Flag(true) => write!(f, "on"),
+            Self::Flag(false) => write!(f, "off"),
+            Self::Key(value) => write!(f, "{}", value),
+            // Special colors are the default color of their layer.
+            Self::Color(Color::Special(_)) => write!(f, "default"),
+            Self::Color(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// What an option applies to, which decides where it can be set.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Scope {
+    Server,
+    Session,
+    Window,
+    Pane,
+}
+
+/// Level at which a value is set. Values set at a narrower level override
+/// the ones of the levels it inherits from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Level {
+    Global,
+    Session,
+    Window,
+    Pane,
+}
+
+impl Level {
+    /// Returns whether options of `scope` can be set at this level.
+    ///
+    /// Every option has a global value. Pane options can also be set for
+    /// all the panes of a window.
+    pub fn allows(self, scope: Scope) -> bool {
+        matches!(
+            (self, scope),
+            (Level::Global, _)
+                | (Level::Session, Scope::Session)
+                | (Level::Window, Scope::Window)
+                | (Level::Window, Scope::Pane)
+                | (Level::Pane, Scope::Pane)
+        )
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Global => write!(f, "global"),
+            Self::Session => write!(f, "session"),
+            Self::Window => write!(f, "window"),
+            Self::Pane => write!(f, "pane"),
+        }
+    }
+}
+
+/// Definition of an option.
+pub struct OptionDef {
+    pub name: &'static str,
+    pub kind: OptionType,
+    pub scope: Scope,
+    pub default: fn() -> Value,
+}
+
+pub const OPTIONS: &[OptionDef] = &[
+    OptionDef {
+        name: "bell-action",
+        kind: OptionType::Choice(&["none", "forward", "visual"]),
+        scope: Scope::Session,
+        default: || Value::String("forward".to_owned()),
+    },
+    OptionDef {
+        name: "bell-rate-limit",
+        kind: OptionType::Number,
+        scope: Scope::Session,
+        default: || Value::Number(500),
+    },
+    OptionDef {
+        name: "buffer-limit",
+        kind: OptionType::Number,
+        scope: Scope::Server,
+        default: || Value::Number(50),
+    },
+    OptionDef {
+        name: "default-shell",
+        kind: OptionType::String,
+        scope: Scope::Session,
+        default: || Value::String(env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_owned())),
+    },
+    OptionDef {
+        name: "escape-time",
+        kind: OptionType::Number,
+        scope: Scope::Server,
+        default: || Value::Number(10),
+    },
+    OptionDef {
+        name: "frame-rate",
+        kind: OptionType::Number,
+        scope: Scope::Server,
+        default: || Value::Number(60),
+    },
+    OptionDef {
+        name: "history-limit",
+        kind: OptionType::Number,
+        scope: Scope::Session,
+        default: || Value::Number(2000),
+    },
+    OptionDef {
+        name: "mode-keys",
+        kind: OptionType::Choice(&["emacs", "vi"]),
+        scope: Scope::Window,
+        default: || Value::String("emacs".to_owned()),
+    },
+    OptionDef {
+        name: "monitor-activity",
+        kind: OptionType::Flag,
+        scope: Scope::Window,
+        default: || Value::Flag(true),
+    },
+    OptionDef {
+        name: "mouse",
+        kind: OptionType::Flag,
+        scope: Scope::Session,
+        default: || Value::Flag(false),
+    },
+    OptionDef {
+        name: "prefix",
+        kind: OptionType::Key,
+        scope: Scope::Session,
+        default: || Value::Key(Key::ctrl('b')),
+    },
+    OptionDef {
+        name: "remain-on-exit",
+        kind: OptionType::Flag,
+        scope: Scope::Pane,
+        default: || Value::Flag(false),
+    },
+    OptionDef {
+        name: "repeat-time",
+        kind: OptionType::Number,
+        scope: Scope::Session,
+        default: || Value::Number(500),
+    },
+    OptionDef {
+        name: "status",
+        kind: OptionType::Flag,
+        scope: Scope::Session,
+        default: || Value::Flag(true),
+    },
+    OptionDef {
+        name: "status-bg",
+        kind: OptionType::Color,
+        scope: Scope::Session,
+        default: || Value::Color(Color::Background),
+    },
+    OptionDef {
+        name: "status-fg",
+        kind: OptionType::Color,
+        scope: Scope::Session,
+        default: || Value::Color(Color::Foreground),
+    },
+    OptionDef {
+        name: "synchronize-panes",
+        kind: OptionType::Flag,
+        scope: Scope::Window,
+        default: || Value::Flag(false),
+    },
+    OptionDef {
+        name: "window-size",
+        kind: OptionType::Choice(&["smallest", "largest", "latest", "manual"]),
+        scope: Scope::Session,
+        default: || Value::String("latest".to_owned()),
+    },
+    OptionDef {
+        name: "word-separators",
+        kind: OptionType::String,
+        scope: Scope::Session,
+        default: || Value::String("!\"#$%&'()*+,-./:;<=>?@[\\]^`{|}~".to_owned()),
+    },
+];
+
+/// Get the definition of option `name`.
+pub fn definition(name: &str) -> Result<&'static OptionDef> {
+    OPTIONS.iter()
+        .find(|def| def.name == name)
+        .ok_or_else(|| anyhow!("unknown option: {}", name))
+}
+
+/// Values of the options set at one level.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    values: HashMap<&'static str, Value>,
+}
+
+impl Options {
+    /// Get the value of option `name`, if it was set at this level.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    /// Set option `name` from its textual value.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        let def = definition(name)?;
+        let value = Value::parse(def.kind, value).map_err(|e| anyhow!("{}: {}", name, e))?;
+        self.values.insert(def.name, value);
+        Ok(())
+    }
+
+    /// Remove the value of option `name`, so that it is inherited again.
+    pub fn unset(&mut self, name: &str) -> Result<()> {
+        let def = definition(name)?;
+        self.values.remove(def.name);
+        Ok(())
+    }
+}
+
+/// Options in effect somewhere, made of the levels it inherits from.
+///
+/// Values are looked up from the narrowest level to the global one, and
+/// options that weren't set anywhere have their default value.
+#[derive(Clone)]
+pub struct ScopedOptions<'a> {
+    /// Levels from the narrowest to the global one.
+    levels: Vec<(Level, &'a Options)>,
+}
+
+impl<'a> ScopedOptions<'a> {
+    pub fn new(global: &'a Options) -> Self {
+        Self {
+            levels: vec![(Level::Global, global)],
+        }
+    }
+
+    /// Add a level that overrides the current ones.
+    pub fn with(mut self, level: Level, options: &'a Options) -> Self {
+        self.levels.insert(0, (level, options));
+        self
+    }
+
+    /// Get the value of option `name` and the level it was set at, or
+    /// `None` for the default value.
+    ///
+    /// Panics if there is no such option, names are checked when options
+    /// are set.
+    pub fn lookup(&self, name: &str) -> (Value, Option<Level>) {
+        let def = definition(name).unwrap();
+        self.levels.iter()
+            .find_map(|(level, options)| options.get(def.name).map(|value| (value.clone(), Some(*level))))
+            .unwrap_or_else(|| ((def.default)(), None))
+    }
+
+    pub fn get(&self, name: &str) -> Value {
+        self.lookup(name).0
+    }
+
+    /// Describe every option, with the level its value comes from.
+    pub fn show(&self) -> Vec<String> {
+        OPTIONS.iter()
+            .map(|def| self.show_option(def.name))
+            .collect()
+    }
+
+    /// Describe option `name`, with the level its value comes from.
+    pub fn show_option(&self, name: &str) -> String {
+        match self.lookup(name) {
+            (value, Some(level)) => format!("{} {} ({})", name, value, level),
+            (value, None) => format!("{} {} (default)", name, value),
+        }
+    }
+
+    pub fn string(&self, name: &str) -> String {
+        match self.get(name) {
+            Value::String(value) => value,
+            value => value.to_string(),
+        }
+    }
+
+    pub fn number(&self, name: &str) -> usize {
+        match self.get(name) {
+            Value::Number(value) => value,
+            value => panic!("option {} is not a number: {:?}", name, value),
+        }
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        match self.get(name) {
+            Value::Flag(value) => value,
+            value => panic!("option {} is not a flag: {:?}", name, value),
+        }
+    }
+
+    pub fn key(&self, name: &str) -> Key {
+        match self.get(name) {
+            Value::Key(value) => value,
+            value => panic!("option {} is not a key: {:?}", name, value),
+        }
+    }
+
+    pub fn color(&self, name: &str) -> Color {
+        match self.get(name) {
+            Value::Color(value) => value,
+            value => panic!("option {} is not a color: {:?}", name, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ansi::Color,
+        input::Key,
+    };
+    use super::{
+        Level,
+        Options,
+        Scope,
+        ScopedOptions,
+        Value,
+    };
+
+    #[test]
+    fn test_options() {
+        let mut global = Options::default();
+        assert_eq!(ScopedOptions::new(&global).number("history-limit"), 2000);
+        assert_eq!(ScopedOptions::new(&global).key("prefix"), Key::ctrl('b'));
+
+        global.set("history-limit", "50").unwrap();
+        global.set("status", "off").unwrap();
+        global.set("prefix", "C-a").unwrap();
+        global.set("status-bg", "blue").unwrap();
+        let options = ScopedOptions::new(&global);
+        assert_eq!(options.number("history-limit"), 50);
+        assert!(!options.flag("status"));
+        assert_eq!(options.key("prefix"), Key::ctrl('a'));
+        assert_eq!(options.color("status-bg"), Color::Blue);
+
+        assert_eq!(global.set("foo", "1").unwrap_err().to_string(), "unknown option: foo");
+        assert_eq!(global.set("status", "maybe").unwrap_err().to_string(), "status: not on or off: maybe");
+        assert_eq!(global.set("history-limit", "x").unwrap_err().to_string(), "history-limit: not a number: x");
+        assert_eq!(global.set("mode-keys", "ed").unwrap_err().to_string(), "mode-keys: not one of emacs, vi: ed");
+    }
+
+    #[test]
+    fn test_scoped_options() {
+        let mut global = Options::default();
+        let mut session = Options::default();
+        let mut window = Options::default();
+        let mut pane = Options::default();
+        global.set("history-limit", "100").unwrap();
+        session.set("history-limit", "200").unwrap();
+        global.set("synchronize-panes", "on").unwrap();
+        window.set("monitor-activity", "off").unwrap();
+        pane.set("monitor-activity", "on").unwrap();
+
+        let options = ScopedOptions::new(&global)
+            .with(Level::Session, &session)
+            .with(Level::Window, &window);
+        assert_eq!(options.lookup("history-limit"), (Value::Number(200), Some(Level::Session)));
+        assert_eq!(options.lookup("synchronize-panes"), (Value::Flag(true), Some(Level::Global)));
+        assert_eq!(options.lookup("monitor-activity"), (Value::Flag(false), Some(Level::Window)));
+        assert_eq!(options.lookup("status"), (Value::Flag(true), None));
+        assert_eq!(options.show_option("history-limit"), "history-limit 200 (session)");
+        assert_eq!(options.show_option("status-bg"), "status-bg default (default)");
+        assert!(options.clone().with(Level::Pane, &pane).flag("monitor-activity"));
+
+        session.unset("history-limit").unwrap();
+        let options = ScopedOptions::new(&global).with(Level::Session, &session);
+        assert_eq!(options.lookup("history-limit"), (Value::Number(100), Some(Level::Global)));
+
+        assert!(Level::Window.allows(Scope::Pane));
+        assert!(!Level::Pane.allows(Scope::Window));
+        assert!(!Level::Session.allows(Scope::Server));
+    }
+}
@@ -66,6 +66,25 @@ fn wrap_io_err(error: bool) -> io::Result<()> {
     }
 }
 
+/// Set the window size of the pty with file descriptor `fd`.
+///
+/// This doesn't need exclusive access to the pty, so it can be used while
+/// its reader and writer are in use.
+pub fn set_size(fd: RawFd, size: Point) -> Result<()> {
+    let winsize = libc::winsize {
+        ws_col: size.x as u16,
+        ws_row: size.y as u16,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    wrap_io_err(
+        unsafe {
+            libc::ioctl(fd, libc::TIOCSWINSZ, &winsize) == -1
+        }
+    ).context("set pty size")?;
+    Ok(())
+}
+
 pub struct PtyFile {
     file: File,
 }
@@ -162,7 +181,6 @@ impl Pty {
             } else {
                 libc::O_RDWR | libc::O_NOCTTY | libc::O_NONBLOCK
             };
-            let flags = libc::O_RDWR | libc::O_NOCTTY;
             let master_fd = libc::posix_openpt(flags);
             wrap_io_err(master_fd < 0).context("open pty master")?;
             wrap_io_err(libc::grantpt(master_fd) != 0).context("grantpt")?;
@@ -208,21 +226,28 @@ impl Pty {
     }
 
     pub fn set_size(&mut self, size: Point) -> Result<()> {
-        let winsize = libc::winsize {
-            ws_col: size.x as u16,
-            ws_row: size.y as u16,
+        set_size(self.as_raw_fd(), size)
+    }
+
+    pub fn get_size(&self) -> Result<Point> {
+        let mut winsize = libc::winsize {
+            ws_col: 0,
+            ws_row: 0,
             ws_xpixel: 0,
             ws_ypixel: 0,
         };
+
         wrap_io_err(
             unsafe {
-                libc::ioctl(self.as_raw_fd(), libc::TIOCSWINSZ, &winsize) == -1
+                libc::ioctl(self.as_raw_fd(), libc::TIOCGWINSZ, &mut winsize) == -1
             }
-        ).context("set pty size")?;
-        Ok(())
+        ).context("get pty size")?;
+        Ok(Point::new(winsize.ws_col as usize, winsize.ws_row as usize))
     }
 
-    pub fn get_size(&self) -> Result<Point> {
+    /// Get the size of a single cell in pixels, or zero if the pty doesn't
+    /// report its pixel dimensions.
+    pub fn get_cell_size(&self) -> Result<Point> {
         let mut winsize = libc::winsize {
             ws_col: 0,
             ws_row: 0,
@@ -235,7 +260,13 @@ impl Pty {
                 libc::ioctl(self.as_raw_fd(), libc::TIOCGWINSZ, &mut winsize) == -1
             }
         ).context("get pty size")?;
-        Ok(Point::new(winsize.ws_col as usize, winsize.ws_row as usize))
+        if winsize.ws_col == 0 || winsize.ws_row == 0 {
+            return Ok(Point::default());
+        }
+        Ok(Point::new(
+            (winsize.ws_xpixel / winsize.ws_col) as usize,
+            (winsize.ws_ypixel / winsize.ws_row) as usize,
+        ))
     }
 
     pub fn set_mode(&mut self, mode: &Termios) -> Result<()> {
@@ -299,11 +330,16 @@ impl WithPty for Command {
     fn with_pty(&mut self, master: &Pty) -> Result<&mut Self> {
         let master_fd = master.as_raw_fd();
         let slave_fd = master.open_slave().context("pty open slave")?;
+        // Each Stdio owns and closes its fd, so they can't share one.
+        let stdout_fd = unsafe { libc::dup(slave_fd) };
+        wrap_io_err(stdout_fd < 0).context("dup slave fd")?;
+        let stderr_fd = unsafe { libc::dup(slave_fd) };
+        wrap_io_err(stderr_fd < 0).context("dup slave fd")?;
         unsafe {
             self
                 .stdin(Stdio::from_raw_fd(slave_fd))
-                .stdout(Stdio::from_raw_fd(slave_fd))
-                .stderr(Stdio::from_raw_fd(slave_fd));
+                .stdout(Stdio::from_raw_fd(stdout_fd))
+                .stderr(Stdio::from_raw_fd(stderr_fd));
 
             self.pre_exec(move || {
                 wrap_io_err(libc::close(master_fd) != 0)?;
@@ -0,0 +1,103 @@
+use std::{
+    fmt,
+    str,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// What to do when a pane rings the bell, from the `bell-action` option.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BellAction {
+    /// Ignore the bell, only the bell flag is set.
+    None,
+    /// Forward the bell to the host terminal.
+    #[default]
+    Forward,
+    /// Briefly flash the pane.
+    Visual,
+}
+
+impl str::FromStr for BellAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "forward" => Ok(Self::Forward),
+            "visual" => Ok(Self::Visual),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for BellAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Forward => write!(f, "forward"),
+            Self::Visual => write!(f, "visual"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BellConfig {
+    /// How long a visual bell keeps the pane flashed.
+    pub flash_duration: Duration,
+}
+
+impl Default for BellConfig {
+    fn default() -> Self {
+        Self {
+            flash_duration: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Rate limiter for bell actions.
+#[derive(Clone, Debug, Default)]
+pub struct BellLimiter {
+    last: Option<Instant>,
+}
+
+impl BellLimiter {
+    /// Returns whether a bell ringing at `now` should trigger its action.
+    pub fn allow(&mut self, now: Instant, rate_limit: Duration) -> bool {
+        match self.last {
+            Some(last) if now.duration_since(last) < rate_limit => false,
+            _ => {
+                self.last = Some(now);
+                true
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use super::{BellAction, BellLimiter};
+
+    #[test]
+    fn test_bell_action() {
+        for action in &[BellAction::None, BellAction::Forward, BellAction::Visual] {
+            assert_eq!(action.to_string().parse(), Ok(*action));
+        }
+        assert_eq!("forward".parse(), Ok(BellAction::Forward));
+        assert_eq!("any".parse::<BellAction>(), Err(()));
+    }
+
+    #[test]
+    fn test_bell_limiter() {
+        let mut limiter = BellLimiter::default();
+        let start = Instant::now();
+        let limit = Duration::from_millis(500);
+        assert!(limiter.allow(start, limit));
+        assert!(!limiter.allow(start + Duration::from_millis(100), limit));
+        assert!(!limiter.allow(start + Duration::from_millis(499), limit));
+        assert!(limiter.allow(start + Duration::from_millis(500), limit));
+        assert!(!limiter.allow(start + Duration::from_millis(700), limit));
+    }
+}
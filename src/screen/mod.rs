@@ -1,7 +1,12 @@
+pub mod bell;
 pub mod renderer;
 
 use {
-    std::cmp::min,
+    std::{
+        cmp::{max, min},
+        collections::HashSet,
+        ops::Range,
+    },
     log::trace,
     crate::{
         ansi::{
@@ -25,18 +30,43 @@ use {
         grid::{
             Grid,
             Cell,
+            Line,
+            LineSize,
         },
         util::Point,
     }
 };
 
+/// Cursor state saved by DECSC and restored by DECRC.
+#[derive(Clone, Debug, Default)]
+struct SavedCursor {
+    position: Point,
+    cell_template: Attributes,
+    charsets: CharsetList,
+    origin: bool,
+}
+
 pub struct Screen {
     grid: Grid,
+    alt_grid: Grid,
     size: Point,
     cursor: Point,
+    saved_cursor: SavedCursor,
+    cursor_style: CursorStyle,
     cell_template: Attributes,
     charsets: CharsetList,
-    active_charset: CharsetIndex,
+    modes: HashSet<TerminalMode>,
+    keypad_application: bool,
+    scroll_region: Range<usize>,
+    tabstops: Vec<bool>,
+    palette: Vec<RgbColor>,
+    special_colors: [Option<RgbColor>; 3],
+    title: String,
+    title_stack: Vec<String>,
+    cell_size: Point,
+    bell: bool,
+    /// Maximum number of lines kept when they scroll off the primary grid.
+    history_limit: usize,
 }
 
 impl Screen {
@@ -44,33 +74,195 @@ impl Screen {
         Self {
             size,
             grid: Grid::new(size),
+            alt_grid: Grid::new(size),
             cursor: Point::default(),
+            saved_cursor: SavedCursor::default(),
+            cursor_style: CursorStyle::default(),
             cell_template: Attributes::default(),
             charsets: CharsetList::default(),
-            active_charset: CharsetIndex::default(),
+            modes: Self::default_modes(),
+            keypad_application: false,
+            scroll_region: 0..size.y,
+            tabstops: Self::default_tabstops(size.x),
+            palette: (0..=255).map(RgbColor::from_index).collect(),
+            special_colors: [None; 3],
+            title: String::new(),
+            title_stack: Vec::new(),
+            cell_size: Point::default(),
+            bell: false,
+            history_limit: 0,
+        }
+    }
+
+    /// Modes that are set when the screen is created.
+    fn default_modes() -> HashSet<TerminalMode> {
+        [TerminalMode::LineWrap, TerminalMode::ShowCursor].iter().cloned().collect()
+    }
+
+    /// Tab stops that are set when the screen is created, one every 8 columns.
+    fn default_tabstops(columns: usize) -> Vec<bool> {
+        (0..columns).map(|x| x % 8 == 0 && x != 0).collect()
+    }
+
+    /// Returns whether the program asked for mouse reports.
+    pub fn mouse_reporting(&self) -> bool {
+        self.modes.contains(&TerminalMode::ReportMouseClicks)
+            || self.modes.contains(&TerminalMode::ReportCellMouseMotion)
+            || self.modes.contains(&TerminalMode::ReportAllMouseMotion)
+    }
+
+    /// Soft terminal reset (DECSTR).
+    ///
+    /// Only resets the state listed by the VT220 specification, leaving
+    /// the screen contents and cursor position untouched.
+    pub fn soft_reset(&mut self) {
+        trace!("[screen] soft_reset");
+        self.modes.insert(TerminalMode::ShowCursor);
+        self.modes.remove(&TerminalMode::Insert);
+        self.modes.remove(&TerminalMode::Origin);
+        self.modes.remove(&TerminalMode::LineWrap);
+        self.modes.remove(&TerminalMode::CursorKeys);
+        self.keypad_application = false;
+        self.scroll_region = 0..self.size.y;
+        self.charsets = CharsetList::default();
+        self.cell_template = Attributes::default();
+        self.saved_cursor = SavedCursor::default();
+    }
+
+    /// Hard terminal reset (RIS).
+    ///
+    /// Restores the screen to the state it had when it was created. Only
+    /// the properties of the host, like the cell size, are kept.
+    pub fn hard_reset(&mut self) {
+        trace!("[screen] hard_reset");
+        let cell_size = self.cell_size;
+        let history_limit = self.history_limit;
+        *self = Self::new(self.size);
+        self.cell_size = cell_size;
+        self.set_history_limit(history_limit);
+    }
+
+    /// Resize the screen.
+    ///
+    /// When the screen gets shorter, lines are removed from the top so that
+    /// the cursor stays on the same line of text.
+    pub fn resize(&mut self, size: Point) {
+        trace!("[screen] resize: size={:?}", size);
+        if size == self.size || size.x == 0 || size.y == 0 {
+            return;
+        }
+        if self.cursor.y >= size.y {
+            self.grid.scroll_up(self.cursor.y + 1 - size.y);
+            self.cursor.y = size.y - 1;
+        }
+        self.grid.resize(size);
+        self.alt_grid.resize(size);
+        self.cursor.x = min(self.cursor.x, size.x - 1);
+        // New columns get the default tab stops.
+        self.tabstops.truncate(size.x);
+        let kept = self.tabstops.len();
+        self.tabstops.extend_from_slice(&Self::default_tabstops(size.x)[kept..]);
+        self.scroll_region = 0..size.y;
+        self.size = size;
+    }
+
+    /// Set the size of a single cell in pixels, as reported by the host.
+    pub fn set_cell_size(&mut self, cell_size: Point) {
+        self.cell_size = cell_size;
+    }
+
+    /// Set the maximum number of lines kept when they scroll off the top of
+    /// the primary grid. The alternate grid keeps no history.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = limit;
+        if self.modes.contains(&TerminalMode::SwapScreenAndSetRestoreCursor) {
+            self.alt_grid.set_history_limit(limit);
+        } else {
+            self.grid.set_history_limit(limit);
+        }
+    }
+
+    /// Returns whether the bell rang since the last call, and clears it.
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::replace(&mut self.bell, false)
+    }
+
+    /// Get the first line that the cursor can be moved to.
+    fn origin_line(&self) -> usize {
+        if self.modes.contains(&TerminalMode::Origin) {
+            self.scroll_region.start
+        } else {
+            0
         }
     }
 
+    /// Get the last column of line `y`. Double width lines only have half
+    /// as many columns.
+    fn right_margin(&self, y: usize) -> usize {
+        let double_width = self.grid.line(y).is_some_and(|line| line.size.is_double_width());
+        if double_width {
+            max(self.size.x / 2, 1) - 1
+        } else {
+            self.size.x - 1
+        }
+    }
+
+    /// Move the cursor back within the right margin of its line.
+    fn clamp_cursor(&mut self) {
+        self.cursor.x = min(self.cursor.x, self.right_margin(self.cursor.y));
+    }
+
     pub fn cursor_next(&mut self) {
         self.cursor.x += 1;
-        if self.cursor.x >= self.size.x {
+        if self.cursor.x > self.right_margin(self.cursor.y) {
+            if let Some(line) = self.grid.line_mut(self.cursor.y) {
+                line.wrapped = true;
+            }
             self.cursor.x = 0;
+            self.index();
+        }
+    }
+
+    /// Move the cursor down a line, scrolling the scroll region when the
+    /// cursor is on its last line.
+    fn index(&mut self) {
+        if self.cursor.y + 1 == self.scroll_region.end {
+            self.grid.scroll_region_up(self.scroll_region.clone(), 1);
+        } else if self.cursor.y + 1 < self.size.y {
             self.cursor.y += 1;
         }
+        self.clamp_cursor();
     }
 
+    /// Move the cursor back a column. Like a VT100, it stops at the first
+    /// column instead of wrapping to the end of the previous line.
     pub fn cursor_prev(&mut self) {
-        if self.cursor.x == 0 {
-            self.cursor.x = self.size.x - 1;
-            self.cursor.y -= 1;
-        } else {
-            self.cursor.x -= 1;
-        }
+        self.cursor.x = self.cursor.x.saturating_sub(1);
+    }
+
+    /// Get the grid that is shown, which is the alternate one while the
+    /// alternate screen is active.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Mark every line as modified, to draw the screen again.
+    pub fn damage_all(&mut self) {
+        self.grid.damage_all();
     }
 
     pub fn cell<'a>(&'a self, point: Point) -> Option<&'a Cell> {
         self.grid.cell(point)
     }
+
+    pub fn line(&self, y: usize) -> Option<&Line> {
+        self.grid.line(y)
+    }
+
+    /// Returns whether line `y` changed after the frame `frame` started.
+    pub fn is_damaged_since(&self, y: usize, frame: u64) -> bool {
+        self.grid.is_damaged_since(y, frame)
+    }
 }
 
 impl Handler for Screen {
@@ -82,9 +274,21 @@ impl Handler for Screen {
         &self.cursor
     }
 
+    fn cell_size(&self) -> &Point {
+        &self.cell_size
+    }
+
+    fn mode(&self, mode: TerminalMode) -> bool {
+        self.modes.contains(&mode)
+    }
+
+    fn scrolling_region(&self) -> &Range<usize> {
+        &self.scroll_region
+    }
+
     fn put_char(&mut self, ch: char) {
         trace!("[handler] put_char: char={:?}", ch);
-        let ch = self.charsets[self.active_charset].map(ch);
+        let ch = self.charsets.map(ch);
         let attributes = self.cell_template.clone();
         self.grid.cell_mut(self.cursor).map(|c| {
             c.ch = Some(ch);
@@ -103,12 +307,13 @@ impl Handler for Screen {
 
     fn put_tab(&mut self, count: usize) {
         trace!("[handler] put_tab: count={:?}", count);
+        self.move_forward_tabs(count);
     }
 
     fn put_lf(&mut self) {
         trace!("[handler] put_lf");
         self.cursor.x = 0;
-        self.cursor.y += 1;
+        self.index();
     }
 
     fn put_cr(&mut self) {
@@ -146,15 +351,42 @@ impl Handler for Screen {
 
     fn bell(&mut self) {
         trace!("[handler] bell");
+        self.bell = true;
     }
 
     fn do_alignment_test(&mut self) {
         trace!("[handler] do_alignment_test");
+        for y in 0..self.size.y {
+            let line = self.grid.line_mut(y).unwrap();
+            line.size = LineSize::Normal;
+            for cell in line.iter_mut() {
+                *cell = Cell {
+                    ch: Some('E'),
+                    ..Cell::default()
+                };
+            }
+        }
+        self.scroll_region = 0..self.size.y;
+        self.modes.remove(&TerminalMode::Origin);
+        self.cursor = Point::default();
+    }
+
+    fn set_line_size(&mut self, size: LineSize) {
+        trace!("[handler] set_line_size: size={:?}", size);
+        if let Some(line) = self.grid.line_mut(self.cursor.y) {
+            line.size = size;
+        }
+        self.clamp_cursor();
     }
 
     fn set_active_charset(&mut self, index: CharsetIndex) {
         trace!("[handler] set_active_charset: index={:?}", index);
-        self.active_charset = index;
+        self.charsets.set_active(index);
+    }
+
+    fn set_single_shift(&mut self, index: CharsetIndex) {
+        trace!("[handler] set_single_shift: index={:?}", index);
+        self.charsets.set_single_shift(index);
     }
 
     fn configure_charset(&mut self, index: CharsetIndex, charset: StandardCharset) {
@@ -164,14 +396,19 @@ impl Handler for Screen {
 
     fn set_title(&mut self, title: &str) {
         trace!("[handler] set_title: title={:?}", title);
+        self.title = title.to_owned();
     }
 
     fn save_title(&mut self) {
         trace!("[handler] save_title");
+        self.title_stack.push(self.title.clone());
     }
 
     fn restore_title(&mut self) {
         trace!("[handler] restore_title");
+        if let Some(title) = self.title_stack.pop() {
+            self.title = title;
+        }
     }
 
     fn set_path(&mut self, path: &str) {
@@ -192,35 +429,76 @@ impl Handler for Screen {
     }
 
     fn save_cursor_position(&mut self) {
-        trace!("[handler] save_cursor_position")
+        trace!("[handler] save_cursor_position");
+        self.saved_cursor = SavedCursor {
+            position: self.cursor,
+            cell_template: self.cell_template.clone(),
+            charsets: self.charsets,
+            origin: self.modes.contains(&TerminalMode::Origin),
+        };
     }
 
     fn restore_cursor_position(&mut self) {
-        trace!("[handler] restore_cursor_position")
+        trace!("[handler] restore_cursor_position");
+        let saved = self.saved_cursor.clone();
+        self.cursor = Point::new(
+            saved.position.x,
+            min(saved.position.y, self.size.y - 1),
+        );
+        self.clamp_cursor();
+        self.cell_template = saved.cell_template;
+        self.charsets = saved.charsets;
+        if saved.origin {
+            self.modes.insert(TerminalMode::Origin);
+        } else {
+            self.modes.remove(&TerminalMode::Origin);
+        }
     }
 
     fn set_cursor_style(&mut self, style: CursorStyle) {
         trace!("[handler] set_cursor_style: style={:?}", style);
+        self.cursor_style = style;
     }
 
     fn set_horizontal_tabstop(&mut self, column: usize) {
         trace!("[handler] set_horizontal_tabstop: column={:?}", column);
+        if let Some(tabstop) = self.tabstops.get_mut(column) {
+            *tabstop = true;
+        }
     }
 
     fn unset_horizontal_tabstop(&mut self, column: usize) {
         trace!("[handler] unset_horizontal_tabstop: column={:?}", column);
+        if let Some(tabstop) = self.tabstops.get_mut(column) {
+            *tabstop = false;
+        }
     }
 
     fn unset_all_horizontal_tabstops(&mut self) {
         trace!("[handler] unset_all_horizontal_tabstops");
+        self.tabstops.iter_mut().for_each(|t| *t = false);
     }
 
     fn move_forward_tabs(&mut self, count: usize) {
         trace!("[handler] move_forward_tabs: count={:?}", count);
+        let right_margin = self.right_margin(self.cursor.y);
+        for _ in 0..count {
+            let next = (self.cursor.x + 1..=right_margin)
+                .find(|x| self.tabstops[*x])
+                .unwrap_or(right_margin);
+            self.cursor.x = next;
+        }
     }
 
     fn move_backward_tabs(&mut self, count: usize) {
         trace!("[handler] move_backward_tabs: count={:?}", count);
+        for _ in 0..count {
+            let prev = (0..self.cursor.x)
+                .rev()
+                .find(|x| self.tabstops[*x])
+                .unwrap_or(0);
+            self.cursor.x = prev;
+        }
     }
 
     fn move_up(&mut self, count: usize) {
@@ -230,6 +508,7 @@ impl Handler for Screen {
         } else {
             self.cursor.y -= count;
         }
+        self.clamp_cursor();
     }
 
     fn move_down(&mut self, count: usize) {
@@ -238,13 +517,14 @@ impl Handler for Screen {
             self.cursor.y + count,
             self.size.y - 1,
         );
+        self.clamp_cursor();
     }
 
     fn move_forward(&mut self, count: usize) {
         trace!("[handler] move_forward: count={:?}", count);
         self.cursor.x = min(
             self.cursor.x + count,
-            self.size.x - 1,
+            self.right_margin(self.cursor.y),
         );
     }
 
@@ -259,76 +539,121 @@ impl Handler for Screen {
 
     fn goto_column(&mut self, column: usize) {
         trace!("[handler] goto_column: column={:?}", column);
-        self.cursor.x = column
+        self.cursor.x = column;
+        self.clamp_cursor();
     }
 
     fn goto_line(&mut self, line: usize) {
         trace!("[handler] goto_line: line={:?}", line);
-        self.cursor.y = line
+        self.cursor.y = min(line + self.origin_line(), self.size.y - 1);
+        self.clamp_cursor();
     }
 
     fn goto(&mut self, line: usize, column: usize) {
         trace!("[handler] goto: line={:?}, column={:?}", line, column);
-        self.cursor.x = column;
-        self.cursor.y = line;
+        self.cursor.y = min(line + self.origin_line(), self.size.y - 1);
+        self.cursor.x = min(column, self.right_margin(self.cursor.y));
     }
 
     fn scroll_up(&mut self, count: usize) {
         trace!("[handler] scroll_up: count={:?}", count);
+        self.grid.scroll_region_up(self.scroll_region.clone(), count);
     }
 
     fn scroll_down(&mut self, count: usize) {
         trace!("[handler] scroll_down: count={:?}", count);
+        self.grid.scroll_region_down(self.scroll_region.clone(), count);
     }
 
     fn set_scrolling_region(&mut self, top: usize, bottom: usize) {
         trace!("[handler] set_scrolling_region: top={:?}, bottom={:?}", top, bottom);
+        // `top` and `bottom` are 1-based and inclusive.
+        let bottom = min(bottom, self.size.y);
+        if top == 0 || top >= bottom {
+            return;
+        }
+        self.scroll_region = (top - 1)..bottom;
+        self.goto(0, 0);
     }
 
     fn set_mode(&mut self, mode: TerminalMode) {
         trace!("[handler] set_mode: mode={:?}", mode);
+        if !self.modes.insert(mode) {
+            return;
+        }
+        match mode {
+            TerminalMode::Origin => self.goto(0, 0),
+            TerminalMode::SwapScreenAndSetRestoreCursor => {
+                self.save_cursor_position();
+                std::mem::swap(&mut self.grid, &mut self.alt_grid);
+                self.grid = Grid::new(self.size);
+                self.grid.damage_all();
+            },
+            _ => {},
+        }
     }
 
     fn unset_mode(&mut self, mode: TerminalMode) {
         trace!("[handler] unset_mode: mode={:?}", mode);
+        if !self.modes.remove(&mode) {
+            return;
+        }
+        match mode {
+            TerminalMode::Origin => self.goto(0, 0),
+            TerminalMode::SwapScreenAndSetRestoreCursor => {
+                std::mem::swap(&mut self.grid, &mut self.alt_grid);
+                self.grid.damage_all();
+                self.restore_cursor_position();
+            },
+            _ => {},
+        }
     }
 
     fn set_keypad_application_mode(&mut self) {
         trace!("[handler] set_keypad_application_mode");
+        self.keypad_application = true;
     }
 
     fn unset_keypad_application_mode(&mut self) {
         trace!("[handler] unset_keypad_application_mode");
+        self.keypad_application = false;
     }
 
     fn get_color(&mut self, index: u8) -> Option<&RgbColor> {
         trace!("[handler] get_color: index={:?}", index);
-        None
+        self.palette.get(index as usize)
     }
 
     fn set_color(&mut self, index: u8, color: RgbColor) {
         trace!("[handler] set_color: index={:?}, color={:?}", index, color);
+        self.palette[index as usize] = color;
     }
 
     fn reset_color(&mut self, index: u8) {
         trace!("[handler] reset_color: index={:?}", index);
+        self.palette[index as usize] = RgbColor::from_index(index);
     }
 
     fn reset_all_colors(&mut self) {
         trace!("[handler] reset_all_colors");
+        for index in 0..=255 {
+            self.reset_color(index);
+        }
     }
 
     fn get_special_color(&mut self, index: SpecialColor) -> Option<&RgbColor> {
         trace!("[handler] get_special_color: index={:?}", index);
-        None
+        self.special_colors[index as usize].as_ref()
     }
 
     fn set_special_color(&mut self, index: SpecialColor, color: RgbColor) {
         trace!("[handler] set_special_color: index={:?}, color={:?}", index, color);
+        self.special_colors[index as usize] = Some(color);
     }
 
     fn reset_special_color(&mut self, index: SpecialColor) {
         trace!("[handler] reset_special_color: index={:?}", index);
+        self.special_colors[index as usize] = None;
     }
 
     fn sgr_attribute(&mut self, attr: Attribute) {
@@ -401,9 +726,213 @@ impl Handler for Screen {
 
     fn reverse_index(&mut self) {
         trace!("[handler] reverse_index");
+        if self.cursor.y == self.scroll_region.start {
+            self.grid.scroll_region_down(self.scroll_region.clone(), 1);
+        } else if self.cursor.y > 0 {
+            self.cursor.y -= 1;
+        }
+        self.clamp_cursor();
     }
 
     fn reset_state(&mut self) {
         trace!("[handler] reset_state");
+        self.hard_reset();
+    }
+
+    fn soft_reset_state(&mut self) {
+        trace!("[handler] soft_reset_state");
+        self.soft_reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ansi::{
+            Attributes,
+            CharsetList,
+            CursorStyle,
+            Handler,
+            Processor,
+            RgbColor,
+            TerminalMode,
+        },
+        grid::LineSize,
+        util::point::P,
+    };
+    use super::Screen;
+
+    fn advance(screen: &mut Screen, input: &[u8]) {
+        let mut processor = Processor::default();
+        processor.advance(input, screen, &mut Vec::new());
+    }
+
+    fn dirty_screen() -> Screen {
+        let mut screen = Screen::new(P(20, 10));
+        advance(
+            &mut screen,
+            concat!(
+                "hello",
+                "\x1b]0;title\x07\x1b[22t\x1b]0;other\x07",
+                "\x1b]4;1;#ff8800\x07",
+                "\x1b[3g",
+                "\x1b)0\x0e",
+                "\x1b[2;5r",
+                "\x1b[?6h\x1b[4h\x1b[?1h\x1b[?7l\x1b[?25l\x1b=",
+                "\x1b[1;31m",
+                "\x1b[2;3H\x1b7",
+                "\x1b[4 q",
+            ).as_bytes(),
+        );
+        screen
+    }
+
+    #[test]
+    fn test_hard_reset() {
+        let mut screen = dirty_screen();
+        advance(&mut screen, b"\x1bc");
+
+        assert_eq!(screen.cell(P(0, 0)).unwrap().ch, None);
+        assert_eq!(*screen.cursor(), P(0, 0));
+        assert_eq!(screen.title, "");
+        advance(&mut screen, b"\x1b[23t");
+        assert_eq!(screen.title, "");
+        assert_eq!(screen.get_color(1), Some(&RgbColor::from_index(1)));
+        assert_eq!(screen.tabstops, Screen::default_tabstops(20));
+        assert_eq!(screen.charsets, CharsetList::default());
+        assert_eq!(*screen.scrolling_region(), 0..10);
+        assert_eq!(screen.modes, Screen::default_modes());
+        assert!(!screen.keypad_application);
+        assert_eq!(screen.cell_template, Attributes::default());
+        assert_eq!(screen.cursor_style, CursorStyle::default());
+    }
+
+    #[test]
+    fn test_hard_reset_alternate_screen() {
+        let mut screen = Screen::new(P(20, 10));
+        advance(&mut screen, b"primary\x1b[?1049halternate\x1bc");
+        assert!(!screen.mode(TerminalMode::SwapScreenAndSetRestoreCursor));
+        assert_eq!(screen.cell(P(0, 0)).unwrap().ch, None);
+        assert_eq!(screen.alt_grid.cell(P(0, 0)).unwrap().ch, None);
+    }
+
+    #[test]
+    fn test_alignment_test() {
+        let mut screen = Screen::new(P(4, 3));
+        advance(&mut screen, b"\x1b[2;3r\x1b[?6h\x1b#6\x1b[2;2H\x1b#8");
+        for y in 0..3 {
+            assert_eq!(screen.line(y).unwrap().size, LineSize::Normal);
+            for x in 0..4 {
+                assert_eq!(screen.cell(P(x, y)).unwrap().ch, Some('E'));
+            }
+        }
+        assert_eq!(*screen.scrolling_region(), 0..3);
+        assert!(!screen.mode(TerminalMode::Origin));
+        assert_eq!(*screen.cursor(), P(0, 0));
+    }
+
+    #[test]
+    fn test_line_size() {
+        let mut screen = Screen::new(P(4, 4));
+        advance(&mut screen, b"\x1b#3\x1b[2H\x1b#4\x1b[3H\x1b#6\x1b#5\x1b[4H\x1b#6");
+        assert_eq!(screen.line(0).unwrap().size, LineSize::DoubleHeightTop);
+        assert_eq!(screen.line(1).unwrap().size, LineSize::DoubleHeightBottom);
+        assert_eq!(screen.line(2).unwrap().size, LineSize::Normal);
+        assert_eq!(screen.line(3).unwrap().size, LineSize::DoubleWidth);
+    }
+
+    #[test]
+    fn test_backspace() {
+        let mut screen = Screen::new(P(10, 4));
+        advance(&mut screen, b"\x08");
+        assert_eq!(*screen.cursor(), P(0, 0));
+        advance(&mut screen, b"ab\r\ncd\x08\x08\x08");
+        assert_eq!(*screen.cursor(), P(0, 1));
+    }
+
+    #[test]
+    fn test_double_width_cursor() {
+        let mut screen = Screen::new(P(10, 4));
+        advance(&mut screen, b"\x1b[1;8H\x1b#6");
+        assert_eq!(*screen.cursor(), P(4, 0));
+        advance(&mut screen, b"\x1b[9G");
+        assert_eq!(*screen.cursor(), P(4, 0));
+        advance(&mut screen, b"\x1b[2;9H\x1b[A");
+        assert_eq!(*screen.cursor(), P(4, 0));
+
+        advance(&mut screen, b"\x1b[Habcdefg");
+        assert_eq!(screen.line(0).unwrap()[4].ch, Some('e'));
+        assert_eq!(screen.line(1).unwrap()[0].ch, Some('f'));
+        assert_eq!(*screen.cursor(), P(2, 1));
+    }
+
+    #[test]
+    fn test_soft_reset() {
+        let mut screen = dirty_screen();
+        advance(&mut screen, b"\x1b[!p");
+
+        // Reset by DECSTR.
+        assert!(screen.mode(TerminalMode::ShowCursor));
+        assert!(!screen.mode(TerminalMode::Insert));
+        assert!(!screen.mode(TerminalMode::Origin));
+        assert!(!screen.mode(TerminalMode::LineWrap));
+        assert!(!screen.mode(TerminalMode::CursorKeys));
+        assert!(!screen.keypad_application);
+        assert_eq!(*screen.scrolling_region(), 0..10);
+        assert_eq!(screen.charsets, CharsetList::default());
+        assert_eq!(screen.cell_template, Attributes::default());
+        advance(&mut screen, b"\x1b8");
+        assert_eq!(*screen.cursor(), P(0, 0));
+
+        // Left untouched by DECSTR.
+        assert_eq!(screen.cell(P(0, 0)).unwrap().ch, Some('h'));
+        assert_eq!(screen.title, "other");
+        assert_eq!(screen.get_color(1), Some(&RgbColor { r: 0xff, g: 0x88, b: 0x00 }));
+        assert!(screen.tabstops.iter().all(|t| !t));
+        assert_eq!(screen.cursor_style, CursorStyle::StaticUnderline);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut screen = Screen::new(P(20, 10));
+        advance(&mut screen, b"top\x1b[8;5Hcursor\x1b[3;5r");
+        advance(&mut screen, b"\x1b[8;11H");
+
+        screen.resize(P(10, 5));
+        assert_eq!(*screen.size(), P(10, 5));
+        assert_eq!(*screen.cursor(), P(9, 4));
+        assert_eq!(screen.cell(P(4, 4)).unwrap().ch, Some('c'));
+        assert_eq!(*screen.scrolling_region(), 0..5);
+        assert_eq!(screen.tabstops, vec![false, false, false, false, false, false, false, false, true, false]);
+
+        screen.resize(P(20, 10));
+        assert_eq!(screen.line(9).unwrap().len(), 20);
+        assert!(screen.tabstops[16]);
+    }
+
+    #[test]
+    fn test_scrolling() {
+        let mut screen = Screen::new(P(4, 3));
+        screen.set_history_limit(10);
+        let text = |screen: &Screen, y| screen.cell(P(0, y)).unwrap().ch;
+        advance(&mut screen, b"a\nb\nc\nd");
+        assert_eq!(*screen.cursor(), P(1, 2));
+        assert_eq!([text(&screen, 0), text(&screen, 1), text(&screen, 2)], [Some('b'), Some('c'), Some('d')]);
+        assert_eq!(screen.grid.history().len(), 1);
+
+        // Wrapping past the last column scrolls too.
+        advance(&mut screen, b"eeee");
+        assert_eq!(*screen.cursor(), P(1, 2));
+        assert_eq!(text(&screen, 0), Some('c'));
+
+        // Only the scroll region scrolls, without keeping the lines.
+        advance(&mut screen, b"\x1b[1;2r\x1b[2;1H\n");
+        assert_eq!([text(&screen, 0), text(&screen, 1), text(&screen, 2)], [Some('d'), None, Some('e')]);
+        assert_eq!(screen.grid.history().len(), 3);
+
+        advance(&mut screen, b"\x1b[1;1H\x1bM");
+        assert_eq!([text(&screen, 0), text(&screen, 1)], [None, Some('d')]);
+        advance(&mut screen, b"\x1b[S");
+        assert_eq!([text(&screen, 0), text(&screen, 1)], [Some('d'), None]);
     }
 }
@@ -1,106 +1,764 @@
 use {
     std::{
-        io,
-        sync::Arc,
-    },
-    tokio::sync::{
-        mpsc::{
-            channel,
-            Sender,
-            Receiver,
+        io::{
+            self,
+            Write,
+        },
+        cmp::min,
+        ops::Range,
+        sync::{
+            atomic::{
+                AtomicBool,
+                AtomicUsize,
+                Ordering,
+            },
+            Arc,
+            Mutex,
         },
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+    tokio::{
+        sync::Notify,
+        time::delay_until,
     },
     log::trace,
     crate::{
         ansi::{
             self,
+            Attributes,
+            Color,
+            ColorDepth,
+            Flags,
             Handler,
+            TerminalMode,
         },
-        term::Terminal,
+        copy::CopyMode,
+        grid::{
+            self,
+            Cell,
+            Line,
+        },
+        layout::Rect,
+        options::{
+            Options,
+            ScopedOptions,
+        },
+        session::Session,
         util::Point,
-        grid::Cell,
+    },
+    super::{
+        bell::{
+            BellAction,
+            BellConfig,
+            BellLimiter,
+        },
     },
 };
 
-pub fn renderer<W: io::Write>(
-    terminal: Arc<Terminal>,
-    writer: W,
-    start: Point,
-) -> (ScreenRenderer<W>, ScreenRendererNotifier)
-{
-    let (tx, rx) = channel(1);
-    (
-        ScreenRenderer { rx, terminal, writer, start },
-        ScreenRendererNotifier { tx },
-    )
+/// Makes the host report mouse presses, releases and drags, in the SGR
+/// encoding.
+pub const MOUSE_ON: &str = "\x1b[?1000h\x1b[?1002h\x1b[?1006h";
+pub const MOUSE_OFF: &str = "\x1b[?1006l\x1b[?1002l\x1b[?1000l";
+
+#[derive(Clone, Debug)]
+pub struct ScreenRendererConfig {
+    /// Bell handling.
+    pub bell: BellConfig,
+    /// Maximum number of frames drawn per second. Updates that arrive
+    /// faster than this are merged into a single frame.
+    pub frame_rate: u32,
+    /// Colors are downgraded to what the host can display.
+    pub color_depth: ColorDepth,
+}
+
+impl Default for ScreenRendererConfig {
+    fn default() -> Self {
+        Self {
+            bell: BellConfig::default(),
+            frame_rate: 60,
+            color_depth: ColorDepth::default(),
+        }
+    }
+}
+
+impl ScreenRendererConfig {
+    fn frame_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.frame_rate.max(1)
+    }
 }
 
+/// Draws the window shown by a client of a session on its host, below
+/// which there is a status line.
+///
+/// The part of a host larger than the windows is filled with padding.
 pub struct ScreenRenderer<W: io::Write> {
-    rx: Receiver<()>,
-    terminal: Arc<Terminal>,
+    signal: Arc<RenderSignal>,
+    /// Signals of every renderer of the session.
+    renderers: Renderers,
+    /// Client whose host is drawn.
+    client: usize,
+    session: Arc<Mutex<Session>>,
+    options: Arc<Mutex<Options>>,
     writer: W,
-    start: Point,
+    /// Frames written with `writer` that the host didn't receive yet.
+    backlog: Arc<Backlog>,
+    config: ScreenRendererConfig,
+    bell_limiter: BellLimiter,
+    flash_until: Option<Instant>,
+    /// Earliest time at which the next frame can be drawn.
+    next_frame: Instant,
+    /// Output of the frame being drawn. Frames are drawn to memory so that
+    /// the session isn't locked while writing to a slow host.
+    frame: Vec<u8>,
+    /// What the host currently shows.
+    shown: HostModel,
+    /// Stamp of the last rendered frame.
+    last_frame: u64,
+    /// Whether the next frame must clear the host and draw everything.
+    full_redraw: bool,
+    /// Whether the host reports the mouse, which it does while the `mouse`
+    /// option is on or a pane asks for mouse reports.
+    mouse: bool,
 }
 
-pub struct ScreenRendererNotifier {
-    tx: Sender<()>,
+/// State shared between a renderer and its notifiers.
+///
+/// Notifying never blocks: requests that arrive while a frame is pending
+/// are merged into it.
+#[derive(Default)]
+struct RenderSignal {
+    redraw: AtomicBool,
+    /// Whether a bell rang that the renderer didn't act on yet.
+    bell: AtomicBool,
+    closed: AtomicBool,
+    notify: Notify,
 }
 
+impl RenderSignal {
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.notify.notify();
+    }
+}
+
+type Renderers = Arc<Mutex<Vec<Arc<RenderSignal>>>>;
+
+/// Frames that a renderer wrote and that its host didn't receive yet.
+///
+/// The next frame is only drawn once the host received the previous one,
+/// so a slow host gets fewer frames instead of a growing queue of them.
+#[derive(Default)]
+pub struct Backlog {
+    frames: AtomicUsize,
+    /// Whether the host is gone, and frames will never be received.
+    closed: AtomicBool,
+    received: Notify,
+}
+
+impl Backlog {
+    /// Count a frame that was written for the host.
+    pub fn push(&self) {
+        self.frames.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Count a frame that the host received.
+    pub fn pop(&self) {
+        self.frames.fetch_sub(1, Ordering::SeqCst);
+        self.received.notify();
+    }
+
+    /// Stop waiting for the host, once it is gone.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.received.notify();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.closed.load(Ordering::SeqCst) || self.frames.load(Ordering::SeqCst) == 0
+    }
+}
+
+/// Closes the renderers once every notifier is dropped.
+#[derive(Default)]
+struct NotifierHandle {
+    renderers: Renderers,
+}
+
+impl Drop for NotifierHandle {
+    fn drop(&mut self) {
+        for signal in self.renderers.lock().unwrap().iter() {
+            signal.close();
+        }
+    }
+}
+
+/// Notifies every renderer of a session, one for each attached client.
+#[derive(Clone, Default)]
+pub struct ScreenRendererNotifier(Arc<NotifierHandle>);
+
 impl ScreenRendererNotifier {
-    pub async fn notify(&mut self) -> Result<(), ()> {
-        self.tx
-            .send(()).await
-            .map_err(|_| ())
+    /// Create a renderer that draws the host of `client` with `writer`. It
+    /// runs until its handle is dropped, or every notifier is.
+    pub fn renderer<W: io::Write>(
+        &self,
+        client: usize,
+        session: Arc<Mutex<Session>>,
+        options: Arc<Mutex<Options>>,
+        writer: W,
+        backlog: Arc<Backlog>,
+        config: ScreenRendererConfig,
+    ) -> (ScreenRenderer<W>, RendererHandle) {
+        let signal = Arc::new(RenderSignal::default());
+        let renderers = Arc::clone(&self.0.renderers);
+        renderers.lock().unwrap().push(Arc::clone(&signal));
+        (
+            ScreenRenderer {
+                signal: Arc::clone(&signal),
+                renderers: Arc::clone(&renderers),
+                client,
+                session,
+                options,
+                writer,
+                backlog,
+                config,
+                bell_limiter: BellLimiter::default(),
+                flash_until: None,
+                next_frame: Instant::now(),
+                frame: Vec::new(),
+                shown: HostModel::new(Point::default()),
+                last_frame: 0,
+                full_redraw: true,
+                mouse: false,
+            },
+            RendererHandle { signal, renderers },
+        )
+    }
+
+    /// Request drawing whatever changed since the last frame.
+    pub fn notify(&self) {
+        for signal in self.0.renderers.lock().unwrap().iter() {
+            signal.notify.notify();
+        }
+    }
+
+    /// Request clearing the hosts and drawing everything.
+    pub fn redraw(&self) {
+        for signal in self.0.renderers.lock().unwrap().iter() {
+            signal.redraw.store(true, Ordering::SeqCst);
+            signal.notify.notify();
+        }
+    }
+}
+
+/// Closes a renderer when dropped.
+pub struct RendererHandle {
+    signal: Arc<RenderSignal>,
+    renderers: Renderers,
+}
+
+impl Drop for RendererHandle {
+    fn drop(&mut self) {
+        self.renderers.lock().unwrap().retain(|signal| !Arc::ptr_eq(signal, &self.signal));
+        self.signal.close();
     }
 }
 
 impl<W: io::Write> ScreenRenderer<W> {
     pub async fn run_loop(&mut self) -> io::Result<()> {
-        while let Some(()) = self.rx.recv().await {
+        loop {
+            match self.flash_until {
+                // Render again once the visual bell is over, even if there
+                // are no new notifications.
+                Some(flash_until) => tokio::select! {
+                    _ = self.signal.notify.notified() => {},
+                    _ = delay_until(flash_until.into()) => {},
+                },
+                None => self.signal.notify.notified().await,
+            }
+            if self.signal.closed.load(Ordering::SeqCst) {
+                break;
+            }
+
+            // Wait for the frame interval to pass, merging every update
+            // that arrives meanwhile into this frame.
+            if Instant::now() < self.next_frame {
+                delay_until(self.next_frame.into()).await;
+            }
+            // Likewise until the host received the previous frame.
+            while !self.backlog.is_empty() {
+                tokio::select! {
+                    _ = self.backlog.received.notified() => {},
+                    _ = self.signal.notify.notified() => {},
+                }
+                if self.signal.closed.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+            }
+            if self.signal.redraw.swap(false, Ordering::SeqCst) {
+                self.redraw();
+            }
+            self.ring_bell()?;
             self.render_screen()?;
+            self.next_frame = Instant::now() + self.config.frame_interval();
+        }
+        Ok(())
+    }
+
+    /// Clear the host and draw everything on the next frame.
+    pub fn redraw(&mut self) {
+        self.full_redraw = true;
+    }
+
+    /// Act on the bell of any terminal of the session, if it rang since the
+    /// last frame.
+    fn ring_bell(&mut self) -> io::Result<()> {
+        let now = Instant::now();
+        if let Some(flash_until) = self.flash_until {
+            if now >= flash_until {
+                self.flash_until = None;
+            }
+        }
+        let mut rang = false;
+        let (action, rate_limit) = {
+            let session = self.session.lock().unwrap();
+            for window in session.windows() {
+                for terminal in window.terminals() {
+                    rang |= terminal.take_pending_bell();
+                }
+            }
+            let options = self.options.lock().unwrap();
+            let options = session.scoped_options(&options);
+            let action: BellAction = options.string("bell-action").parse().unwrap_or_default();
+            // Bells that ring faster than this only set the bell flag.
+            (action, Duration::from_millis(options.number("bell-rate-limit") as u64))
+        };
+        // Every host hears the bells, whichever renderer took them.
+        if rang {
+            for signal in self.renderers.lock().unwrap().iter() {
+                signal.bell.store(true, Ordering::SeqCst);
+                signal.notify.notify();
+            }
+        }
+        let rang = self.signal.bell.swap(false, Ordering::SeqCst);
+        if !rang || !self.bell_limiter.allow(now, rate_limit) {
+            return Ok(());
+        }
+        trace!("ringing bell: action={:?}", action);
+        match action {
+            BellAction::None => {},
+            BellAction::Forward => write!(self.frame, "\x07")?,
+            BellAction::Visual => {
+                self.flash_until = Some(now + self.config.bell.flash_duration);
+            },
         }
         Ok(())
     }
 
     fn render_screen(&mut self) -> io::Result<()> {
         trace!("rendering screen");
-        let screen = self.terminal.screen.lock().unwrap();
-        let mut prev_attrs = None;
-        let size = screen.size();
-        writeln!(self.writer, "\x1b[2J\x1b[3J\x1b[{};{}H", self.start.y, self.start.x)?;
-        write!(self.writer, "+")?;
-        for _ in 0..screen.size().x {
-            write!(self.writer, "-")?;
-        }
-        writeln!(self.writer, "+")?;
+        let frame = grid::damage_frame();
+        let session = Arc::clone(&self.session);
+        let session = session.lock().unwrap();
+        let flash = self.flash_until.is_some();
+        let (style, mouse) = {
+            let options = self.options.lock().unwrap();
+            let options = session.scoped_options(&options);
+            (StatusStyle::new(&options), options.flag("mouse"))
+        };
+        let window = session.client_window(self.client);
+        // Panes that asked for mouse reports get them even when terman
+        // doesn't use the mouse itself.
+        let mouse = mouse || window.is_some_and(|window| {
+            window.terminals().any(|terminal| terminal.screen.lock().unwrap().mouse_reporting())
+        });
+        // The status line goes at the bottom of the host. Without it, the
+        // prompt and messages are drawn over the last line of the windows.
+        let lines = 1 + style.enabled as usize;
+        let size = session.client_size(self.client)
+            .unwrap_or_else(|| session.size() + Point::new(0, lines - 1));
+        let size = Point::new(size.x.max(1), size.y.max(lines));
+        let rows = size.y - (lines - 1);
+        let overlay = !style.enabled && (session.prompt().is_some() || session.message().is_some());
+        let full_redraw = self.full_redraw || self.shown.size != size;
+        // A host may have been attached since the last full redraw, it
+        // doesn't know whether to report the mouse.
+        if mouse != self.mouse || full_redraw {
+            write!(self.frame, "{}", if mouse { MOUSE_ON } else { MOUSE_OFF })?;
+            self.mouse = mouse;
+        }
+
+        // Hide the cursor while drawing so that it doesn't jump around.
+        write!(self.frame, "\x1b[?25l")?;
+        if full_redraw {
+            trace!("full redraw");
+            write!(self.frame, "\x1b[0m\x1b[2J\x1b[3J")?;
+            self.shown = HostModel::new(size);
+            self.full_redraw = false;
+        }
+
+        let mut cursor = None;
+        if let Some(window) = window {
+            let panes = window.layout().panes();
+            let rects: Vec<Rect> = panes.iter().map(|(_, _, rect)| *rect).collect();
+            // Lines that didn't change may still have to be drawn when the
+            // panes moved or another window is shown, the host model skips
+            // the cells that are shown.
+            let force = self.shown.flash != flash || self.shown.panes != rects
+                || self.shown.overlay != overlay || self.shown.window != Some(window.index());
+            if self.shown.panes != rects {
+                self.render_borders(session.size(), &rects)?;
+                self.render_padding(session.size(), Point::new(size.x, rows))?;
+            }
+            self.shown.flash = flash;
+            self.shown.window = Some(window.index());
+            self.shown.panes = rects;
+            self.shown.overlay = overlay;
+            for (id, terminal, rect) in panes {
+                let active = id == window.active_pane();
+                if let Some(copy_mode) = terminal.copy_mode.lock().unwrap().as_ref() {
+                    self.render_copy_mode(copy_mode, rect, flash && active)?;
+                    if active {
+                        cursor = Some(rect.start + copy_mode.cursor());
+                    }
+                    continue;
+                }
+                let screen = terminal.screen.lock().unwrap();
+                let lines = min(rect.size.y, screen.size().y);
+                for y in 0..lines {
+                    if force || screen.is_damaged_since(y, self.last_frame) {
+                        self.render_line(screen.line(y).unwrap(), rect, y, flash && active)?;
+                    }
+                }
+                if active && screen.mode(TerminalMode::ShowCursor) {
+                    let mut position = *screen.cursor();
+                    // Double width lines are drawn with a blank after each
+                    // character, see `render_line`.
+                    if screen.line(position.y).is_some_and(|line| line.size.is_double_width()) {
+                        position.x *= 2;
+                    }
+                    cursor = Some(rect.start + position);
+                }
+            }
+        }
+        // The cursor may be out of a host smaller than the windows.
+        cursor = cursor.filter(|cursor| cursor.x < size.x && cursor.y < rows);
+        if style.enabled || overlay {
+            let active = window.map(|window| window.index());
+            if let Some(prompt_cursor) = self.render_status(&session, active, &style, size)? {
+                cursor = Some(prompt_cursor);
+            }
+        }
+        self.last_frame = frame;
+        drop(session);
+
+        if let Some(cursor) = cursor {
+            trace!("real cursor position: {:?}", cursor);
+            self.move_host_cursor(cursor)?;
+            write!(self.frame, "\x1b[?25h")?;
+        }
+
+        self.writer.write_all(&self.frame)?;
+        self.frame.clear();
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Move the host cursor to the 0-based position `point`.
+    fn move_host_cursor(&mut self, point: Point) -> io::Result<()> {
+        if self.shown.cursor != Some(point) {
+            write!(self.frame, "\x1b[{};{}H", point.y + 1, point.x + 1)?;
+            self.shown.cursor = Some(point);
+        }
+        Ok(())
+    }
+
+    /// Draw a cell at the 0-based host position `point`, unless the host
+    /// already shows it.
+    fn render_cell(&mut self, point: Point, cell: &Cell) -> io::Result<()> {
+        if point.y >= self.shown.size.y || point.x >= self.shown.size.x
+            || self.shown.lines[point.y][point.x] == *cell
+        {
+            return Ok(());
+        }
+        self.move_host_cursor(point)?;
+        let mut renderer = ansi::Renderer(&mut self.frame, self.config.color_depth);
+        match &self.shown.attributes {
+            Some(shown) => renderer.render_transition(shown, &cell.attributes)?,
+            None => renderer.render_attributes(&cell.attributes)?,
+        }
+        self.shown.attributes = Some(cell.attributes.clone());
+        write!(self.frame, "{}", cell.ch.unwrap_or(' '))?;
+        // The host cursor is left after the character, unless it's on the
+        // last column, where its position depends on the host.
+        self.shown.cursor = if point.x + 1 < self.shown.size.x {
+            Some(Point::new(point.x + 1, point.y))
+        } else {
+            None
+        };
+        self.shown.lines[point.y][point.x] = cell.clone();
+        Ok(())
+    }
+
+    /// Draw the borders between panes, which are the cells of the window
+    /// that no pane covers.
+    fn render_borders(&mut self, size: Point, panes: &[Rect]) -> io::Result<()> {
+        let is_border = |x: usize, y: usize| {
+            x < size.x && y < size.y && !panes.iter().any(|rect| rect.contains(Point::new(x, y)))
+        };
         for y in 0..size.y {
-            write!(self.writer, "\x1b[{}G|", self.start.x)?;
             for x in 0..size.x {
-                let ref cell = screen.cell(Point::new(x, y)).unwrap();
-                if Some(&cell.attributes) != prev_attrs {
-                    ansi::Renderer(&mut self.writer).render_attributes(&cell.attributes)?;
+                if !is_border(x, y) {
+                    continue;
                 }
-                prev_attrs = Some(&cell.attributes);
-                write!(self.writer, "{}", match cell.ch {
-                    Some(c) => c,
-                    None => ' ',
-                })?;
+                let horizontal = (x > 0 && is_border(x - 1, y)) || is_border(x + 1, y);
+                let vertical = (y > 0 && is_border(x, y - 1)) || is_border(x, y + 1);
+                let ch = match (horizontal, vertical) {
+                    (true, true) => '+',
+                    (true, false) => '-',
+                    (false, _) => '|',
+                };
+                let cell = Cell {
+                    ch: Some(ch),
+                    ..Cell::default()
+                };
+                self.render_cell(Point::new(x, y), &cell)?;
             }
-            writeln!(self.writer, "|")?;
-        }
-        write!(self.writer, "\x1b[{}G+", self.start.x)?;
-        for _ in 0..screen.size().x {
-            write!(self.writer, "-")?;
-        }
-        writeln!(self.writer, "+")?;
-        let cursor = screen.cursor();
-        let real_cursor = Point::new(
-            cursor.x + self.start.x + 1,
-            cursor.y + self.start.y + 2,
-        );
-        trace!("real cursor position: {:?}", real_cursor);
-        write!(self.writer, "\x1b[{};{}H", real_cursor.y, real_cursor.x)?;
-        self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Fill the part of the host of `size` that the windows of `area`
+    /// don't cover.
+    fn render_padding(&mut self, area: Point, size: Point) -> io::Result<()> {
+        let cell = Cell {
+            ch: Some('.'),
+            ..Cell::default()
+        };
+        for y in 0..size.y {
+            let start = if y < area.y { area.x } else { 0 };
+            for x in start..size.x {
+                self.render_cell(Point::new(x, y), &cell)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the status line, which lists the windows of the session.
+    ///
+    /// Windows are flagged with `*` when shown, and `!` when the bell rang
+    /// or `#` when there was activity in a pane that isn't focused. The
+    /// command prompt and messages take the place of the list.
+    ///
+    /// Returns the position of the cursor when the prompt is open.
+    fn render_status(
+        &mut self,
+        session: &Session,
+        active: Option<usize>,
+        style: &StatusStyle,
+        size: Point,
+    ) -> io::Result<Option<Point>> {
+        let attributes = style.attributes();
+        let mut cursor = None;
+        let status = if let Some(prompt) = session.prompt() {
+            let status = format!("{}{}", prompt.label, prompt.text);
+            // Keep the end of long commands in view.
+            let skip = (status.chars().count() + 1).saturating_sub(size.x);
+            let status: String = status.chars().skip(skip).collect();
+            cursor = Some(Point::new(status.chars().count(), size.y - 1));
+            status
+        } else if let Some(message) = session.message() {
+            message.to_owned()
+        } else {
+            window_list(session, &self.options.lock().unwrap(), active).0
+        };
+        let mut chars = status.chars();
+        for x in 0..size.x {
+            let cell = Cell {
+                ch: chars.next(),
+                attributes: attributes.clone(),
+            };
+            self.render_cell(Point::new(x, size.y - 1), &cell)?;
+        }
+        Ok(cursor)
+    }
+
+    /// Draw a pane in copy mode, with the position of the view in the
+    /// history at its top right.
+    ///
+    /// Every line is drawn, the host model skips the cells that are shown.
+    fn render_copy_mode(&mut self, copy_mode: &CopyMode, rect: Rect, flash: bool) -> io::Result<()> {
+        for y in 0..rect.size.y {
+            let line = copy_mode.line(y).unwrap_or_else(|| Line::new(rect.size.x));
+            self.render_line(&line, rect, y, flash)?;
+        }
+        let (offset, history) = copy_mode.position();
+        let mut position = format!("[{}/{}]", offset, history);
+        // The match counter goes before the position.
+        match copy_mode.search_position() {
+            Some((_, 0)) => position.insert_str(0, "(no matches) "),
+            Some((Some(current), count)) => position.insert_str(0, &format!("({}/{} results) ", current, count)),
+            Some((None, count)) => position.insert_str(0, &format!("({} results) ", count)),
+            None => {},
+        }
+        let width = position.chars().count();
+        if width <= rect.size.x {
+            let mut cell = Cell::default();
+            cell.attributes.flags.insert(Flags::INVERSE);
+            for (x, ch) in position.chars().enumerate() {
+                cell.ch = Some(ch);
+                self.render_cell(rect.start + Point::new(rect.size.x - width + x, 0), &cell)?;
+            }
+        }
         Ok(())
     }
+
+    /// Draw the cells of line `y` of a pane that differ from what the host
+    /// shows.
+    fn render_line(&mut self, line: &Line, rect: Rect, y: usize, flash: bool) -> io::Result<()> {
+        // Panes rarely span the whole host line, so the DEC line
+        // attributes can't be forwarded to the host. Double width lines
+        // are approximated by following each character with a blank,
+        // which keeps the left half of the line visible.
+        let double_width = line.size.is_double_width();
+        for x in 0..min(line.len(), rect.size.x) {
+            let mut cell = if double_width {
+                let mut cell = line[x / 2].clone();
+                if x % 2 == 1 {
+                    cell.ch = None;
+                }
+                cell
+            } else {
+                line[x].clone()
+            };
+            if flash {
+                cell.attributes.flags.toggle(Flags::INVERSE);
+            }
+            self.render_cell(rect.start + Point::new(x, y), &cell)?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the list of windows of the status line of a client showing window
+/// `active`, with the columns taken by each window.
+fn window_list(
+    session: &Session,
+    global: &Options,
+    active: Option<usize>,
+) -> (String, Vec<(Range<usize>, usize)>) {
+    let mut status = format!("[{}]", session.name());
+    let mut columns = Vec::new();
+    for window in session.windows() {
+        let monitor_activity = window.scoped_options(session.scoped_options(global))
+            .flag("monitor-activity");
+        let shown = if Some(window.index()) == active { "*" } else { "" };
+        let flag = if window.has_bell() {
+            "!"
+        } else if monitor_activity && window.has_activity() {
+            "#"
+        } else {
+            ""
+        };
+        status.push(' ');
+        let start = status.chars().count();
+        status.push_str(&format!("{}:{}{}{}", window.index(), window.name(), shown, flag));
+        columns.push((start..status.chars().count(), window.index()));
+    }
+    (status, columns)
+}
+
+/// Get the index of the window listed at column `x` of the status line of
+/// the client showing the active window.
+pub fn window_at(session: &Session, global: &Options, x: usize) -> Option<usize> {
+    let active = session.active_window().map(|window| window.index());
+    window_list(session, global, active).1.into_iter()
+        .find(|(columns, _)| columns.contains(&x))
+        .map(|(_, index)| index)
+}
+
+/// How the status line is drawn, from the options.
+struct StatusStyle {
+    enabled: bool,
+    fg: Color,
+    bg: Color,
+}
+
+impl StatusStyle {
+    fn new(options: &ScopedOptions) -> Self {
+        Self {
+            enabled: options.flag("status"),
+            fg: options.color("status-fg"),
+            bg: options.color("status-bg"),
+        }
+    }
+
+    /// Get the attributes of the status line cells. With the default
+    /// colors, the status line is drawn in inverse video.
+    fn attributes(&self) -> Attributes {
+        let mut attributes = Attributes::default();
+        match (self.fg, self.bg) {
+            (Color::Special(_), Color::Special(_)) => attributes.flags.insert(Flags::INVERSE),
+            (fg, bg) => {
+                attributes.fg = fg;
+                attributes.bg = bg;
+            },
+        }
+        attributes
+    }
+}
+
+/// Model of what the host terminal currently shows.
+struct HostModel {
+    size: Point,
+    lines: Vec<Vec<Cell>>,
+    /// Index of the window that is shown.
+    window: Option<usize>,
+    /// Rectangles of the panes that are shown.
+    panes: Vec<Rect>,
+    flash: bool,
+    /// Whether the prompt or a message is drawn over the panes.
+    overlay: bool,
+    /// Host cursor position, if known.
+    cursor: Option<Point>,
+    /// Attributes that the host is currently using, if known.
+    attributes: Option<Attributes>,
+}
+
+impl HostModel {
+    /// Model of the host after its screen is cleared.
+    fn new(size: Point) -> Self {
+        Self {
+            size,
+            lines: vec![vec![Cell::default(); size.x]; size.y],
+            window: None,
+            panes: Vec::new(),
+            flash: false,
+            overlay: false,
+            cursor: None,
+            attributes: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Backlog;
+
+    #[test]
+    fn test_backlog() {
+        let backlog = Backlog::default();
+        assert!(backlog.is_empty());
+        backlog.push();
+        backlog.push();
+        backlog.pop();
+        assert!(!backlog.is_empty());
+        backlog.pop();
+        assert!(backlog.is_empty());
+        backlog.push();
+        backlog.close();
+        assert!(backlog.is_empty());
+    }
 }
@@ -0,0 +1,396 @@
+use {
+    std::{
+        io,
+        sync::{
+            atomic::Ordering,
+            Arc,
+            Mutex,
+        },
+    },
+    anyhow::{
+        anyhow,
+        Result,
+    },
+    log::trace,
+    tokio::{
+        net::UnixStream,
+        sync::mpsc::{
+            self,
+            UnboundedReceiver,
+            UnboundedSender,
+        },
+        task,
+        time::timeout,
+    },
+    crate::{
+        command::{
+            parser::parse_command_line,
+            Command,
+            CommandContext,
+            Target,
+        },
+        input::{
+            Input,
+            InputConfig,
+            KeyEvent,
+        },
+        options::ScopedOptions,
+        screen::renderer::{
+            Backlog,
+            ScreenRendererConfig,
+        },
+        util::Point,
+    },
+    super::{
+        input,
+        protocol::{
+            read_message,
+            write_message,
+            ClientMessage,
+            ServerMessage,
+        },
+        Server,
+    },
+};
+
+/// A client attached to a session.
+struct AttachedClient {
+    id: usize,
+    sender: UnboundedSender<ServerMessage>,
+}
+
+/// The clients attached to a session.
+#[derive(Clone, Default)]
+pub struct Clients(Arc<Mutex<Vec<AttachedClient>>>);
+
+impl Clients {
+    /// Attach client `id`, getting where its renderer writes.
+    fn attach(
+        &self,
+        id: usize,
+        sender: UnboundedSender<ServerMessage>,
+        backlog: Arc<Backlog>,
+    ) -> ClientOutput {
+        let _ = sender.send(ServerMessage::Attached);
+        self.0.lock().unwrap().push(AttachedClient { id, sender: sender.clone() });
+        ClientOutput { sender, backlog }
+    }
+
+    /// Detach client `id`, which exits after showing `message`.
+    pub fn detach(&self, id: usize, message: String) {
+        let mut clients = self.0.lock().unwrap();
+        if let Some(position) = clients.iter().position(|client| client.id == id) {
+            let client = clients.remove(position);
+            let _ = client.sender.send(ServerMessage::Exit { code: 0, message });
+        }
+    }
+
+    /// Detach every client, which exit after showing `message`.
+    pub fn detach_all(&self, message: String) {
+        for client in self.0.lock().unwrap().drain(..) {
+            let _ = client.sender.send(ServerMessage::Exit { code: 0, message: message.clone() });
+        }
+    }
+
+    /// Forget client `id` if it is still attached, once it is gone.
+    fn remove(&self, id: usize) {
+        self.0.lock().unwrap().retain(|client| client.id != id);
+    }
+
+    fn is_attached(&self, id: usize) -> bool {
+        self.0.lock().unwrap().iter().any(|client| client.id == id)
+    }
+}
+
+/// Where the renderer of a client writes.
+pub struct ClientOutput {
+    sender: UnboundedSender<ServerMessage>,
+    /// Output that wasn't written to the client yet.
+    backlog: Arc<Backlog>,
+}
+
+impl io::Write for ClientOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.sender.send(ServerMessage::Output(buf.to_vec())).is_ok() {
+            self.backlog.push();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Select the window and pane of `target` in the session of `context`,
+/// which a client attaching to it shows first.
+fn select_attach_target(context: &CommandContext, target: Option<&Target>) -> Result<()> {
+    let target = match target {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+    let mut session = context.session.lock().unwrap();
+    if let Some(index) = target.window {
+        session.select_window(index)?;
+    }
+    if let Some(position) = target.pane {
+        let window = session.active_window_mut().ok_or_else(|| anyhow!("no window"))?;
+        let id = window.pane_at(position).ok_or_else(|| anyhow!("no such pane: {}", position))?;
+        window.select_pane(id);
+    }
+    context.notifier.notify();
+    Ok(())
+}
+
+/// Run the commands a client was run with, `args`, in the sessions they
+/// target or the last session. Without any, a session is created.
+///
+/// Returns the lines the commands printed, and the session to attach the
+/// client to if a command asked for it, read-only or not.
+async fn run_commands(
+    server: &Arc<Server>,
+    args: &[String],
+    size: Point,
+    cell_size: Point,
+) -> Result<(Vec<String>, Option<(Arc<CommandContext>, bool)>)> {
+    let commands = if args.is_empty() {
+        vec![(Command::NewSession { name: None, detached: false, command: None }, None)]
+    } else {
+        parse_command_line(args)?
+    };
+    let mut printed = Vec::new();
+    let mut attach = None;
+    for (command, target) in commands {
+        let name = target.as_ref().and_then(|target| target.session.as_deref());
+        match command {
+            Command::NewSession { name, detached, command } => {
+                let context = server.new_session(size, cell_size, name, command).await?;
+                if !detached {
+                    attach = Some((context, false));
+                }
+            },
+            Command::AttachSession { read_only } => {
+                let context = server.find_session(name)?;
+                select_attach_target(&context, target.as_ref())?;
+                attach = Some((context, read_only));
+            },
+            command => {
+                let input = Input::new(InputConfig::default(), Arc::clone(&server.tables));
+                let context = server.find_session(name)?.for_command_line(input, target.unwrap_or_default());
+                context.configure_input();
+                context.execute(&command).await?;
+                if let Some(lines) = &context.printed {
+                    printed.append(&mut lines.lock().unwrap());
+                }
+            },
+        }
+    }
+    Ok((printed, attach))
+}
+
+/// Serve a client connected on `stream`: run the commands it was run with,
+/// then route its input to the session it is attached to until it
+/// detaches or goes away.
+pub async fn serve(server: &Arc<Server>, stream: UnixStream) -> Result<()> {
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let (sender, mut outgoing) = mpsc::unbounded_channel();
+    let backlog = Arc::new(Backlog::default());
+    // Messages are written by their own task so that a slow client never
+    // blocks the session.
+    let writing = {
+        let backlog = Arc::clone(&backlog);
+        task::spawn(async move {
+            let res = async {
+                while let Some(message) = outgoing.recv().await {
+                    let exit = matches!(message, ServerMessage::Exit { .. });
+                    let output = matches!(message, ServerMessage::Output(_));
+                    write_message(&mut writer, &message).await?;
+                    if output {
+                        backlog.pop();
+                    }
+                    if exit {
+                        break;
+                    }
+                }
+                Ok::<_, anyhow::Error>(())
+            }.await;
+            backlog.close();
+            res
+        })
+    };
+    // Messages are read by their own task, waiting for one can be given up
+    // without losing part of it.
+    let (incoming_sender, mut incoming) = mpsc::unbounded_channel();
+    task::spawn(async move {
+        loop {
+            match read_message::<ClientMessage, _>(&mut reader).await {
+                Ok(Some(message)) => {
+                    if incoming_sender.send(message).is_err() {
+                        break;
+                    }
+                },
+                Ok(None) => break,
+                Err(e) => {
+                    trace!("client read failed: {:#}", e);
+                    break;
+                },
+            }
+        }
+    });
+
+    let (size, cell_size, color_depth) = match incoming.recv().await {
+        Some(ClientMessage::Identify { size, cell_size, color_depth }) => (size, cell_size, color_depth),
+        _ => return Err(anyhow!("client didn't identify")),
+    };
+    let args = match incoming.recv().await {
+        Some(ClientMessage::Command(args)) => args,
+        _ => return Err(anyhow!("client sent no command")),
+    };
+    trace!("client command: {:?}", args);
+    let (context, read_only) = match run_commands(server, &args, size, cell_size).await {
+        Ok((_, Some(attach))) => attach,
+        Ok((printed, None)) => {
+            let _ = sender.send(ServerMessage::Exit { code: 0, message: printed.join("\n") });
+            drop(sender);
+            return writing.await?;
+        },
+        Err(e) => {
+            let _ = sender.send(ServerMessage::Exit { code: 1, message: format!("{:#}", e) });
+            drop(sender);
+            return writing.await?;
+        },
+    };
+
+    let id = server.next_client.fetch_add(1, Ordering::SeqCst);
+    let input = Input::new(InputConfig::default(), Arc::clone(&server.tables));
+    let context = context.for_client(id, input, read_only);
+    context.configure_input();
+    let output = context.clients.attach(id, sender, Arc::clone(&backlog));
+    let frame_rate = ScopedOptions::new(&context.options.lock().unwrap()).number("frame-rate") as u32;
+    let (mut renderer, renderer_handle) = context.notifier.renderer(
+        id,
+        Arc::clone(&context.session),
+        Arc::clone(&context.options),
+        output,
+        backlog,
+        ScreenRendererConfig {
+            frame_rate,
+            color_depth,
+            ..ScreenRendererConfig::default()
+        },
+    );
+    task::spawn(async move {
+        if let Err(e) = renderer.run_loop().await {
+            trace!("render task failed: {}", e);
+        }
+    });
+    context.session.lock().unwrap().attach_client(id, size, read_only);
+    fit(&context);
+    context.notifier.notify();
+
+    let res = read_input(&context, id, &mut incoming).await;
+    // A session that lost its last pane tells the client it exited.
+    if !context.session.lock().unwrap().is_empty() {
+        context.clients.remove(id);
+    }
+    context.session.lock().unwrap().detach_client(id);
+    drop(renderer_handle);
+    fit(&context);
+    trace!("client {} finished", id);
+    res?;
+    writing.await?
+}
+
+/// Resize the windows of the session to fit the attached clients.
+fn fit(context: &CommandContext) {
+    let resized = {
+        let mut session = context.session.lock().unwrap();
+        let size = session.size();
+        if let Err(e) = session.fit_clients(&context.options.lock().unwrap()) {
+            trace!("failed to resize session: {}", e);
+        }
+        session.size() != size
+    };
+    if resized {
+        context.notifier.redraw();
+    }
+}
+
+/// Route the input of attached client `id` until it detaches.
+async fn read_input(
+    context: &CommandContext,
+    id: usize,
+    incoming: &mut UnboundedReceiver<ClientMessage>,
+) -> Result<()> {
+    let input = &context.input;
+    while context.clients.is_attached(id) {
+        // Options may have been set from another client.
+        context.configure_input();
+        let (pending, escape_time) = {
+            let input = input.lock().unwrap();
+            (input.has_pending(), input.config().escape_time)
+        };
+        let message = if pending {
+            // Wait for the rest of the sequence, or read the escape key on
+            // its own.
+            match timeout(escape_time, incoming.recv()).await {
+                Ok(message) => message,
+                Err(_) => {
+                    let events = input.lock().unwrap().flush();
+                    if !handle_events(context, events).await? {
+                        return Ok(());
+                    }
+                    continue;
+                },
+            }
+        } else {
+            incoming.recv().await
+        };
+        let events = match message {
+            Some(ClientMessage::Input(bytes)) => input.lock().unwrap().decode(&bytes),
+            Some(ClientMessage::Resize(size)) => {
+                context.session.lock().unwrap().resize_client(id, size);
+                fit(context);
+                context.notifier.notify();
+                continue;
+            },
+            Some(message) => {
+                trace!("unexpected client message: {:?}", message);
+                continue;
+            },
+            None => return Ok(()),
+        };
+        if !handle_events(context, events).await? {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Handle keys and mouse events read from the host.
+///
+/// Returns whether there still is a pane to send input to.
+///
+/// The client becomes the one that was used last, commands act on the
+/// window it shows. Read-only clients don't take the session from the
+/// others and don't use the mouse.
+async fn handle_events(context: &CommandContext, events: Vec<KeyEvent>) -> Result<bool> {
+    if events.is_empty() {
+        return Ok(true);
+    }
+    if let Some(id) = context.client.filter(|_| !context.read_only) {
+        context.session.lock().unwrap().focus_client(id);
+        fit(context);
+    }
+    for event in events {
+        let running = match event.mouse {
+            Some(_) if context.read_only => true,
+            Some(mouse) => input::handle_mouse(context, mouse).await?,
+            None => input::handle_key(context, event).await?,
+        };
+        if !running {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
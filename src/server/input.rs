@@ -0,0 +1,398 @@
+use {
+    std::{
+        io,
+        sync::Arc,
+        time::Instant,
+    },
+    anyhow::{
+        Context,
+        Result,
+    },
+    log::trace,
+    crate::{
+        ansi::{
+            Handler,
+            TerminalMode,
+        },
+        command::{
+            Command,
+            CommandContext,
+        },
+        copy::CopyAction,
+        input::{
+            mouse::{
+                Drag,
+                MouseAction,
+                MouseButton,
+                MouseEvent,
+            },
+            prompt::{
+                self,
+                PromptEdit,
+            },
+            InputAction,
+            KeyEvent,
+            COPY_MODE_TABLE,
+            COPY_MODE_VI_TABLE,
+            ROOT_TABLE,
+        },
+        layout::{
+            PaneId,
+            Rect,
+        },
+        screen::renderer,
+        term::Terminal,
+    },
+};
+
+/// Get the table that keys are read from: a copy mode table when the
+/// active pane is in copy mode, otherwise the root table.
+fn base_table(context: &CommandContext) -> &'static str {
+    let session = context.session.lock().unwrap();
+    let window = match session.active_window() {
+        Some(window) => window,
+        None => return ROOT_TABLE,
+    };
+    let copy_mode = window.active_terminal()
+        .is_some_and(|terminal| terminal.copy_mode.lock().unwrap().is_some());
+    if !copy_mode {
+        return ROOT_TABLE;
+    }
+    let options = context.options.lock().unwrap();
+    let options = window.pane_scoped_options(session.scoped_options(&options), window.active_pane());
+    match options.string("mode-keys").as_str() {
+        "vi" => COPY_MODE_VI_TABLE,
+        _ => COPY_MODE_TABLE,
+    }
+}
+
+/// Get the terminals that keys are sent to: the active pane, or every
+/// pane of the active window when its panes are synchronized.
+fn target_terminals(context: &CommandContext) -> Option<Vec<Arc<Terminal>>> {
+    let session = context.session.lock().unwrap();
+    let window = session.active_window()?;
+    let options = context.options.lock().unwrap();
+    let options = window.pane_scoped_options(session.scoped_options(&options), window.active_pane());
+    if options.flag("synchronize-panes") {
+        Some(window.terminals().cloned().collect())
+    } else {
+        window.active_terminal().map(|terminal| vec![terminal])
+    }
+}
+
+/// Returns whether a read-only client can run `command`: it can only
+/// detach and choose the window it shows.
+fn read_only_allows(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::DetachClient | Command::NextWindow | Command::PreviousWindow | Command::SelectWindow(_)
+    )
+}
+
+/// Route a key read from the host to the command prompt, the key
+/// bindings or the active pane.
+///
+/// The keys of read-only clients only run the commands they are allowed.
+///
+/// Returns whether there still is a pane to send keys to.
+pub async fn handle_key(context: &CommandContext, event: KeyEvent) -> Result<bool> {
+    let session = &context.session;
+    let submitted = if context.read_only {
+        None
+    } else {
+        let mut session = session.lock().unwrap();
+        if session.message().is_some() {
+            session.set_message(None);
+            context.notifier.notify();
+        }
+        match (session.prompt_mut(), event.key) {
+            (Some(prompt), Some(key)) => {
+                let edit = prompt::edit(&mut prompt.text, &key);
+                // Incremental prompts run their command as the text changes,
+                // it is done when the text is entered.
+                let line = match &edit {
+                    PromptEdit::Edited if prompt.incremental => Some(prompt.command_line(&prompt.text)),
+                    PromptEdit::Submit(text) if !prompt.incremental => Some(prompt.command_line(text)),
+                    _ => None,
+                };
+                if edit != PromptEdit::Edited {
+                    session.set_prompt(None);
+                }
+                context.notifier.notify();
+                match line {
+                    Some(line) => Some(line),
+                    None => return Ok(true),
+                }
+            },
+            (Some(_), None) => return Ok(true),
+            (None, _) => None,
+        }
+    };
+    if let Some(line) = submitted {
+        if let Err(e) = context.execute_line(&line).await {
+            session.lock().unwrap().set_message(Some(format!("{:#}", e)));
+            context.notifier.notify();
+        }
+        return Ok(true);
+    }
+
+    let table = base_table(context);
+    let actions = {
+        let mut input = context.input.lock().unwrap();
+        input.set_base_table(table);
+        input.handle(event, Instant::now())
+    };
+    for action in actions {
+        let event = match action {
+            InputAction::Forward(_) if context.read_only => continue,
+            InputAction::Forward(event) => event,
+            InputAction::Execute(command) if context.read_only && !read_only_allows(&command) => {
+                trace!("read-only client can't run: {:?}", command);
+                continue;
+            },
+            InputAction::Execute(command) => {
+                if let Err(e) = context.execute(&command).await {
+                    trace!("command failed: {:?}: {}", command, e);
+                    session.lock().unwrap().set_message(Some(format!("{:#}", e)));
+                    context.notifier.notify();
+                }
+                continue;
+            },
+        };
+        let terminals = match target_terminals(context) {
+            Some(terminals) => terminals,
+            None => return Ok(false),
+        };
+        for terminal in terminals {
+            let cursor_keys = terminal.screen.lock().unwrap().mode(TerminalMode::CursorKeys);
+            trace!("writing terminal pty");
+            let res = terminal.pty_write(&event.encode(cursor_keys)).await;
+            trace!("wrote terminal pty");
+            match res {
+                Ok(_) => {},
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => return Ok(false),
+                Err(e) => return Err(e).context("pty write"),
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Number of lines scrolled by a step of the mouse wheel.
+const WHEEL_LINES: usize = 3;
+
+/// Get the report of a mouse event for a pane, or `None` if the pane
+/// doesn't take the mouse: it didn't ask for mouse reports, or it is in
+/// copy mode. Drags are only reported if the pane asked for motion.
+fn mouse_report(terminal: &Terminal, rect: Rect, event: &MouseEvent) -> Option<Vec<u8>> {
+    if terminal.copy_mode.lock().unwrap().is_some() {
+        return None;
+    }
+    let screen = terminal.screen.lock().unwrap();
+    if !screen.mouse_reporting() {
+        return None;
+    }
+    let motion = screen.mode(TerminalMode::ReportCellMouseMotion)
+        || screen.mode(TerminalMode::ReportAllMouseMotion);
+    if event.action == MouseAction::Drag && !motion {
+        return Some(Vec::new());
+    }
+    Some(event.encode(rect.relative(event.point), screen.mode(TerminalMode::SgrMouse)))
+}
+
+/// Get pane `id` of the active window, with its rectangle.
+fn active_pane(context: &CommandContext, id: PaneId) -> Option<(Arc<Terminal>, Rect)> {
+    let session = context.session.lock().unwrap();
+    let (terminal, rect) = session.active_window()?.layout().pane(id)?;
+    Some((Arc::clone(terminal), rect))
+}
+
+/// Execute a command from the mouse, showing its error on the status line.
+async fn execute_mouse(context: &CommandContext, command: Command) {
+    if let Err(e) = context.execute(&command).await {
+        trace!("command failed: {:?}: {}", command, e);
+        context.session.lock().unwrap().set_message(Some(format!("{:#}", e)));
+        context.notifier.notify();
+    }
+}
+
+/// Route a mouse event read from the host to the pane under it, if the
+/// pane asked for mouse reports, or use it in terman when the `mouse`
+/// option is on.
+///
+/// With the left button, a click selects a pane or a window of the status
+/// line, a drag moves a border or selects text, which is copied to a paste
+/// buffer when the button is released. Double and triple clicks select a
+/// word or a line. The wheel scrolls the pane in copy mode.
+///
+/// Returns whether there still is a pane to send input to.
+pub async fn handle_mouse(context: &CommandContext, event: MouseEvent) -> Result<bool> {
+    let drag = context.input.lock().unwrap().mouse_mut().drag;
+    let set_drag = |drag| context.input.lock().unwrap().mouse_mut().drag = drag;
+    let (enabled, status_window, pane) = {
+        let session = context.session.lock().unwrap();
+        let global = context.options.lock().unwrap();
+        let options = session.scoped_options(&global);
+        let status = options.flag("status") && session.prompt().is_none() && session.message().is_none();
+        // The status line is the last line of the host of the client.
+        let host = context.client.and_then(|id| session.client_size(id));
+        let status_line = host.map_or(session.size().y, |host| host.y.saturating_sub(1));
+        let status_window = if status && event.point.y == status_line {
+            renderer::window_at(&session, &global, event.point.x)
+        } else {
+            None
+        };
+        let pane = session.active_window().and_then(|window| {
+            let id = window.layout().pane_at(event.point)?;
+            let (terminal, rect) = window.layout().pane(id)?;
+            Some((id, Arc::clone(terminal), rect))
+        });
+        (options.flag("mouse"), status_window, pane)
+    };
+
+    let mut report = None;
+    match (event.action, drag) {
+        (MouseAction::Press, _) => {},
+        (_, Some(Drag::Report(id))) => {
+            if event.action == MouseAction::Release {
+                set_drag(None);
+            }
+            report = active_pane(context, id)
+                .and_then(|(terminal, rect)| Some((Arc::clone(&terminal), mouse_report(&terminal, rect, &event)?)));
+        },
+        (MouseAction::Drag, Some(Drag::Border(border))) => {
+            let moved = {
+                let mut session = context.session.lock().unwrap();
+                match session.active_window_mut() {
+                    Some(window) => window.move_border(border, event.point)?,
+                    None => None,
+                }
+            };
+            set_drag(moved.map(Drag::Border));
+            context.notifier.notify();
+        },
+        (MouseAction::Drag, Some(Drag::Select { pane, start, selecting })) => {
+            let (terminal, rect) = match active_pane(context, pane) {
+                Some(pane) => pane,
+                None => {
+                    set_drag(None);
+                    return Ok(true);
+                },
+            };
+            if !selecting {
+                execute_mouse(context, Command::CopyMode { page_up: false }).await;
+                if let Some(copy_mode) = terminal.copy_mode.lock().unwrap().as_mut() {
+                    copy_mode.move_to(start);
+                    copy_mode.apply(CopyAction::BeginSelection);
+                }
+                set_drag(Some(Drag::Select { pane, start, selecting: true }));
+            }
+            if let Some(copy_mode) = terminal.copy_mode.lock().unwrap().as_mut() {
+                copy_mode.move_to(rect.relative(event.point));
+            }
+            context.notifier.notify();
+        },
+        (MouseAction::Release, Some(Drag::Select { selecting: true, .. })) => {
+            set_drag(None);
+            execute_mouse(context, Command::CopyAction(CopyAction::CopySelectionAndCancel)).await;
+        },
+        (MouseAction::Release, _) => set_drag(None),
+        (MouseAction::Drag, _) => {},
+    }
+
+    if event.action == MouseAction::Press {
+        if let Some(index) = status_window {
+            if enabled && event.button == MouseButton::Left {
+                execute_mouse(context, Command::SelectWindow(index)).await;
+            }
+            return Ok(true);
+        }
+        let (id, terminal, rect) = match pane {
+            Some(pane) => pane,
+            None => {
+                // Between panes, the left button grabs the border.
+                if enabled && event.button == MouseButton::Left {
+                    set_drag(Some(Drag::Border(event.point)));
+                }
+                return Ok(true);
+            },
+        };
+        if enabled {
+            if let Some(window) = context.session.lock().unwrap().active_window_mut() {
+                window.select_pane(id);
+            }
+            context.notifier.notify();
+        }
+        if let Some(bytes) = mouse_report(&terminal, rect, &event) {
+            if !matches!(event.button, MouseButton::WheelUp | MouseButton::WheelDown) {
+                set_drag(Some(Drag::Report(id)));
+            }
+            report = Some((terminal, bytes));
+        } else if enabled {
+            let point = rect.relative(event.point);
+            match event.button {
+                MouseButton::Left => {
+                    let clicks = context.input.lock().unwrap().mouse_mut().click(event.point, Instant::now());
+                    let select = match clicks {
+                        1 => {
+                            if let Some(copy_mode) = terminal.copy_mode.lock().unwrap().as_mut() {
+                                copy_mode.apply(CopyAction::ClearSelection);
+                                copy_mode.move_to(point);
+                            }
+                            set_drag(Some(Drag::Select { pane: id, start: point, selecting: false }));
+                            None
+                        },
+                        2 => Some(CopyAction::SelectWord),
+                        _ => Some(CopyAction::SelectLine),
+                    };
+                    if let Some(select) = select {
+                        execute_mouse(context, Command::CopyMode { page_up: false }).await;
+                        if let Some(copy_mode) = terminal.copy_mode.lock().unwrap().as_mut() {
+                            copy_mode.move_to(point);
+                            copy_mode.apply(select);
+                        }
+                        execute_mouse(context, Command::CopyAction(CopyAction::CopySelection)).await;
+                    }
+                },
+                MouseButton::WheelUp => {
+                    execute_mouse(context, Command::CopyMode { page_up: false }).await;
+                    for _ in 0..WHEEL_LINES {
+                        execute_mouse(context, Command::CopyAction(CopyAction::ScrollUp)).await;
+                    }
+                },
+                MouseButton::WheelDown => {
+                    let bottom = {
+                        let mut copy_mode = terminal.copy_mode.lock().unwrap();
+                        match copy_mode.as_mut() {
+                            Some(copy_mode) => {
+                                for _ in 0..WHEEL_LINES {
+                                    copy_mode.apply(CopyAction::ScrollDown);
+                                }
+                                copy_mode.position().0 == 0 && !copy_mode.has_selection()
+                            },
+                            None => false,
+                        }
+                    };
+                    // Scrolling back to the bottom leaves copy mode.
+                    if bottom {
+                        execute_mouse(context, Command::CopyAction(CopyAction::Cancel)).await;
+                    }
+                    context.notifier.notify();
+                },
+                MouseButton::Middle | MouseButton::Right => {},
+            }
+        }
+    }
+
+    if let Some((terminal, bytes)) = report {
+        if bytes.is_empty() {
+            return Ok(true);
+        }
+        match terminal.pty_write(&bytes).await {
+            Ok(_) => {},
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => return Ok(false),
+            Err(e) => return Err(e).context("pty write"),
+        }
+    }
+    Ok(true)
+}
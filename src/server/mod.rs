@@ -0,0 +1,337 @@
+pub mod client;
+pub mod input;
+pub mod protocol;
+
+use {
+    std::{
+        env,
+        ffi::OsString,
+        fs::{
+            self,
+            DirBuilder,
+            Permissions,
+        },
+        io,
+        os::unix::fs::{
+            DirBuilderExt,
+            MetadataExt,
+            PermissionsExt,
+        },
+        path::{
+            Path,
+            PathBuf,
+        },
+        sync::{
+            atomic::AtomicUsize,
+            Arc,
+            Mutex,
+        },
+    },
+    anyhow::{
+        anyhow,
+        Context,
+        Result,
+    },
+    log::trace,
+    nix::unistd::getuid,
+    tokio::{
+        net::{
+            UnixListener,
+            UnixStream,
+        },
+        sync::Notify,
+        task,
+    },
+    crate::{
+        buffer::PasteBuffers,
+        command::{
+            Command,
+            CommandContext,
+            Target,
+        },
+        input::{
+            Input,
+            InputConfig,
+            KeyTables,
+        },
+        options::{
+            Options,
+            ScopedOptions,
+        },
+        screen::renderer::ScreenRendererNotifier,
+        session::{
+            window_size,
+            Session,
+            Spawner,
+        },
+        util::Point,
+    },
+};
+
+pub use self::client::Clients;
+
+/// Name of the server socket in the socket directory.
+const SOCKET_NAME: &str = "default";
+
+/// Get the directory of the sockets of user `uid`: `terman` in the
+/// runtime directory, or `/tmp/terman-UID` without one.
+fn socket_dir(runtime_dir: Option<OsString>, uid: u32) -> PathBuf {
+    match runtime_dir {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("terman"),
+        _ => PathBuf::from(format!("/tmp/terman-{}", uid)),
+    }
+}
+
+/// Get the path of the server socket.
+pub fn socket_path() -> PathBuf {
+    socket_dir(env::var_os("XDG_RUNTIME_DIR"), getuid().as_raw()).join(SOCKET_NAME)
+}
+
+/// Create directory `dir`, only accessible by the user, or check that it
+/// is if it exists. Anyone who can reach the socket controls the shells.
+fn secure_dir(dir: &Path) -> Result<()> {
+    match DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => {},
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {},
+        Err(e) => return Err(e).with_context(|| format!("create {}", dir.display())),
+    }
+    let metadata = fs::symlink_metadata(dir).with_context(|| format!("stat {}", dir.display()))?;
+    if !metadata.is_dir() || metadata.uid() != getuid().as_raw() || metadata.mode() & 0o077 != 0 {
+        return Err(anyhow!("{}: unsafe permissions", dir.display()));
+    }
+    Ok(())
+}
+
+/// Check that `name` can be used as a session name, which targets give
+/// before a `:`.
+fn check_name(name: String) -> Result<String> {
+    if name.is_empty() || name.contains(':') {
+        return Err(anyhow!("bad session name: {}", name));
+    }
+    Ok(name)
+}
+
+/// Owns the sessions and their terminals, which outlive the clients.
+pub struct Server {
+    options: Arc<Mutex<Options>>,
+    buffers: Arc<Mutex<PasteBuffers>>,
+    tables: Arc<Mutex<KeyTables>>,
+    /// Sessions in the order they were created.
+    sessions: Mutex<Vec<Arc<CommandContext>>>,
+    /// Command that loads the config file, run when the first session is
+    /// created.
+    config: Mutex<Option<Command>>,
+    next_client: AtomicUsize,
+    /// Notified when the last session ends.
+    exit: Notify,
+}
+
+impl Server {
+    pub fn new(config: Option<Command>) -> Self {
+        Self {
+            options: Arc::new(Mutex::new(Options::default())),
+            buffers: Arc::new(Mutex::new(PasteBuffers::default())),
+            tables: Arc::new(Mutex::new(KeyTables::default())),
+            sessions: Mutex::new(Vec::new()),
+            config: Mutex::new(config),
+            next_client: AtomicUsize::new(0),
+            exit: Notify::new(),
+        }
+    }
+
+    /// Create a session for a host of `size`, named `name` or with the
+    /// lowest unused number.
+    ///
+    /// Its first window runs `command`. Without one, it is only created if
+    /// the config file created none.
+    async fn new_session(
+        self: &Arc<Self>,
+        size: Point,
+        cell_size: Point,
+        name: Option<String>,
+        command: Option<String>,
+    ) -> Result<Arc<CommandContext>> {
+        let status = ScopedOptions::new(&self.options.lock().unwrap()).flag("status");
+        let exit = Arc::new(Notify::new());
+        // The name is taken as soon as it is chosen.
+        let context = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let names: Vec<String> = sessions.iter()
+                .map(|context| context.session.lock().unwrap().name().to_owned())
+                .collect();
+            let name = match name {
+                Some(name) if names.contains(&name) => return Err(anyhow!("duplicate session: {}", name)),
+                Some(name) => check_name(name)?,
+                None => (0..).map(|i: usize| i.to_string()).find(|name| !names.contains(name)).unwrap(),
+            };
+            let session = Arc::new(Mutex::new(Session::new(name, window_size(size, status))));
+            let notifier = ScreenRendererNotifier::default();
+            let spawner = Spawner::new(
+                Arc::clone(&session),
+                Arc::clone(&self.options),
+                notifier.clone(),
+                Arc::clone(&exit),
+                cell_size,
+            );
+            let context = Arc::new(CommandContext {
+                session,
+                spawner,
+                notifier,
+                input: Arc::new(Mutex::new(Input::new(InputConfig::default(), Arc::clone(&self.tables)))),
+                options: Arc::clone(&self.options),
+                buffers: Arc::clone(&self.buffers),
+                clients: Clients::default(),
+                client: None,
+                read_only: false,
+                printed: None,
+                target: Target::default(),
+                server: Arc::downgrade(self),
+            });
+            sessions.push(Arc::clone(&context));
+            context
+        };
+        context.apply_options();
+        let session = &context.session;
+
+        // The config file is loaded before the first window, so that its
+        // options apply to it. It may create windows itself.
+        let config = self.config.lock().unwrap().take();
+        if let Some(config) = config {
+            if let Err(e) = context.execute(&config).await {
+                session.lock().unwrap().set_message(Some(format!("{:#}", e)));
+            }
+        }
+        if command.is_some() || session.lock().unwrap().is_empty() {
+            let res = context.execute(&Command::NewWindow { name: None, cwd: None, command }).await;
+            if let Err(e) = res {
+                // Without a window, the session would never end.
+                if session.lock().unwrap().is_empty() {
+                    self.remove_session(&context);
+                }
+                return Err(e).context("create window");
+            }
+        }
+
+        let server = Arc::clone(self);
+        let ended = Arc::clone(&context);
+        task::spawn(async move {
+            exit.notified().await;
+            trace!("session {} ended", ended.session.lock().unwrap().name());
+            ended.clients.detach_all("exited".to_owned());
+            server.remove_session(&ended);
+        });
+        Ok(context)
+    }
+
+    /// Find session `name`, or the session that was created last.
+    fn find_session(&self, name: Option<&str>) -> Result<Arc<CommandContext>> {
+        let sessions = self.sessions.lock().unwrap();
+        match name {
+            Some(name) => sessions.iter()
+                .find(|context| context.session.lock().unwrap().name() == name)
+                .cloned()
+                .ok_or_else(|| anyhow!("no such session: {}", name)),
+            None => sessions.last().cloned().ok_or_else(|| anyhow!("no sessions")),
+        }
+    }
+
+    /// List the sessions, with their number of windows and whether clients
+    /// are attached.
+    pub fn list_sessions(&self) -> Vec<String> {
+        self.sessions.lock().unwrap().iter()
+            .map(|context| {
+                let session = context.session.lock().unwrap();
+                let attached = match session.client_count() {
+                    0 => "",
+                    _ => " (attached)",
+                };
+                format!("{}: {} windows{}", session.name(), session.windows().len(), attached)
+            })
+            .collect()
+    }
+
+    /// Rename `session`, unless another session has the name.
+    pub fn rename_session(&self, session: &Arc<Mutex<Session>>, name: String) -> Result<()> {
+        let name = check_name(name)?;
+        let sessions = self.sessions.lock().unwrap();
+        let taken = sessions.iter()
+            .filter(|context| !Arc::ptr_eq(&context.session, session))
+            .any(|context| context.session.lock().unwrap().name() == name);
+        if taken {
+            return Err(anyhow!("duplicate session: {}", name));
+        }
+        session.lock().unwrap().rename(name);
+        Ok(())
+    }
+
+    /// Kill every session. The server stops once they end.
+    pub fn kill_server(&self) {
+        for context in self.sessions.lock().unwrap().iter() {
+            context.session.lock().unwrap().kill();
+        }
+    }
+
+    /// Forget a session that ended, stopping the server after the last
+    /// one.
+    fn remove_session(&self, context: &Arc<CommandContext>) {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|session| !Arc::ptr_eq(session, context));
+        if sessions.is_empty() {
+            self.exit.notify();
+        }
+    }
+}
+
+/// Listen on the socket at `path` until the last session ends.
+///
+/// Fails if another server is listening on it already.
+pub async fn run(path: &Path, config: Option<Command>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        secure_dir(dir)?;
+    }
+    if UnixStream::connect(path).await.is_ok() {
+        return Err(anyhow!("a server is already running on {}", path.display()));
+    }
+    // The socket of a server that didn't exit cleanly is left behind.
+    let _ = fs::remove_file(path);
+    let mut listener = UnixListener::bind(path).with_context(|| format!("listen on {}", path.display()))?;
+    fs::set_permissions(path, Permissions::from_mode(0o600))
+        .with_context(|| format!("chmod {}", path.display()))?;
+    trace!("server listening on {}", path.display());
+
+    let server = Arc::new(Server::new(config));
+    loop {
+        tokio::select! {
+            res = listener.accept() => {
+                let (stream, _) = res.context("accept client")?;
+                let server = Arc::clone(&server);
+                task::spawn(async move {
+                    if let Err(e) = client::serve(&server, stream).await {
+                        trace!("client failed: {:#}", e);
+                    }
+                    // The first client may fail before creating a session.
+                    if server.sessions.lock().unwrap().is_empty() {
+                        server.exit.notify();
+                    }
+                });
+            },
+            _ = server.exit.notified() => break,
+        }
+    }
+    trace!("server exiting");
+    let _ = fs::remove_file(path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::socket_dir;
+
+    #[test]
+    fn test_socket_dir() {
+        assert_eq!(socket_dir(Some("/run/user/1000".into()), 1000), PathBuf::from("/run/user/1000/terman"));
+        assert_eq!(socket_dir(Some("".into()), 1000), PathBuf::from("/tmp/terman-1000"));
+        assert_eq!(socket_dir(None, 0), PathBuf::from("/tmp/terman-0"));
+    }
+}
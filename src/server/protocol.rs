@@ -0,0 +1,307 @@
+use {
+    std::convert::TryInto,
+    anyhow::{
+        anyhow,
+        Context,
+        Result,
+    },
+    tokio::io::{
+        AsyncRead,
+        AsyncReadExt,
+        AsyncWrite,
+        AsyncWriteExt,
+    },
+    crate::{
+        ansi::ColorDepth,
+        util::Point,
+    },
+};
+
+/// Largest payload accepted, so that a broken peer can't make us allocate
+/// without limit.
+const MAX_PAYLOAD: usize = 1 << 24;
+
+/// A message sent by a client to the server.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientMessage {
+    /// Describes the host of the client. It is sent first.
+    Identify {
+        size: Point,
+        cell_size: Point,
+        color_depth: ColorDepth,
+    },
+    /// Arguments of the command the client was run with, sent after
+    /// `Identify`.
+    Command(Vec<String>),
+    /// Bytes read from the host.
+    Input(Vec<u8>),
+    /// The host was resized.
+    Resize(Point),
+}
+
+/// A message sent by the server to a client.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    /// The client is attached to a session, its host now shows the output.
+    Attached,
+    /// Bytes to write to the host.
+    Output(Vec<u8>),
+    /// The client must exit with `code` after showing `message`. It is the
+    /// last message the server sends.
+    Exit {
+        code: u8,
+        message: String,
+    },
+}
+
+/// A message that is sent as a frame: its kind in a byte, then the length
+/// of its payload in 4 bytes and the payload.
+pub trait Message: Sized {
+    fn encode(&self) -> (u8, Vec<u8>);
+    fn decode(kind: u8, payload: &[u8]) -> Result<Self>;
+}
+
+impl Message for ClientMessage {
+    fn encode(&self) -> (u8, Vec<u8>) {
+        let mut payload = Payload::default();
+        let kind = match self {
+            Self::Identify { size, cell_size, color_depth } => {
+                payload.point(*size);
+                payload.point(*cell_size);
+                payload.0.push(match color_depth {
+                    ColorDepth::Ansi16 => 0,
+                    ColorDepth::Indexed256 => 1,
+                    ColorDepth::TrueColor => 2,
+                });
+                0
+            },
+            Self::Command(args) => {
+                for arg in args {
+                    payload.bytes(arg.as_bytes());
+                }
+                1
+            },
+            Self::Input(bytes) => {
+                payload.0.extend_from_slice(bytes);
+                2
+            },
+            Self::Resize(size) => {
+                payload.point(*size);
+                3
+            },
+        };
+        (kind, payload.0)
+    }
+
+    fn decode(kind: u8, payload: &[u8]) -> Result<Self> {
+        let mut reader = PayloadReader(payload);
+        let message = match kind {
+            0 => Self::Identify {
+                size: reader.point()?,
+                cell_size: reader.point()?,
+                color_depth: match reader.take(1)? {
+                    [0] => ColorDepth::Ansi16,
+                    [1] => ColorDepth::Indexed256,
+                    [2] => ColorDepth::TrueColor,
+                    _ => return Err(anyhow!("unknown color depth")),
+                },
+            },
+            1 => {
+                let mut args = Vec::new();
+                while !reader.0.is_empty() {
+                    args.push(reader.string()?);
+                }
+                Self::Command(args)
+            },
+            2 => Self::Input(reader.rest()),
+            3 => Self::Resize(reader.point()?),
+            kind => return Err(anyhow!("unknown client message: {}", kind)),
+        };
+        reader.finish()?;
+        Ok(message)
+    }
+}
+
+impl Message for ServerMessage {
+    fn encode(&self) -> (u8, Vec<u8>) {
+        let mut payload = Payload::default();
+        let kind = match self {
+            Self::Attached => 0,
+            Self::Output(bytes) => {
+                payload.0.extend_from_slice(bytes);
+                1
+            },
+            Self::Exit { code, message } => {
+                payload.0.push(*code);
+                payload.0.extend_from_slice(message.as_bytes());
+                2
+            },
+        };
+        (kind, payload.0)
+    }
+
+    fn decode(kind: u8, payload: &[u8]) -> Result<Self> {
+        let mut reader = PayloadReader(payload);
+        let message = match kind {
+            0 => Self::Attached,
+            1 => Self::Output(reader.rest()),
+            2 => Self::Exit {
+                code: reader.take(1)?[0],
+                message: String::from_utf8(reader.rest()).context("exit message")?,
+            },
+            kind => return Err(anyhow!("unknown server message: {}", kind)),
+        };
+        reader.finish()?;
+        Ok(message)
+    }
+}
+
+/// Read the next message, or `None` if the peer closed the connection.
+pub async fn read_message<M, R>(reader: &mut R) -> Result<Option<M>>
+    where M: Message, R: AsyncRead + Unpin
+{
+    let mut header = [0u8; 5];
+    if reader.read(&mut header[..1]).await.context("read message")? == 0 {
+        return Ok(None);
+    }
+    reader.read_exact(&mut header[1..]).await.context("read message")?;
+    let len = u32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+    if len > MAX_PAYLOAD {
+        return Err(anyhow!("message too long: {} bytes", len));
+    }
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload).await.context("read message")?;
+    M::decode(header[0], &payload).map(Some)
+}
+
+pub async fn write_message<M, W>(writer: &mut W, message: &M) -> Result<()>
+    where M: Message, W: AsyncWrite + Unpin
+{
+    writer.write_all(&frame(message)).await.context("write message")
+}
+
+/// Get the frame of a message.
+fn frame<M: Message>(message: &M) -> Vec<u8> {
+    let (kind, payload) = message.encode();
+    let mut frame = Vec::with_capacity(payload.len() + 5);
+    frame.push(kind);
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend(payload);
+    frame
+}
+
+#[derive(Default)]
+struct Payload(Vec<u8>);
+
+impl Payload {
+    fn number(&mut self, value: usize) {
+        self.0.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+
+    fn point(&mut self, point: Point) {
+        self.number(point.x);
+        self.number(point.y);
+    }
+
+    /// Add bytes after their length.
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.number(bytes.len());
+        self.0.extend_from_slice(bytes);
+    }
+}
+
+struct PayloadReader<'a>(&'a [u8]);
+
+impl<'a> PayloadReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(anyhow!("message too short"));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as usize)
+    }
+
+    fn point(&mut self) -> Result<Point> {
+        Ok(Point::new(self.number()?, self.number()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.number()?;
+        String::from_utf8(self.take(len)?.to_vec()).context("invalid string")
+    }
+
+    fn rest(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.0).to_vec()
+    }
+
+    /// Fail if the payload has bytes left.
+    fn finish(&self) -> Result<()> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("message too long"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        futures::executor::block_on,
+        crate::{
+            ansi::ColorDepth,
+            util::point::P,
+        },
+    };
+    use super::{
+        frame,
+        read_message,
+        ClientMessage,
+        Message,
+        ServerMessage,
+    };
+
+    fn round_trip<M: Message + Clone + std::fmt::Debug + PartialEq>(messages: &[M]) {
+        let bytes: Vec<u8> = messages.iter().flat_map(frame).collect();
+        let mut reader = &bytes[..];
+        for message in messages {
+            assert_eq!(block_on(read_message::<M, _>(&mut reader)).unwrap().as_ref(), Some(message));
+        }
+        assert_eq!(block_on(read_message::<M, _>(&mut reader)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(&[
+            ClientMessage::Identify {
+                size: P(80, 24),
+                cell_size: P(9, 18),
+                color_depth: ColorDepth::TrueColor,
+            },
+            ClientMessage::Command(vec!["new-session".to_owned(), "-d".to_owned(), "".to_owned()]),
+            ClientMessage::Input(b"\x1b[A".to_vec()),
+            ClientMessage::Resize(P(100, 30)),
+        ]);
+        round_trip(&[
+            ServerMessage::Attached,
+            ServerMessage::Output(b"\x1b[2J".to_vec()),
+            ServerMessage::Exit { code: 1, message: "no sessions".to_owned() },
+        ]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(ClientMessage::decode(3, &[0; 7]).is_err());
+        assert!(ClientMessage::decode(3, &[0; 9]).is_err());
+        assert!(ClientMessage::decode(9, &[]).is_err());
+        assert!(ServerMessage::decode(2, &[]).is_err());
+        // The frame ends before its payload.
+        let bytes = [1, 0, 0, 0, 4, b'a'];
+        assert!(block_on(read_message::<ServerMessage, _>(&mut &bytes[..])).is_err());
+    }
+}
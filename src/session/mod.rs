@@ -0,0 +1,756 @@
+pub mod spawner;
+pub mod window;
+
+use {
+    std::{
+        str::FromStr,
+        sync::Arc,
+    },
+    anyhow::{
+        anyhow,
+        Result,
+    },
+    log::trace,
+    crate::{
+        input::prompt::Prompt,
+        options::{
+            Level,
+            Options,
+            ScopedOptions,
+        },
+        term::Terminal,
+        util::Point,
+    },
+};
+
+pub use self::{
+    spawner::Spawner,
+    window::Window,
+};
+
+/// Get the size of the windows of a session shown on a host of `size`,
+/// which gives a line to the status line if it is on.
+pub fn window_size(size: Point, status: bool) -> Point {
+    let lines = if status { 2 } else { 1 };
+    Point::new(size.x.max(1), size.y.max(lines) - (lines - 1))
+}
+
+/// How the size of the windows follows the clients attached to the
+/// session, from the `window-size` option.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SizePolicy {
+    /// Fit the smallest client, in each direction.
+    Smallest,
+    /// Fill the largest client, in each direction.
+    Largest,
+    /// Fit the client that was used last.
+    Latest,
+    /// Keep the size set with `resize-window`.
+    Manual,
+}
+
+impl SizePolicy {
+    /// Get the size of the windows for clients whose hosts can show
+    /// windows of `sizes`, of which `latest` was used last.
+    ///
+    /// Returns `None` if the size doesn't follow the clients, or there are
+    /// none.
+    pub fn size(self, sizes: &[Point], latest: Option<Point>) -> Option<Point> {
+        let first = *sizes.first()?;
+        match self {
+            Self::Smallest => Some(sizes.iter().fold(first, |size, other| {
+                Point::new(size.x.min(other.x), size.y.min(other.y))
+            })),
+            Self::Largest => Some(sizes.iter().fold(first, |size, other| {
+                Point::new(size.x.max(other.x), size.y.max(other.y))
+            })),
+            Self::Latest => latest,
+            Self::Manual => None,
+        }
+    }
+}
+
+impl FromStr for SizePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "smallest" => Ok(Self::Smallest),
+            "largest" => Ok(Self::Largest),
+            "latest" => Ok(Self::Latest),
+            "manual" => Ok(Self::Manual),
+            _ => Err(anyhow!("unknown window size: {}", s)),
+        }
+    }
+}
+
+/// A client attached to the session, which shows one of its windows.
+struct SessionClient {
+    id: usize,
+    /// Size of the host of the client.
+    size: Point,
+    /// Index of the window shown by the client.
+    window: usize,
+    /// Whether the client only watches the session, so never becomes the
+    /// current one.
+    read_only: bool,
+}
+
+/// Ordered list of windows, of which each attached client shows one.
+///
+/// The active window is the one shown by the client that was used last,
+/// which commands act on.
+pub struct Session {
+    name: String,
+    /// Windows sorted by index.
+    windows: Vec<Window>,
+    /// Position of the active window in `windows`.
+    active: usize,
+    /// Size of the area covered by the windows.
+    size: Point,
+    /// The prompt, when it is open.
+    prompt: Option<Prompt>,
+    /// Message shown on the status line until the next key.
+    message: Option<String>,
+    /// Options set for the session.
+    options: Options,
+    /// Clients attached to the session.
+    clients: Vec<SessionClient>,
+    /// Client that was used last, whose window follows the active one.
+    current: Option<usize>,
+}
+
+impl Session {
+    pub fn new(name: String, size: Point) -> Self {
+        Self {
+            name,
+            windows: Vec::new(),
+            active: 0,
+            size,
+            prompt: None,
+            message: None,
+            options: Options::default(),
+            clients: Vec::new(),
+            current: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rename(&mut self, name: String) {
+        trace!("[session] rename: name={:?}", name);
+        self.name = name;
+    }
+
+    /// Get the size of the area covered by the windows.
+    pub fn size(&self) -> Point {
+        self.size
+    }
+
+    /// Returns whether the last window was closed.
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Get the options set for the session.
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    /// Get the options in effect for the session, which override the
+    /// `global` ones.
+    pub fn scoped_options<'a>(&'a self, global: &'a Options) -> ScopedOptions<'a> {
+        ScopedOptions::new(global).with(Level::Session, &self.options)
+    }
+
+    /// Apply the options to the screens of every pane.
+    pub fn apply_options(&self, global: &Options) {
+        for window in self.windows.iter() {
+            for (id, terminal, _) in window.layout().panes() {
+                let options = window.pane_scoped_options(self.scoped_options(global), id);
+                terminal.screen.lock().unwrap().set_history_limit(options.number("history-limit"));
+            }
+        }
+    }
+
+    /// Get the windows, sorted by index.
+    pub fn windows(&self) -> &[Window] {
+        &self.windows
+    }
+
+    pub fn prompt(&self) -> Option<&Prompt> {
+        self.prompt.as_ref()
+    }
+
+    pub fn prompt_mut(&mut self) -> Option<&mut Prompt> {
+        self.prompt.as_mut()
+    }
+
+    /// Open `prompt`, or close the prompt.
+    pub fn set_prompt(&mut self, prompt: Option<Prompt>) {
+        self.prompt = prompt;
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn set_message(&mut self, message: Option<String>) {
+        self.message = message;
+    }
+
+    /// Returns whether the pane of `terminal` is kept once its process
+    /// exits.
+    pub fn remain_on_exit(&self, terminal: &Arc<Terminal>, global: &Options) -> bool {
+        self.windows.iter()
+            .find_map(|window| {
+                let id = window.pane_of(terminal)?;
+                Some(window.pane_scoped_options(self.scoped_options(global), id).flag("remain-on-exit"))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Get window `index`.
+    pub fn window(&self, index: usize) -> Result<&Window> {
+        let position = self.position(index)?;
+        Ok(&self.windows[position])
+    }
+
+    pub fn window_mut(&mut self, index: usize) -> Result<&mut Window> {
+        let position = self.position(index)?;
+        Ok(&mut self.windows[position])
+    }
+
+    pub fn active_window(&self) -> Option<&Window> {
+        self.windows.get(self.active)
+    }
+
+    pub fn active_window_mut(&mut self) -> Option<&mut Window> {
+        self.windows.get_mut(self.active)
+    }
+
+    /// Attach client `id`, with a host of `size`, showing the active
+    /// window. Unless it is `read_only`, it becomes the current client.
+    pub fn attach_client(&mut self, id: usize, size: Point, read_only: bool) {
+        trace!("[session] attach_client: id={:?}, size={:?}, read_only={:?}", id, size, read_only);
+        let window = self.active_window().map_or(0, Window::index);
+        self.clients.push(SessionClient { id, size, window, read_only });
+        if !read_only {
+            self.current = Some(id);
+        }
+    }
+
+    pub fn detach_client(&mut self, id: usize) {
+        trace!("[session] detach_client: id={:?}", id);
+        self.clients.retain(|client| client.id != id);
+        if self.current == Some(id) {
+            self.current = None;
+            let last = self.clients.iter().rev().find(|client| !client.read_only);
+            if let Some(last) = last.map(|client| client.id) {
+                self.focus_client(last);
+            }
+        }
+    }
+
+    /// Get the number of clients attached.
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    fn client(&self, id: usize) -> Option<&SessionClient> {
+        self.clients.iter().find(|client| client.id == id)
+    }
+
+    /// Get the size of the host of client `id`.
+    pub fn client_size(&self, id: usize) -> Option<Point> {
+        self.client(id).map(|client| client.size)
+    }
+
+    pub fn resize_client(&mut self, id: usize, size: Point) {
+        if let Some(client) = self.clients.iter_mut().find(|client| client.id == id) {
+            client.size = size;
+        }
+    }
+
+    /// Get the window shown by client `id`, or the active window.
+    pub fn client_window(&self, id: usize) -> Option<&Window> {
+        self.client(id)
+            .and_then(|client| self.position(client.window).ok())
+            .and_then(|position| self.windows.get(position))
+            .or_else(|| self.active_window())
+    }
+
+    /// Make client `id` the one that was used last, activating the window
+    /// it shows. Read-only clients never become the current one.
+    pub fn focus_client(&mut self, id: usize) {
+        let window = match self.client(id) {
+            Some(client) if !client.read_only => client.window,
+            _ => return,
+        };
+        self.current = Some(id);
+        if let Ok(position) = self.position(window) {
+            if position != self.active {
+                self.activate(position);
+            }
+        }
+    }
+
+    /// Make client `id` show window `index` without activating it, as
+    /// clients that only watch the session do.
+    pub fn show_window(&mut self, id: usize, index: usize) -> Result<()> {
+        self.position(index)?;
+        if let Some(client) = self.clients.iter_mut().find(|client| client.id == id) {
+            client.window = index;
+        }
+        Ok(())
+    }
+
+    /// Make client `id` show the window after the one it shows, or before
+    /// it, wrapping around, without activating it.
+    pub fn cycle_window(&mut self, id: usize, forward: bool) -> Result<()> {
+        let shown = self.client_window(id).map(Window::index).ok_or_else(|| anyhow!("no window"))?;
+        let position = self.position(shown)?;
+        let len = self.windows.len();
+        let position = if forward {
+            (position + 1) % len
+        } else {
+            (position + len - 1) % len
+        };
+        let index = self.windows[position].index();
+        self.show_window(id, index)
+    }
+
+    /// Resize the windows to fit the attached clients, as the
+    /// `window-size` option says.
+    pub fn fit_clients(&mut self, global: &Options) -> Result<()> {
+        let options = self.scoped_options(global);
+        let status = options.flag("status");
+        let policy: SizePolicy = options.string("window-size").parse()?;
+        let sizes: Vec<Point> = self.clients.iter()
+            .map(|client| window_size(client.size, status))
+            .collect();
+        let latest = self.current
+            .and_then(|id| self.client(id))
+            .map(|client| window_size(client.size, status));
+        match policy.size(&sizes, latest) {
+            Some(size) if size != self.size => self.resize(size),
+            _ => Ok(()),
+        }
+    }
+
+    /// Make the current client show the active window, and the clients
+    /// whose window is gone show it too.
+    fn update_clients(&mut self) {
+        let active = match self.active_window() {
+            Some(window) => window.index(),
+            None => return,
+        };
+        let indexes: Vec<usize> = self.windows.iter().map(Window::index).collect();
+        let current = self.current;
+        for client in self.clients.iter_mut() {
+            if Some(client.id) == current || !indexes.contains(&client.window) {
+                client.window = active;
+            }
+        }
+    }
+
+    fn position(&self, index: usize) -> Result<usize> {
+        self.windows.iter()
+            .position(|window| window.index() == index)
+            .ok_or_else(|| anyhow!("no such window: {}", index))
+    }
+
+    /// Make the window at position `position` the active one.
+    fn activate(&mut self, position: usize) {
+        if let Some(window) = self.windows.get(self.active) {
+            window.set_active(false);
+        }
+        self.active = position;
+        if let Some(window) = self.windows.get(self.active) {
+            window.set_active(true);
+        }
+        self.update_clients();
+    }
+
+    /// Create a window with the lowest unused index and select it.
+    ///
+    /// `spawn` is called with the size of the window to create its first
+    /// terminal.
+    pub fn new_window<F>(&mut self, name: String, spawn: F) -> Result<usize>
+        where F: FnOnce(Point) -> Result<Arc<Terminal>>
+    {
+        let index = (0..).find(|i| self.position(*i).is_err()).unwrap();
+        trace!("[session] new_window: index={:?}, name={:?}", index, name);
+        let window = Window::new(index, name, spawn(self.size)?, self.size);
+        let position = self.windows.iter()
+            .position(|window| window.index() > index)
+            .unwrap_or(self.windows.len());
+        self.windows.insert(position, window);
+        if position <= self.active && self.windows.len() > 1 {
+            self.active += 1;
+        }
+        self.activate(position);
+        Ok(index)
+    }
+
+    /// Kill every terminal of window `index` and remove it.
+    pub fn kill_window(&mut self, index: usize) -> Result<()> {
+        trace!("[session] kill_window: index={:?}", index);
+        let position = self.position(index)?;
+        let window = self.windows.remove(position);
+        for terminal in window.terminals() {
+            if let Err(e) = terminal.kill() {
+                trace!("[session] failed to kill terminal: {}", e);
+            }
+        }
+        self.removed(position);
+        Ok(())
+    }
+
+    /// Kill every window. The session ends once their processes exit.
+    pub fn kill(&mut self) {
+        trace!("[session] kill");
+        let indexes: Vec<usize> = self.windows.iter().map(Window::index).collect();
+        for index in indexes {
+            // The index was just listed.
+            let _ = self.kill_window(index);
+        }
+    }
+
+    /// Update the active window after removing the window at `position`.
+    fn removed(&mut self, position: usize) {
+        if self.windows.is_empty() {
+            self.active = 0;
+        } else if position < self.active {
+            self.active -= 1;
+        } else if position == self.active {
+            // Select the window that took its place, or the new last one.
+            let position = position.min(self.windows.len() - 1);
+            self.active = position;
+            self.windows[position].set_active(true);
+        }
+        self.update_clients();
+    }
+
+    pub fn rename_window(&mut self, index: usize, name: String) -> Result<()> {
+        trace!("[session] rename_window: index={:?}, name={:?}", index, name);
+        let position = self.position(index)?;
+        self.windows[position].set_name(name);
+        Ok(())
+    }
+
+    /// Change the index of window `index` to `new_index`, which must be
+    /// unused.
+    pub fn move_window(&mut self, index: usize, new_index: usize) -> Result<()> {
+        trace!("[session] move_window: index={:?}, new_index={:?}", index, new_index);
+        let position = self.position(index)?;
+        if index == new_index {
+            return Ok(());
+        }
+        if self.position(new_index).is_ok() {
+            return Err(anyhow!("index in use: {}", new_index));
+        }
+        let active = self.windows[self.active].index();
+        let mut window = self.windows.remove(position);
+        window.set_index(new_index);
+        let position = self.windows.iter()
+            .position(|window| window.index() > new_index)
+            .unwrap_or(self.windows.len());
+        self.windows.insert(position, window);
+        let active = if active == index { new_index } else { active };
+        self.active = self.position(active)?;
+        for client in self.clients.iter_mut().filter(|client| client.window == index) {
+            client.window = new_index;
+        }
+        Ok(())
+    }
+
+    /// Select the window after the active one, wrapping around.
+    pub fn next_window(&mut self) {
+        if !self.windows.is_empty() {
+            self.activate((self.active + 1) % self.windows.len());
+        }
+    }
+
+    /// Select the window before the active one, wrapping around.
+    pub fn previous_window(&mut self) {
+        if !self.windows.is_empty() {
+            let len = self.windows.len();
+            self.activate((self.active + len - 1) % len);
+        }
+    }
+
+    pub fn select_window(&mut self, index: usize) -> Result<()> {
+        trace!("[session] select_window: index={:?}", index);
+        let position = self.position(index)?;
+        self.activate(position);
+        Ok(())
+    }
+
+    /// Remove the pane of a terminal whose process exited, and its window
+    /// if it was the last pane.
+    ///
+    /// Returns whether the terminal was found.
+    pub fn remove_terminal(&mut self, terminal: &Arc<Terminal>) -> Result<bool> {
+        let position = self.windows.iter()
+            .position(|window| window.pane_of(terminal).is_some());
+        let position = match position {
+            Some(position) => position,
+            None => return Ok(false),
+        };
+        let window = &mut self.windows[position];
+        let id = window.pane_of(terminal).unwrap();
+        window.close_pane(id)?;
+        if window.is_empty() {
+            self.windows.remove(position);
+            self.removed(position);
+        } else if position == self.active {
+            window.set_active(true);
+        }
+        Ok(true)
+    }
+
+    /// Resize the area covered by the windows, resizing every pane.
+    pub fn resize(&mut self, size: Point) -> Result<()> {
+        self.size = size;
+        let mut result = Ok(());
+        for window in self.windows.iter_mut() {
+            if let Err(e) = window.resize(size) {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::sync::{
+            atomic::Ordering,
+            Arc,
+        },
+        anyhow::Result,
+        tokio::process::Command,
+        crate::{
+            layout::Direction,
+            options::Options,
+            term::Terminal,
+            util::{
+                point::P,
+                Point,
+            },
+        },
+    };
+    use super::{
+        window_size,
+        Session,
+        SizePolicy,
+    };
+
+    fn spawn(size: Point) -> Result<Arc<Terminal>> {
+        let (terminal, _) = Terminal::spawn(Command::new("cat"), size)?;
+        Ok(Arc::new(terminal))
+    }
+
+    fn indexes(session: &Session) -> Vec<usize> {
+        session.windows().iter().map(|window| window.index()).collect()
+    }
+
+    fn active(session: &Session) -> usize {
+        session.active_window().unwrap().index()
+    }
+
+    #[tokio::test]
+    async fn test_window_indexes() {
+        let mut session = Session::new("test".to_owned(), P(80, 24));
+        for _ in 0..3 {
+            session.new_window("cat".to_owned(), spawn).unwrap();
+        }
+        assert_eq!(indexes(&session), vec![0, 1, 2]);
+        assert_eq!(active(&session), 2);
+
+        session.move_window(0, 5).unwrap();
+        assert_eq!(indexes(&session), vec![1, 2, 5]);
+        assert_eq!(active(&session), 2);
+        assert!(session.move_window(1, 2).is_err());
+        assert!(session.move_window(3, 4).is_err());
+
+        assert_eq!(session.new_window("cat".to_owned(), spawn).unwrap(), 0);
+        assert_eq!(indexes(&session), vec![0, 1, 2, 5]);
+        assert_eq!(active(&session), 0);
+
+        session.rename_window(5, "renamed".to_owned()).unwrap();
+        assert_eq!(session.windows()[3].name(), "renamed");
+    }
+
+    #[tokio::test]
+    async fn test_select_window() {
+        let mut session = Session::new("test".to_owned(), P(80, 24));
+        for _ in 0..3 {
+            session.new_window("cat".to_owned(), spawn).unwrap();
+        }
+        session.next_window();
+        assert_eq!(active(&session), 0);
+        session.previous_window();
+        assert_eq!(active(&session), 2);
+        session.select_window(1).unwrap();
+        assert_eq!(active(&session), 1);
+        assert!(session.select_window(7).is_err());
+
+        session.kill_window(1).unwrap();
+        assert_eq!(indexes(&session), vec![0, 2]);
+        assert_eq!(active(&session), 2);
+        session.kill_window(0).unwrap();
+        assert_eq!(active(&session), 2);
+        session.kill_window(2).unwrap();
+        assert!(session.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_window_flags() {
+        let mut session = Session::new("test".to_owned(), P(80, 24));
+        session.new_window("cat".to_owned(), spawn).unwrap();
+        session.new_window("cat".to_owned(), spawn).unwrap();
+        let background = session.windows()[0].active_terminal().unwrap();
+        assert!(!background.focused.load(Ordering::SeqCst));
+
+        background.ring_bell();
+        background.activity.store(true, Ordering::SeqCst);
+        assert!(session.windows()[0].has_bell());
+        assert!(session.windows()[0].has_activity());
+
+        session.select_window(0).unwrap();
+        assert!(background.focused.load(Ordering::SeqCst));
+        assert!(!session.windows()[0].has_bell());
+        assert!(!session.windows()[0].has_activity());
+
+        // The flags of the panes that aren't focused stay.
+        let window = session.active_window_mut().unwrap();
+        let first = window.active_pane();
+        window.split(first, Direction::Horizontal, spawn).unwrap();
+        background.ring_bell();
+        session.select_window(1).unwrap();
+        session.select_window(0).unwrap();
+        assert!(session.windows()[0].has_bell());
+        session.active_window_mut().unwrap().select_pane(first);
+        assert!(!session.windows()[0].has_bell());
+    }
+
+    #[test]
+    fn test_window_size() {
+        assert_eq!(window_size(P(80, 24), true), P(80, 23));
+        assert_eq!(window_size(P(80, 24), false), P(80, 24));
+        assert_eq!(window_size(P(0, 1), true), P(1, 1));
+    }
+
+    #[test]
+    fn test_size_policy() {
+        let sizes = [P(80, 24), P(120, 20)];
+        assert_eq!(SizePolicy::Smallest.size(&sizes, Some(P(120, 20))), Some(P(80, 20)));
+        assert_eq!(SizePolicy::Largest.size(&sizes, Some(P(120, 20))), Some(P(120, 24)));
+        assert_eq!(SizePolicy::Latest.size(&sizes, Some(P(120, 20))), Some(P(120, 20)));
+        assert_eq!(SizePolicy::Manual.size(&sizes, Some(P(120, 20))), None);
+        assert_eq!(SizePolicy::Smallest.size(&[], None), None);
+        assert_eq!("largest".parse::<SizePolicy>().unwrap(), SizePolicy::Largest);
+        assert!("biggest".parse::<SizePolicy>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_clients() {
+        let mut session = Session::new("test".to_owned(), P(80, 23));
+        session.new_window("cat".to_owned(), spawn).unwrap();
+        session.attach_client(1, P(80, 24), false);
+        session.new_window("cat".to_owned(), spawn).unwrap();
+        session.attach_client(2, P(100, 30), false);
+        session.select_window(0).unwrap();
+        assert_eq!(session.client_window(1).unwrap().index(), 1);
+        assert_eq!(session.client_window(2).unwrap().index(), 0);
+
+        // Using a client brings back its window.
+        session.focus_client(1);
+        assert_eq!(active(&session), 1);
+        session.kill_window(1).unwrap();
+        assert_eq!(session.client_window(1).unwrap().index(), 0);
+
+        let mut global = Options::default();
+        session.fit_clients(&global).unwrap();
+        assert_eq!(session.size(), P(80, 23));
+        global.set("window-size", "largest").unwrap();
+        session.fit_clients(&global).unwrap();
+        assert_eq!(session.size(), P(100, 29));
+        session.detach_client(2);
+        session.fit_clients(&global).unwrap();
+        assert_eq!(session.size(), P(80, 23));
+
+        // Watching clients change their window only.
+        session.new_window("cat".to_owned(), spawn).unwrap();
+        session.attach_client(3, P(80, 24), true);
+        session.focus_client(1);
+        session.cycle_window(3, true).unwrap();
+        assert_eq!(session.client_window(3).unwrap().index(), 0);
+        assert_eq!(session.client_window(1).unwrap().index(), 1);
+        assert_eq!(active(&session), 1);
+        session.show_window(3, 1).unwrap();
+        assert_eq!(session.client_window(3).unwrap().index(), 1);
+        assert!(session.show_window(3, 5).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_only_client() {
+        let mut session = Session::new("test".to_owned(), P(80, 23));
+        session.new_window("cat".to_owned(), spawn).unwrap();
+        let mut global = Options::default();
+        global.set("window-size", "latest").unwrap();
+        session.attach_client(1, P(80, 24), false);
+        session.fit_clients(&global).unwrap();
+        assert_eq!(session.size(), P(80, 23));
+
+        // A watching client doesn't resize the windows, whether it
+        // attaches, resizes or is used.
+        session.attach_client(2, P(100, 30), true);
+        session.fit_clients(&global).unwrap();
+        assert_eq!(session.size(), P(80, 23));
+        session.resize_client(2, P(120, 40));
+        session.focus_client(2);
+        session.fit_clients(&global).unwrap();
+        assert_eq!(session.size(), P(80, 23));
+
+        // Nor does it take over when the writer detaches.
+        session.attach_client(3, P(90, 25), false);
+        session.fit_clients(&global).unwrap();
+        assert_eq!(session.size(), P(90, 24));
+        session.detach_client(3);
+        session.fit_clients(&global).unwrap();
+        assert_eq!(session.size(), P(80, 23));
+    }
+
+    #[tokio::test]
+    async fn test_remove_terminal() {
+        let mut session = Session::new("test".to_owned(), P(80, 24));
+        session.new_window("cat".to_owned(), spawn).unwrap();
+        session.new_window("cat".to_owned(), spawn).unwrap();
+        let terminal = session.active_window().unwrap().active_terminal().unwrap();
+        assert!(session.remove_terminal(&terminal).unwrap());
+        assert!(!session.remove_terminal(&terminal).unwrap());
+        assert_eq!(indexes(&session), vec![0]);
+        assert_eq!(active(&session), 0);
+    }
+
+    #[tokio::test]
+    async fn test_remain_on_exit() {
+        let mut session = Session::new("test".to_owned(), P(80, 24));
+        session.new_window("cat".to_owned(), spawn).unwrap();
+        let terminal = session.active_window().unwrap().active_terminal().unwrap();
+        let mut global = Options::default();
+        assert!(!session.remain_on_exit(&terminal, &global));
+        global.set("remain-on-exit", "on").unwrap();
+        assert!(session.remain_on_exit(&terminal, &global));
+        let window = session.active_window_mut().unwrap();
+        let id = window.active_pane();
+        window.pane_options_mut(id).set("remain-on-exit", "off").unwrap();
+        assert!(!session.remain_on_exit(&terminal, &global));
+    }
+}
@@ -0,0 +1,124 @@
+use {
+    std::{
+        path::Path,
+        sync::{
+            Arc,
+            Mutex,
+        },
+    },
+    anyhow::{
+        Context,
+        Result,
+    },
+    log::trace,
+    tokio::{
+        process::Command,
+        sync::Notify,
+        task,
+    },
+    crate::{
+        ansi::Processor,
+        options::Options,
+        screen::renderer::ScreenRendererNotifier,
+        term::{
+            Terminal,
+            terminal_task,
+        },
+        util::Point,
+    },
+    super::Session,
+};
+
+/// Get the default name of the windows running `shell`, the name of the
+/// shell.
+pub fn window_name(shell: &str) -> String {
+    Path::new(shell)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| shell.to_owned())
+}
+
+/// What a pane kept after its process exited shows.
+const DEAD_MESSAGE: &[u8] = b"\r\nPane is dead";
+
+/// Creates the terminals of a session.
+///
+/// Each terminal runs in its own task, which removes its pane from the
+/// session when the process exits, unless `remain-on-exit` is on.
+#[derive(Clone)]
+pub struct Spawner {
+    session: Arc<Mutex<Session>>,
+    options: Arc<Mutex<Options>>,
+    notifier: ScreenRendererNotifier,
+    /// Notified when the last window of the session is closed.
+    exit: Arc<Notify>,
+    cell_size: Point,
+}
+
+impl Spawner {
+    pub fn new(
+        session: Arc<Mutex<Session>>,
+        options: Arc<Mutex<Options>>,
+        notifier: ScreenRendererNotifier,
+        exit: Arc<Notify>,
+        cell_size: Point,
+    ) -> Self {
+        Self {
+            session,
+            options,
+            notifier,
+            exit,
+            cell_size,
+        }
+    }
+
+    /// Spawn `shell` in a new terminal of `size`, starting in directory
+    /// `cwd` instead of the current one. The shell runs `shell_command`
+    /// if given, and exits with it.
+    ///
+    /// The options of the new pane are applied once it is added to the
+    /// session.
+    pub fn spawn(
+        &self,
+        size: Point,
+        shell: &str,
+        cwd: Option<&str>,
+        shell_command: Option<&str>,
+    ) -> Result<Arc<Terminal>> {
+        let mut command = Command::new(shell);
+        if let Some(shell_command) = shell_command {
+            command.arg("-c").arg(shell_command);
+        }
+        if let Some(cwd) = cwd {
+            command.current_dir(cwd);
+        }
+        let (terminal, process) = Terminal::spawn(command, size).context("create terminal")?;
+        terminal.screen.lock().unwrap().set_cell_size(self.cell_size);
+        let terminal = Arc::new(terminal);
+        let spawner = self.clone();
+        let task_terminal = Arc::clone(&terminal);
+        task::spawn(async move {
+            let notifier = spawner.notifier.clone();
+            let status = terminal_task(&task_terminal, process, notifier).await;
+            trace!("terminal task finished: {:?}", status);
+            let mut session = spawner.session.lock().unwrap();
+            if session.remain_on_exit(&task_terminal, &spawner.options.lock().unwrap()) {
+                let mut screen = task_terminal.screen.lock().unwrap();
+                Processor::default().advance(DEAD_MESSAGE, &mut *screen, &mut Vec::new());
+            } else if let Err(e) = session.remove_terminal(&task_terminal) {
+                trace!("failed to remove terminal: {}", e);
+            }
+            spawner.removed(&session);
+            drop(session);
+            spawner.notifier.notify();
+        });
+        Ok(terminal)
+    }
+
+    /// Notify that the session ended if its last window was closed.
+    pub fn removed(&self, session: &Session) {
+        if session.is_empty() {
+            self.exit.notify();
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use {
+    std::{
+        collections::HashMap,
+        sync::Arc,
+    },
+    anyhow::Result,
+    crate::{
+        layout::{
+            Direction,
+            Layout,
+            PaneId,
+            Side,
+        },
+        options::{
+            Level,
+            Options,
+            ScopedOptions,
+        },
+        term::Terminal,
+        util::Point,
+    },
+};
+
+/// A window of a session, made of panes laid out by a layout tree.
+pub struct Window {
+    index: usize,
+    name: String,
+    layout: Layout<Arc<Terminal>>,
+    active_pane: PaneId,
+    /// Options set for the window.
+    options: Options,
+    /// Options set for single panes.
+    pane_options: HashMap<PaneId, Options>,
+}
+
+impl Window {
+    pub fn new(index: usize, name: String, terminal: Arc<Terminal>, size: Point) -> Self {
+        Self {
+            index,
+            name,
+            layout: Layout::new(size, terminal),
+            active_pane: 0,
+            options: Options::default(),
+            pane_options: HashMap::new(),
+        }
+    }
+
+    /// Get the index by which the window is selected.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub(super) fn set_index(&mut self, index: usize) {
+        self.index = index;
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(super) fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    /// Get the options set for the window.
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    /// Get the options set for pane `id`.
+    pub fn pane_options_mut(&mut self, id: PaneId) -> &mut Options {
+        self.pane_options.entry(id).or_default()
+    }
+
+    /// Get the options in effect for the window, which override the ones
+    /// of its session.
+    pub fn scoped_options<'a>(&'a self, session: ScopedOptions<'a>) -> ScopedOptions<'a> {
+        session.with(Level::Window, &self.options)
+    }
+
+    /// Get the options in effect for pane `id`.
+    pub fn pane_scoped_options<'a>(&'a self, session: ScopedOptions<'a>, id: PaneId) -> ScopedOptions<'a> {
+        let options = self.scoped_options(session);
+        match self.pane_options.get(&id) {
+            Some(pane) => options.with(Level::Pane, pane),
+            None => options,
+        }
+    }
+
+    pub fn layout(&self) -> &Layout<Arc<Terminal>> {
+        &self.layout
+    }
+
+    pub fn active_pane(&self) -> PaneId {
+        self.active_pane
+    }
+
+    pub fn active_terminal(&self) -> Option<Arc<Terminal>> {
+        self.terminal(self.active_pane)
+    }
+
+    /// Get the terminal of pane `id`.
+    pub fn terminal(&self, id: PaneId) -> Option<Arc<Terminal>> {
+        self.layout.pane(id).map(|(terminal, _)| Arc::clone(terminal))
+    }
+
+    /// Get the pane at `position` in the order of the layout.
+    pub fn pane_at(&self, position: usize) -> Option<PaneId> {
+        self.layout.panes().get(position).map(|(id, _, _)| *id)
+    }
+
+    /// Get the terminals of every pane.
+    pub fn terminals(&self) -> impl Iterator<Item = &Arc<Terminal>> {
+        self.layout.panes().into_iter().map(|(_, terminal, _)| terminal)
+    }
+
+    /// Returns whether the bell rang in any pane since the window was last
+    /// active.
+    pub fn has_bell(&self) -> bool {
+        self.terminals().any(|terminal| terminal.has_bell())
+    }
+
+    /// Returns whether any pane printed output since the window was last
+    /// active.
+    pub fn has_activity(&self) -> bool {
+        self.terminals().any(|terminal| terminal.has_activity())
+    }
+
+    /// Returns whether the last pane was closed.
+    pub fn is_empty(&self) -> bool {
+        self.layout.is_empty()
+    }
+
+    /// Get the pane that shows `terminal`.
+    pub fn pane_of(&self, terminal: &Arc<Terminal>) -> Option<PaneId> {
+        self.layout.panes().into_iter()
+            .find(|(_, pane, _)| Arc::ptr_eq(pane, terminal))
+            .map(|(id, _, _)| id)
+    }
+
+    /// Focus the active pane if the window is active, which clears its
+    /// flags. The other panes keep theirs until they are focused.
+    pub(super) fn set_active(&self, active: bool) {
+        for (id, terminal, _) in self.layout.panes() {
+            if active && id == self.active_pane {
+                terminal.focus();
+            } else {
+                terminal.unfocus();
+            }
+        }
+    }
+
+    /// Split pane `id`, making the new pane the active one.
+    pub fn split<F>(&mut self, id: PaneId, direction: Direction, spawn: F) -> Result<PaneId>
+        where F: FnOnce(Point) -> Result<Arc<Terminal>>
+    {
+        let id = self.layout.split(id, direction, spawn)?;
+        self.select_pane(id);
+        Ok(id)
+    }
+
+    /// Make pane `id` the active pane, if it exists.
+    pub fn select_pane(&mut self, id: PaneId) {
+        if self.layout.pane(id).is_some() {
+            if let Some(terminal) = self.active_terminal() {
+                terminal.unfocus();
+            }
+            self.active_pane = id;
+            if let Some(terminal) = self.active_terminal() {
+                terminal.focus();
+            }
+        }
+    }
+
+    /// Make the pane next to pane `id` on `side` the active pane.
+    ///
+    /// Returns whether there is such a pane.
+    pub fn select_neighbour(&mut self, id: PaneId, side: Side) -> bool {
+        match self.layout.neighbour(id, side) {
+            Some(id) => {
+                self.select_pane(id);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Move the border of pane `id` on `side` by `count` cells towards
+    /// that side.
+    pub fn resize_pane(&mut self, id: PaneId, side: Side, count: usize) -> Result<bool> {
+        let delta = match side {
+            Side::Left | Side::Up => -(count as isize),
+            Side::Right | Side::Down => count as isize,
+        };
+        self.layout.resize_pane(id, side.direction(), delta)
+    }
+
+    /// Move the border between panes at `point` towards `to`, returning
+    /// where it is afterwards.
+    pub fn move_border(&mut self, point: Point, to: Point) -> Result<Option<Point>> {
+        self.layout.move_border(point, to)
+    }
+
+    /// Close pane `id`. When the active pane is closed, the first pane
+    /// becomes active.
+    pub(super) fn close_pane(&mut self, id: PaneId) -> Result<()> {
+        self.layout.close(id)?;
+        self.pane_options.remove(&id);
+        if id == self.active_pane {
+            if let Some((first, _, _)) = self.layout.panes().first() {
+                self.active_pane = *first;
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn resize(&mut self, size: Point) -> Result<()> {
+        self.layout.resize(size)
+    }
+}
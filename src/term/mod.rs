@@ -1,9 +1,12 @@
-mod futures;
 mod task;
 
 use {
     std::{
         io,
+        os::unix::io::{
+            AsRawFd,
+            RawFd,
+        },
         sync::{
             atomic::{
                 AtomicBool,
@@ -17,117 +20,170 @@ use {
         Result,
     },
     log::trace,
-    tokio::process::{
-        Child,
-        Command,
+    nix::{
+        sys::signal::{
+            kill,
+            Signal,
+        },
+        unistd::Pid,
+    },
+    tokio::{
+        io::AsyncWriteExt,
+        process::{
+            Child,
+            Command,
+        },
+        sync::Mutex as AsyncMutex,
     },
     crate::{
+        copy::CopyMode,
         pty::{
+            self,
             Pty,
             PtyReader,
             PtyWriter,
             WithPty,
             split,
         },
+        layout::Pane,
         screen::Screen,
         util::Point,
     },
-    self::futures as fut,
 };
 
 pub use task::terminal_task;
 
 pub struct Terminal {
-    // SAFETY: Pty operations are thread safe and so accessing them
-    // from an immutable reference is safe.
-    pub process: Mutex<Child>,
-    pub pty_reader: Mutex<PtyReader>,
-    pub pty_writer: Mutex<PtyWriter>,
+    pid: u32,
+    /// File descriptor of the pty, owned by `pty_writer`.
+    pty_fd: RawFd,
+    pub pty_writer: AsyncMutex<PtyWriter>,
     pub screen: Mutex<Screen>,
+    /// Copy mode, when the pane is in it.
+    pub copy_mode: Mutex<Option<CopyMode>>,
     pub running: AtomicBool,
-    pub dirty: AtomicBool,
+    /// Whether this terminal is the focused pane.
+    pub focused: AtomicBool,
+    /// Bell flag, set when the bell rings while the pane isn't focused.
+    pub bell: AtomicBool,
+    /// Set when the bell rings, until the renderer acts on it.
+    pub pending_bell: AtomicBool,
+    /// Activity flag, set when the process prints output.
+    pub activity: AtomicBool,
+}
+
+/// The parts of a terminal that are owned by its task: the child process
+/// and the reading half of the pty.
+pub struct TerminalProcess {
+    pub child: Child,
+    pub pty_reader: PtyReader,
 }
 
 impl Terminal {
-    pub fn spawn(mut command: Command, size: Point) -> Result<Self> {
+    pub fn spawn(mut command: Command, size: Point) -> Result<(Self, TerminalProcess)> {
         let mut pty = Pty::open().context("open pty")?;
         pty.set_size(size).context("pty set size")?;
-        let process = command
+        let child = command
             .with_pty(&pty).context("process add pty")?
             .spawn().context("spawn process")?;
+        let pty_fd = pty.as_raw_fd();
         let (pty_reader, pty_writer) = split(pty);
-        trace!("process spawned: {:?}", process);
-        Ok(Self {
-            process: Mutex::new(process),
-            pty_reader: Mutex::new(pty_reader),
-            pty_writer: Mutex::new(pty_writer),
+        trace!("process spawned: {:?}", child);
+        let terminal = Self {
+            pid: child.id(),
+            pty_fd,
+            pty_writer: AsyncMutex::new(pty_writer),
             screen: Mutex::new(Screen::new(size)),
+            copy_mode: Mutex::new(None),
             running: AtomicBool::new(true),
-            dirty: AtomicBool::new(true),
-        })
+            focused: AtomicBool::new(false),
+            bell: AtomicBool::new(false),
+            pending_bell: AtomicBool::new(false),
+            activity: AtomicBool::new(false),
+        };
+        Ok((terminal, TerminalProcess { child, pty_reader }))
     }
 
-    pub fn alive(&self) -> bool {
-        self.running.load(Ordering::SeqCst)
+    /// Resize the screen and the pty, which sends SIGWINCH to the process.
+    pub fn resize(&self, size: Point) -> Result<()> {
+        self.screen.lock().unwrap().resize(size);
+        if let Some(copy_mode) = self.copy_mode.lock().unwrap().as_mut() {
+            copy_mode.resize(size);
+        }
+        pty::set_size(self.pty_fd, size)
     }
 
-    pub fn kill(&self) -> io::Result<()> {
-        let mut process = self.process.lock().unwrap();
-        self.running.store(false, Ordering::SeqCst);
-        process.kill()
+    /// Focus this terminal, clearing its flags.
+    pub fn focus(&self) {
+        self.focused.store(true, Ordering::SeqCst);
+        self.clear_flags();
     }
 
-    pub fn wait<'a>(&'a self) -> fut::Wait<'a> {
-        fut::Wait {
-            terminal: self,
-        }
+    pub fn unfocus(&self) {
+        self.focused.store(false, Ordering::SeqCst);
     }
 
-    pub fn pty_read<'a>(&'a self, buf: &'a mut [u8]) -> fut::Read<'a> {
-        fut::Read {
-            terminal: self,
-            buf,
+    /// Ring the bell of this terminal.
+    pub fn ring_bell(&self) {
+        if !self.focused.load(Ordering::SeqCst) {
+            self.bell.store(true, Ordering::SeqCst);
         }
+        self.pending_bell.store(true, Ordering::SeqCst);
     }
 
-    pub fn pty_write<'a>(&'a self, buf: &'a [u8]) -> fut::Write<'a> {
-        fut::Write {
-            terminal: self,
-            buf,
+    /// Record output of the terminal, which sets the activity flag unless
+    /// it is focused.
+    pub fn set_activity(&self) {
+        if !self.focused.load(Ordering::SeqCst) {
+            self.activity.store(true, Ordering::SeqCst);
         }
     }
 
-    pub fn pty_flush<'a>(&'a self) -> fut::Flush<'a> {
-        fut::Flush {
-            terminal: self,
-        }
+    /// Returns whether the bell rang since the last call, and clears it.
+    pub fn take_pending_bell(&self) -> bool {
+        self.pending_bell.swap(false, Ordering::SeqCst)
+    }
+
+    /// Returns whether the bell flag is set.
+    pub fn has_bell(&self) -> bool {
+        self.bell.load(Ordering::SeqCst)
     }
 
-    pub fn pty_shutdown<'a>(&'a self) -> fut::Shutdown<'a> {
-        fut::Shutdown {
-            terminal: self,
+    /// Returns whether the activity flag is set.
+    pub fn has_activity(&self) -> bool {
+        self.activity.load(Ordering::SeqCst)
+    }
+
+    /// Clear the bell and activity flags.
+    pub fn clear_flags(&self) {
+        self.bell.store(false, Ordering::SeqCst);
+        self.activity.store(false, Ordering::SeqCst);
+    }
+
+    pub fn alive(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Kill the child, unless it was already reaped and its pid may belong
+    /// to another process.
+    pub fn kill(&self) -> io::Result<()> {
+        if !self.running.swap(false, Ordering::SeqCst) {
+            return Ok(());
         }
+        kill(Pid::from_raw(self.pid as i32), Signal::SIGKILL)
+            .map_err(io::Error::other)
+    }
+
+    /// Write the whole buffer to the pty.
+    pub async fn pty_write(&self, buf: &[u8]) -> io::Result<()> {
+        let mut pty_writer = self.pty_writer.lock().await;
+        pty_writer.write_all(buf).await?;
+        pty_writer.flush().await
     }
+}
 
-    // pub async fn pty_read(&self, buf: &mut [u8]) -> io::Result<usize> {
-        // let mut pty_reader = self.pty_reader.lock().await;
-        // pty_reader.read(buf).await
-    // }
-//
-    // pub async fn pty_write(&self, buf: &[u8]) -> io::Result<usize> {
-        // let mut pty_writer = self.pty_writer.lock().await;
-        // let n = pty_writer.write(buf).await?;
-        // pty_writer.flush().await?;
-        // Ok(n)
-    // }
-//
-    // pub async fn pty_flush(&self) -> io::Result<()> {
-        // let mut pty_writer = self.pty_writer.lock().await;
-        // pty_writer.flush().await
-    // }
-//
-    // pub async fn pty_shutdown(&self) -> io::Result<()> {
-        // let mut pty_writer = self.pty_writer.lock().await;
-        // pty_writer.shutdown().await
-    // }
+impl Pane for Terminal {
+    fn resize(&self, size: Point) -> Result<()> {
+        Terminal::resize(self, size)
+    }
 }
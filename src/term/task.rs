@@ -1,32 +1,55 @@
 use {
     std::{
         io,
-        pin::Pin,
         process::ExitStatus,
         sync::atomic::Ordering,
-    },
-    futures::{
-        task::{
-            Context,
-            Poll,
+        time::{
+            Duration,
+            Instant,
         },
-        Future,
-        FutureExt,
     },
     log::trace,
+    tokio::{
+        io::AsyncReadExt,
+        time::{
+            delay_until,
+            timeout,
+        },
+    },
     crate::{
-        ansi::Processor,
+        ansi::{
+            Handler,
+            Processor,
+            TerminalMode,
+        },
+        pty::PtyReader,
         screen::renderer::ScreenRendererNotifier,
     },
-    super::Terminal,
+    super::{
+        Terminal,
+        TerminalProcess,
+    },
 };
 
+/// Longest time that renderer notifications are held back while the
+/// application has synchronized output enabled.
+const SYNC_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Size of the buffer used to read from the pty. Output is parsed as fast
+/// as it arrives, the renderer decides how often it's drawn.
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// How long to keep draining the pty after the child exits. Background
+/// processes may keep the pty open, so we can't wait for it to close.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(100);
+
 struct TerminalTask<'a> {
     terminal: &'a Terminal,
     notifier: ScreenRendererNotifier,
     processor: Processor,
-    must_notify: bool,
-    buf: [u8; 512],
+    /// Deadline for the synchronized update in progress, if any.
+    sync_deadline: Option<Instant>,
+    buf: [u8; READ_BUFFER_SIZE],
 }
 
 impl<'a> TerminalTask<'a> {
@@ -35,88 +58,129 @@ impl<'a> TerminalTask<'a> {
             terminal,
             notifier,
             processor: Processor::default(),
-            must_notify: true,
-            buf: [0; 512],
+            sync_deadline: None,
+            buf: [0; READ_BUFFER_SIZE],
         }
     }
-}
 
-impl<'a> Future for TerminalTask<'a> {
-    type Output = io::Result<ExitStatus>;
+    /// Parse `n` bytes of the buffer and notify the renderer.
+    async fn advance(&mut self, n: usize) -> io::Result<()> {
+        trace!("read {} bytes from pty", n);
+        let mut replies = Vec::new();
+        {
+            let mut screen = self.terminal.screen.lock().unwrap();
+            self.processor.advance(&self.buf[..n], &mut *screen, &mut replies);
+            if screen.take_bell() {
+                self.terminal.ring_bell();
+            }
+            self.terminal.set_activity();
+            // Hold back the frame until the application ends the
+            // synchronized update, or it takes too long.
+            if screen.mode(TerminalMode::SynchronizedOutput) {
+                if self.sync_deadline.is_none() {
+                    self.sync_deadline = Some(Instant::now() + SYNC_TIMEOUT);
+                }
+            } else {
+                self.sync_deadline = None;
+                self.notifier.notify();
+            }
+        }
+        if !replies.is_empty() {
+            self.terminal.pty_write(&replies).await?;
+        }
+        Ok(())
+    }
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let Self {
-            ref terminal,
-            ref mut notifier,
-            ref mut processor,
-            ref mut must_notify,
-            ref mut buf,
-        } = *self;
-        if *must_notify {
-            let mut notify = notifier.notify().boxed_local();
-            match notify.poll_unpin(cx) {
-                Poll::Ready(_) => {
-                    *must_notify = false;
+    /// Read and parse the pty output until the pty is closed or the child
+    /// exits, whichever happens first.
+    ///
+    /// Returns the exit status if the child exited.
+    async fn read_loop(
+        &mut self,
+        process: &mut TerminalProcess,
+    ) -> io::Result<Option<ExitStatus>> {
+        loop {
+            // `delay_until` is only polled when there is a deadline.
+            let sync_deadline = self.sync_deadline.unwrap_or_else(Instant::now);
+            tokio::select! {
+                res = process.pty_reader.read(&mut self.buf[..]) => match read_result(res)? {
+                    0 => return Ok(None),
+                    n => self.advance(n).await?,
+                },
+                status = &mut process.child => {
+                    return status.map(Some);
                 },
-                Poll::Pending => return Poll::Pending,
-            };
+                _ = delay_until(sync_deadline.into()), if self.sync_deadline.is_some() => {
+                    trace!("synchronized output timed out");
+                    self.sync_deadline = None;
+                    self.notifier.notify();
+                },
+            }
         }
-        let mut wait = terminal.wait();
-        trace!("polling process");
-        let res = match Pin::new(&mut wait).poll(cx) {
-            Poll::Ready(exit_status) => {
-                trace!("process finished");
-                terminal.running.store(false, Ordering::SeqCst);
-                Poll::Ready(exit_status)
-            },
-            Poll::Pending => {
-                trace!("process still running");
-                // If we don't wake this here, the runtime doesn't polls
-                // this future again until the child exits.
-                cx.waker().wake_by_ref();
-                Poll::Pending
-            },
-        };
-        if let Poll::Ready(stat) = res {
-            return Poll::Ready(stat);
+    }
+
+    /// Parse whatever the child wrote before exiting.
+    async fn drain(&mut self, pty_reader: &mut PtyReader) -> io::Result<()> {
+        loop {
+            let res = timeout(DRAIN_TIMEOUT, pty_reader.read(&mut self.buf[..])).await;
+            match res {
+                Ok(res) => match read_result(res)? {
+                    0 => return Ok(()),
+                    n => self.advance(n).await?,
+                },
+                Err(_) => return Ok(()),
+            }
         }
+    }
 
-        let mut read = terminal.pty_read(&mut buf[..]);
-        trace!("polling pty reader");
-        let res = match Pin::new(&mut read).poll(cx) {
-            Poll::Ready(res) => {
-                match res {
-                    Ok(n) => {
-                        trace!("read {} bytes from pty", n);
-                        let mut screen = terminal.screen.lock().unwrap();
-                        let mut pty = terminal.pty_writer.lock().unwrap();
-                        processor.advance(
-                            &buf[..n],
-                            &mut *screen,
-                            &mut *pty,
-                        );
-                        *must_notify = true;
-                        // Wake so that we poll again and trigger the notifier.
-                        cx.waker().wake_by_ref();
-                        Poll::Pending
-                    },
-                    Err(e) => {
-                        trace!("error reading from pty: {}", e);
-                        terminal.kill()?;
-                        Poll::Ready(Err(e))
-                    }
-                }
+    /// Run the terminal until its child exits.
+    ///
+    /// The task ends when both the child has exited and the pty is closed
+    /// or idle. If the pty is closed first, the child is waited for; if
+    /// the child exits first, the pty is drained of its remaining output.
+    async fn run(mut self, mut process: TerminalProcess) -> io::Result<ExitStatus> {
+        let res = self.read_loop(&mut process).await;
+        let status = match res {
+            Ok(Some(status)) => {
+                // The pid may be reused once the child is reaped, so it
+                // must not be killed anymore.
+                self.terminal.running.store(false, Ordering::SeqCst);
+                trace!("process finished, draining pty");
+                self.drain(&mut process.pty_reader).await?;
+                status
+            },
+            Ok(None) => {
+                trace!("pty closed, waiting for process");
+                let status = (&mut process.child).await;
+                self.terminal.running.store(false, Ordering::SeqCst);
+                status?
             },
-            Poll::Pending => {
-                trace!("pty reader still pending");
-                Poll::Pending
+            Err(e) => {
+                trace!("error reading from pty: {}", e);
+                self.terminal.kill()?;
+                // Reap the killed child so that it doesn't stay a zombie.
+                (&mut process.child).await?;
+                return Err(e);
             },
         };
+        self.notifier.notify();
+        Ok(status)
+    }
+}
 
-        res
+/// Map the result of a pty read, treating EIO as the end of the stream.
+/// Linux returns EIO on the master side once every slave fd is closed.
+fn read_result(res: io::Result<usize>) -> io::Result<usize> {
+    match res {
+        Err(e) if e.raw_os_error() == Some(libc::EIO) => Ok(0),
+        res => res,
     }
 }
 
-pub async fn terminal_task(terminal: &Terminal, notifier: ScreenRendererNotifier) -> io::Result<ExitStatus> {
-    TerminalTask::new(terminal, notifier).await
+pub async fn terminal_task(
+    terminal: &Terminal,
+    process: TerminalProcess,
+    notifier: ScreenRendererNotifier,
+) -> io::Result<ExitStatus> {
+    TerminalTask::new(terminal, notifier).run(process).await
 }
@@ -1,3 +1,4 @@
 pub mod point;
+pub mod regex;
 
 pub use point::{Point, P};
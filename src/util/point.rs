@@ -11,7 +11,7 @@ use std::{
     }
 };
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, PartialOrd)]
 pub struct Point {
     // NOTE `y` has to go before `x` so that it has higher
     // priority when deriving PartialOrd
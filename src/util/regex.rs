@@ -0,0 +1,480 @@
+use {
+    std::{
+        iter::Peekable,
+        str::Chars,
+    },
+    anyhow::{
+        anyhow,
+        Result,
+    },
+};
+
+/// A set of characters given by `[...]` or an escape like `\d`.
+#[derive(Clone, Debug, PartialEq)]
+struct Class {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl Class {
+    fn new(ranges: &[(char, char)], negated: bool) -> Self {
+        Self {
+            ranges: ranges.to_vec(),
+            negated,
+        }
+    }
+
+    fn matches(&self, ch: char) -> bool {
+        self.ranges.iter().any(|&(start, end)| start <= ch && ch <= end) != self.negated
+    }
+}
+
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[('\t', '\r'), (' ', ' ')];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Char(char),
+    /// Any character, `.`.
+    Any,
+    Class(Class),
+    /// Start of the text, `^`.
+    Start,
+    /// End of the text, `$`.
+    End,
+    /// Boundary between a word character and another character, `\b`.
+    WordBoundary,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+/// A regular expression, matched in time linear in the length of the
+/// text by running all the ways to match it side by side.
+///
+/// It supports the common syntax: `.`, `[...]` classes, the `\d`, `\w`
+/// and `\s` escapes and their negations, `^`, `$`, `\b`, groups,
+/// alternation and the `*`, `+`, `?` and `{n,m}` greedy quantifiers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regex {
+    program: Vec<Inst>,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let mut chars = pattern.chars().peekable();
+        let alternatives = parse_alternatives(&mut chars)?;
+        match chars.next() {
+            Some(ch) => Err(anyhow!("unmatched {}", ch)),
+            None => Ok(Self::compile(&alternatives)),
+        }
+    }
+
+    /// Get a regular expression that matches `text` as it is.
+    pub fn literal(text: &str) -> Self {
+        Self::compile(&[text.chars().map(Node::Char).collect()])
+    }
+
+    fn compile(alternatives: &[Vec<Node>]) -> Self {
+        let mut program = Vec::new();
+        compile_alternatives(&mut program, alternatives);
+        program.push(Inst::Match);
+        Self { program }
+    }
+
+    /// Find the leftmost match starting at or after `start`, returning its
+    /// range of characters.
+    ///
+    /// Among the matches starting there, it prefers the one a backtracking
+    /// matcher would find: earlier alternatives and longer repetitions
+    /// first.
+    pub fn find_at(&self, text: &[char], start: usize) -> Option<(usize, usize)> {
+        let mut threads = Threads {
+            program: &self.program,
+            text,
+            added: vec![usize::MAX; self.program.len()],
+        };
+        let mut current = Vec::new();
+        let mut next = Vec::new();
+        let mut found = None;
+        for position in start..=text.len() {
+            // A match starting here has a lower priority than the ones
+            // started before.
+            if found.is_none() {
+                threads.add(&mut current, 0, position, position);
+            }
+            let ch = text.get(position).copied();
+            for &Thread { pc, start } in &current {
+                let matched = match &self.program[pc] {
+                    Inst::Char(expected) => ch == Some(*expected),
+                    Inst::Any => ch.is_some(),
+                    Inst::Class(class) => ch.is_some_and(|ch| class.matches(ch)),
+                    Inst::Match => {
+                        // Threads after this one have a lower priority.
+                        found = Some((start, position));
+                        break;
+                    },
+                    _ => unreachable!("only consuming instructions are queued"),
+                };
+                if matched {
+                    threads.add(&mut next, pc + 1, start, position + 1);
+                }
+            }
+            current.clear();
+            std::mem::swap(&mut current, &mut next);
+            if found.is_some() && current.is_empty() {
+                break;
+            }
+        }
+        found
+    }
+
+    /// Find every match that isn't empty, from left to right without
+    /// overlaps.
+    pub fn find_all(&self, text: &[char]) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+        let mut start = 0;
+        while let Some((match_start, match_end)) = self.find_at(text, start) {
+            if match_end > match_start {
+                matches.push((match_start, match_end));
+                start = match_end;
+            } else {
+                start = match_start + 1;
+            }
+            if start > text.len() {
+                break;
+            }
+        }
+        matches
+    }
+}
+
+fn parse_alternatives(chars: &mut Peekable<Chars>) -> Result<Vec<Vec<Node>>> {
+    let mut alternatives = vec![parse_sequence(chars)?];
+    while chars.peek() == Some(&'|') {
+        chars.next();
+        alternatives.push(parse_sequence(chars)?);
+    }
+    Ok(alternatives)
+}
+
+fn parse_sequence(chars: &mut Peekable<Chars>) -> Result<Vec<Node>> {
+    let mut sequence = Vec::new();
+    while let Some(&ch) = chars.peek() {
+        if ch == '|' || ch == ')' {
+            break;
+        }
+        chars.next();
+        let node = match ch {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '(' => {
+                let group = parse_alternatives(chars)?;
+                if chars.next() != Some(')') {
+                    return Err(anyhow!("missing )"));
+                }
+                Node::Group(group)
+            },
+            '[' => Node::Class(parse_class(chars)?),
+            '\\' => parse_escape(chars)?,
+            '*' | '+' | '?' | '{' => return Err(anyhow!("nothing to repeat before {}", ch)),
+            ch => Node::Char(ch),
+        };
+        let node = parse_quantifier(chars, node)?;
+        sequence.push(node);
+    }
+    Ok(sequence)
+}
+
+fn parse_quantifier(chars: &mut Peekable<Chars>, node: Node) -> Result<Node> {
+    let (min, max) = match chars.peek() {
+        Some('*') => (0, None),
+        Some('+') => (1, None),
+        Some('?') => (0, Some(1)),
+        Some('{') => {
+            chars.next();
+            let mut bounds = String::new();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(ch) => bounds.push(ch),
+                    None => return Err(anyhow!("missing }}")),
+                }
+            }
+            let number = |text: &str| text.trim().parse::<usize>()
+                .map_err(|_| anyhow!("invalid repetition: {{{}}}", bounds));
+            let (min, max) = match bounds.find(',') {
+                Some(comma) if comma + 1 == bounds.len() => (number(&bounds[..comma])?, None),
+                Some(comma) => (number(&bounds[..comma])?, Some(number(&bounds[comma + 1..])?)),
+                None => {
+                    let count = number(&bounds)?;
+                    (count, Some(count))
+                },
+            };
+            if max.is_some_and(|max| max < min) {
+                return Err(anyhow!("invalid repetition: {{{}}}", bounds));
+            }
+            return parse_quantifier(chars, Node::Repeat { node: Box::new(node), min, max });
+        },
+        _ => return Ok(node),
+    };
+    chars.next();
+    parse_quantifier(chars, Node::Repeat { node: Box::new(node), min, max })
+}
+
+fn parse_escape(chars: &mut Peekable<Chars>) -> Result<Node> {
+    let node = match chars.next().ok_or_else(|| anyhow!("trailing \\"))? {
+        'd' => Node::Class(Class::new(DIGIT, false)),
+        'D' => Node::Class(Class::new(DIGIT, true)),
+        'w' => Node::Class(Class::new(WORD, false)),
+        'W' => Node::Class(Class::new(WORD, true)),
+        's' => Node::Class(Class::new(SPACE, false)),
+        'S' => Node::Class(Class::new(SPACE, true)),
+        'b' => Node::WordBoundary,
+        't' => Node::Char('\t'),
+        ch if ch.is_alphanumeric() => return Err(anyhow!("unknown escape: \\{}", ch)),
+        ch => Node::Char(ch),
+    };
+    Ok(node)
+}
+
+fn parse_class(chars: &mut Peekable<Chars>) -> Result<Class> {
+    let negated = chars.peek() == Some(&'^');
+    if negated {
+        chars.next();
+    }
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let start = match chars.next() {
+            None => return Err(anyhow!("missing ]")),
+            // A `]` right after the `[` is a member of the class.
+            Some(']') if !first => break,
+            Some('\\') => match chars.next() {
+                Some('d') => {
+                    ranges.extend_from_slice(DIGIT);
+                    first = false;
+                    continue;
+                },
+                Some('w') => {
+                    ranges.extend_from_slice(WORD);
+                    first = false;
+                    continue;
+                },
+                Some('s') => {
+                    ranges.extend_from_slice(SPACE);
+                    first = false;
+                    continue;
+                },
+                Some('t') => '\t',
+                Some(ch) => ch,
+                None => return Err(anyhow!("missing ]")),
+            },
+            Some(ch) => ch,
+        };
+        first = false;
+        let mut lookahead = chars.clone();
+        if lookahead.next() == Some('-') && lookahead.peek().is_some_and(|&ch| ch != ']') {
+            chars.next();
+            let end = chars.next().unwrap();
+            if end < start {
+                return Err(anyhow!("invalid range: {}-{}", start, end));
+            }
+            ranges.push((start, end));
+        } else {
+            ranges.push((start, start));
+        }
+    }
+    Ok(Class { ranges, negated })
+}
+
+/// An instruction of a compiled expression.
+#[derive(Clone, Debug, PartialEq)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    WordBoundary,
+    /// Continue at both targets, preferring the first.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+fn compile_alternatives(program: &mut Vec<Inst>, alternatives: &[Vec<Node>]) {
+    let mut jumps = Vec::new();
+    for (index, sequence) in alternatives.iter().enumerate() {
+        if index + 1 == alternatives.len() {
+            compile_sequence(program, sequence);
+            break;
+        }
+        let split = program.len();
+        program.push(Inst::Split(split + 1, 0));
+        compile_sequence(program, sequence);
+        jumps.push(program.len());
+        program.push(Inst::Jump(0));
+        program[split] = Inst::Split(split + 1, program.len());
+    }
+    let end = program.len();
+    for jump in jumps {
+        program[jump] = Inst::Jump(end);
+    }
+}
+
+fn compile_sequence(program: &mut Vec<Inst>, sequence: &[Node]) {
+    for node in sequence {
+        compile_node(program, node);
+    }
+}
+
+fn compile_node(program: &mut Vec<Inst>, node: &Node) {
+    match node {
+        Node::Char(ch) => program.push(Inst::Char(*ch)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(class) => program.push(Inst::Class(class.clone())),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::WordBoundary => program.push(Inst::WordBoundary),
+        Node::Group(alternatives) => compile_alternatives(program, alternatives),
+        Node::Repeat { node, min, max } => {
+            for _ in 0..*min {
+                compile_node(program, node);
+            }
+            match max {
+                Some(max) => {
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        compile_node(program, node);
+                    }
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, end);
+                    }
+                },
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(0, 0));
+                    compile_node(program, node);
+                    program.push(Inst::Jump(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                },
+            }
+        },
+    }
+}
+
+/// A way to match the expression, waiting at a consuming instruction.
+#[derive(Clone, Copy, Debug)]
+struct Thread {
+    pc: usize,
+    start: usize,
+}
+
+struct Threads<'a> {
+    program: &'a [Inst],
+    text: &'a [char],
+    /// The position each instruction was last queued at, so a thread is
+    /// only queued once per position and empty loops end.
+    added: Vec<usize>,
+}
+
+impl<'a> Threads<'a> {
+    /// Queue the thread at `pc` in priority order, following the
+    /// instructions that don't consume a character.
+    fn add(&mut self, list: &mut Vec<Thread>, pc: usize, start: usize, position: usize) {
+        if self.added[pc] == position {
+            return;
+        }
+        self.added[pc] = position;
+        let text = self.text;
+        match self.program[pc] {
+            Inst::Split(first, second) => {
+                self.add(list, first, start, position);
+                self.add(list, second, start, position);
+            },
+            Inst::Jump(target) => self.add(list, target, start, position),
+            Inst::Start if position == 0 => self.add(list, pc + 1, start, position),
+            Inst::End if position == text.len() => self.add(list, pc + 1, start, position),
+            Inst::Start | Inst::End => (),
+            Inst::WordBoundary => {
+                let word = Class::new(WORD, false);
+                let before = position > 0 && word.matches(text[position - 1]);
+                let after = text.get(position).is_some_and(|&ch| word.matches(ch));
+                if before != after {
+                    self.add(list, pc + 1, start, position);
+                }
+            },
+            _ => list.push(Thread { pc, start }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Regex;
+
+    fn find_all(pattern: &str, text: &str) -> Vec<String> {
+        let text: Vec<char> = text.chars().collect();
+        Regex::new(pattern).unwrap()
+            .find_all(&text)
+            .into_iter()
+            .map(|(start, end)| text[start..end].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_match() {
+        assert_eq!(find_all("error", "error: no error"), vec!["error", "error"]);
+        assert_eq!(find_all("e.r", "ear err"), vec!["ear", "err"]);
+        assert_eq!(find_all("a+b*", "aab ab b aaa"), vec!["aab", "ab", "aaa"]);
+        assert_eq!(find_all("colou?r", "color colour colouur"), vec!["color", "colour"]);
+        assert_eq!(find_all("[0-9]+:[0-9]+", "main.rs:12:5"), vec!["12:5"]);
+        assert_eq!(find_all("\\d{2,3}", "1 22 4444"), vec!["22", "444"]);
+        assert_eq!(find_all("[^a-z ]+", "abc DEF ghi 12"), vec!["DEF", "12"]);
+        assert_eq!(find_all("(warn|error)ing", "warning erroring"), vec!["warning", "erroring"]);
+        assert_eq!(find_all("^\\w+", "fn main"), vec!["fn"]);
+        assert_eq!(find_all("\\w+$", "fn main"), vec!["main"]);
+        assert_eq!(find_all("\\bin\\b", "in main in"), vec!["in", "in"]);
+        assert_eq!(find_all("a.*b", "a1b2b3"), vec!["a1b2b"]);
+        assert_eq!(find_all("x*", "abc"), Vec::<String>::new());
+        assert_eq!(find_all("\\.rs", "a.rs ars"), vec![".rs"]);
+        assert_eq!(find_all("[]x]", "a]x"), vec!["]", "x"]);
+        assert_eq!(find_all("[\\d]+", "a12]"), vec!["12"]);
+        assert_eq!(find_all("[\\w-]+", "a-b c]"), vec!["a-b", "c"]);
+    }
+
+    #[test]
+    fn test_long_line() {
+        let line = format!("a{}b", "x".repeat(200_000));
+        let text: Vec<char> = line.chars().collect();
+        assert_eq!(Regex::new("a.*b").unwrap().find_all(&text), vec![(0, text.len())]);
+        assert_eq!(Regex::new("x+").unwrap().find_all(&text), vec![(1, text.len() - 1)]);
+        assert_eq!(Regex::new("(x|y)*z").unwrap().find_all(&text), vec![]);
+    }
+
+    #[test]
+    fn test_literal() {
+        let text: Vec<char> = "a.*b a.*b".chars().collect();
+        assert_eq!(Regex::literal("a.*").find_all(&text), vec![(0, 3), (5, 8)]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |pattern| Regex::new(pattern).unwrap_err().to_string();
+        assert_eq!(error("(a"), "missing )");
+        assert_eq!(error("a)"), "unmatched )");
+        assert_eq!(error("[a"), "missing ]");
+        assert_eq!(error("*a"), "nothing to repeat before *");
+        assert_eq!(error("a{2,1}"), "invalid repetition: {2,1}");
+        assert_eq!(error("\\q"), "unknown escape: \\q");
+    }
+}